@@ -171,6 +171,7 @@ async fn handle_manual_provider_setup(config: &Config) {
             );
             set_extension(ExtensionEntry {
                 enabled: true,
+                depends_on: Vec::new(),
                 config: ExtensionConfig::default(),
             });
         }
@@ -948,6 +949,7 @@ fn configure_builtin_extension() -> anyhow::Result<()> {
 
     set_extension(ExtensionEntry {
         enabled: true,
+        depends_on: Vec::new(),
         config: ExtensionConfig::Builtin {
             name: extension.clone(),
             display_name: Some(display_name),
@@ -987,6 +989,7 @@ fn configure_stdio_extension() -> anyhow::Result<()> {
 
     set_extension(ExtensionEntry {
         enabled: true,
+        depends_on: Vec::new(),
         config: ExtensionConfig::Stdio {
             name: name.clone(),
             cmd,
@@ -1030,6 +1033,7 @@ fn configure_streamable_http_extension() -> anyhow::Result<()> {
 
     set_extension(ExtensionEntry {
         enabled: true,
+        depends_on: Vec::new(),
         config: ExtensionConfig::StreamableHttp {
             name: name.clone(),
             uri,
@@ -1693,6 +1697,7 @@ pub async fn handle_openrouter_auth() -> anyhow::Result<()> {
                     if !has_developer {
                         set_extension(ExtensionEntry {
                             enabled: true,
+                            depends_on: Vec::new(),
                             config: ExtensionConfig::Builtin {
                                 name: "developer".to_string(),
                                 display_name: Some(goose::config::DEFAULT_DISPLAY_NAME.to_string()),
@@ -1763,6 +1768,7 @@ pub async fn handle_tetrate_auth() -> anyhow::Result<()> {
                     if !has_developer {
                         set_extension(ExtensionEntry {
                             enabled: true,
+                            depends_on: Vec::new(),
                             config: ExtensionConfig::Builtin {
                                 name: "developer".to_string(),
                                 display_name: Some(goose::config::DEFAULT_DISPLAY_NAME.to_string()),
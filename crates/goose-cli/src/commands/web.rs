@@ -585,7 +585,8 @@ async fn process_message_streaming(
                     Ok(AgentEvent::HistoryReplaced(_)) => {
                         tracing::info!("History replaced, compacting happened in reply");
                     }
-                    Ok(AgentEvent::McpNotification(_)) => {
+                    Ok(AgentEvent::McpNotification(_))
+                    | Ok(AgentEvent::ExtensionNotification(_)) => {
                         tracing::info!("Received MCP notification in web interface");
                     }
                     Ok(AgentEvent::ModelChange { model, mode }) => {
@@ -76,6 +76,11 @@ enum StreamEvent {
         #[serde(flatten)]
         data: NotificationData,
     },
+    /// Sent by an extension outside any tool request, e.g. when it crashes
+    ExtensionNotification {
+        extension: Option<String>,
+        notification: ServerNotification,
+    },
     ModelChange {
         model: String,
         mode: String,
@@ -1026,6 +1031,17 @@ impl CliSession {
                                 self.debug,
                             );
                         }
+                        Some(Ok(AgentEvent::ExtensionNotification((extension, notification)))) => {
+                            if is_stream_json_mode {
+                                emit_stream_event(&StreamEvent::ExtensionNotification { extension, notification });
+                            } else if self.debug {
+                                eprintln!(
+                                    "Notification from {}: {:?}",
+                                    extension.as_deref().unwrap_or("an extension"),
+                                    notification
+                                );
+                            }
+                        }
                         Some(Ok(AgentEvent::HistoryReplaced(updated_conversation))) => {
                             self.messages = updated_conversation;
                         }
@@ -286,10 +286,13 @@ pub async fn add_extension(
     let extensions = goose::config::get_all_extensions();
     let key = goose::config::extensions::name_to_key(&extension_query.name);
 
-    let is_update = extensions.iter().any(|e| e.config.key() == key);
+    let existing = extensions.iter().find(|e| e.config.key() == key);
+    let is_update = existing.is_some();
 
     goose::config::set_extension(ExtensionEntry {
         enabled: extension_query.enabled,
+        // The UI doesn't edit dependencies, so keep whatever the config already declares
+        depends_on: existing.map(|e| e.depends_on.clone()).unwrap_or_default(),
         config: extension_query.config,
     });
 
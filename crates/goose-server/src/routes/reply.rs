@@ -143,6 +143,13 @@ pub enum MessageEvent {
         #[schema(value_type = Object)]
         message: ServerNotification,
     },
+    /// A notification an extension sent outside any tool request, such as a crash, an
+    /// update to a watched resource, or a chunk of a streamed resource
+    ExtensionNotification {
+        extension: Option<String>,
+        #[schema(value_type = Object)]
+        message: ServerNotification,
+    },
     UpdateConversation {
         conversation: Conversation,
     },
@@ -365,6 +372,12 @@ pub async fn reply(
                                 message: n,
                             }, &tx, &cancel_token).await;
                         }
+                        Ok(Some(Ok(AgentEvent::ExtensionNotification((extension, n))))) => {
+                            stream_event(MessageEvent::ExtensionNotification {
+                                extension,
+                                message: n,
+                            }, &tx, &cancel_token).await;
+                        }
 
                         Ok(Some(Err(e))) => {
                             tracing::error!("Error processing message: {}", e);
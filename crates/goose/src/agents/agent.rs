@@ -56,7 +56,7 @@ use rmcp::model::{
     ServerNotification, Tool,
 };
 use serde_json::Value;
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::{broadcast, mpsc, Mutex};
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, instrument, warn};
 
@@ -134,13 +134,27 @@ pub struct Agent {
     pub(super) retry_manager: RetryManager,
     pub(super) tool_inspection_manager: ToolInspectionManager,
     container: Mutex<Option<Container>>,
+    /// Extension lifecycle events, such as a crash, passed on to the client
+    lifecycle_events: Mutex<broadcast::Receiver<(String, ServerNotification)>>,
+    /// Held for the agent's lifetime so resource watches outlive the call that started them
+    resource_updates: Mutex<broadcast::Receiver<(String, ServerNotification)>>,
+    /// Chunks of streamed resources, paired with the extension they were requested from
+    resource_chunks: Mutex<mpsc::Receiver<(Option<String>, ServerNotification)>>,
 }
 
 #[derive(Clone, Debug)]
 pub enum AgentEvent {
     Message(Message),
+    /// A notification sent while serving the tool request with this id
     McpNotification((String, ServerNotification)),
-    ModelChange { model: String, mode: String },
+    /// A notification an extension sent outside any tool request: a lifecycle event such
+    /// as a crash, an update to a watched resource, or a chunk of a streamed resource.
+    /// Paired with the extension it came from, when known.
+    ExtensionNotification((Option<String>, ServerNotification)),
+    ModelChange {
+        model: String,
+        mode: String,
+    },
     HistoryReplaced(Conversation),
 }
 
@@ -206,10 +220,18 @@ impl Agent {
 
         let session_manager = Arc::clone(&config.session_manager);
         let permission_manager = Arc::clone(&config.permission_manager);
+        let extension_manager = Arc::new(ExtensionManager::new(provider.clone(), session_manager));
+        let lifecycle_events = Mutex::new(extension_manager.subscribe_lifecycle());
+        let resource_updates = Mutex::new(extension_manager.subscribe_resource_updates());
+        let resource_chunks = Mutex::new(
+            extension_manager
+                .take_resource_chunks()
+                .expect("a new extension manager still has its chunk receiver"),
+        );
         Self {
             provider: provider.clone(),
             config,
-            extension_manager: Arc::new(ExtensionManager::new(provider.clone(), session_manager)),
+            extension_manager,
             sub_recipes: Mutex::new(HashMap::new()),
             final_output_tool: Arc::new(Mutex::new(None)),
             frontend_tools: Mutex::new(HashMap::new()),
@@ -222,6 +244,9 @@ impl Agent {
             retry_manager: RetryManager::new(),
             tool_inspection_manager: Self::create_tool_inspection_manager(permission_manager),
             container: Mutex::new(None),
+            lifecycle_events,
+            resource_updates,
+            resource_chunks,
         }
     }
 
@@ -301,6 +326,36 @@ impl Agent {
         messages
     }
 
+    /// Notifications extensions sent outside a tool call since the last drain: lifecycle
+    /// events such as a crash, updates from watched resources, and chunks of streamed
+    /// resources, each paired with the extension it comes from. Taking the chunks makes room
+    /// for paused streams to go on.
+    async fn drain_extension_notifications(&self) -> Vec<(Option<String>, ServerNotification)> {
+        let mut notifications = Vec::new();
+        for (receiver, kind) in [
+            (&self.lifecycle_events, "lifecycle events"),
+            (&self.resource_updates, "resource updates"),
+        ] {
+            let mut receiver = receiver.lock().await;
+            loop {
+                match receiver.try_recv() {
+                    Ok((extension, notification)) => {
+                        notifications.push((Some(extension), notification))
+                    }
+                    Err(broadcast::error::TryRecvError::Lagged(skipped)) => {
+                        warn!("Dropped {} {} that were not read in time", skipped, kind);
+                    }
+                    Err(_) => break,
+                }
+            }
+        }
+        let mut resource_chunks = self.resource_chunks.lock().await;
+        while let Ok(chunk) = resource_chunks.try_recv() {
+            notifications.push(chunk);
+        }
+        notifications
+    }
+
     async fn prepare_reply_context(
         &self,
         session_id: &str,
@@ -1147,6 +1202,10 @@ impl Agent {
                     break;
                 }
 
+                for notification in self.drain_extension_notifications().await {
+                    yield AgentEvent::ExtensionNotification(notification);
+                }
+
                 if let Some(final_output_tool) = self.final_output_tool.lock().await.as_ref() {
                     if final_output_tool.final_output.is_some() {
                         let final_event = AgentEvent::Message(
@@ -1363,6 +1422,9 @@ impl Agent {
                                         for msg in self.drain_elicitation_messages(&session_config.id).await {
                                             yield AgentEvent::Message(msg);
                                         }
+                                        for notification in self.drain_extension_notifications().await {
+                                            yield AgentEvent::ExtensionNotification(notification);
+                                        }
 
                                         tokio::select! {
                                             biased;
@@ -2025,4 +2087,335 @@ mod tests {
 
         Ok(())
     }
+
+    struct NotesResourceClient {}
+
+    #[async_trait::async_trait]
+    impl crate::agents::mcp_client::McpClientTrait for NotesResourceClient {
+        fn get_info(&self) -> Option<&rmcp::model::InitializeResult> {
+            None
+        }
+
+        async fn read_resource(
+            &self,
+            _session_id: &str,
+            uri: &str,
+            _cancellation_token: CancellationToken,
+        ) -> Result<rmcp::model::ReadResourceResult, rmcp::ServiceError> {
+            Ok(rmcp::model::ReadResourceResult {
+                contents: vec![rmcp::model::ResourceContents::text("notes", uri)],
+            })
+        }
+
+        async fn list_tools(
+            &self,
+            _session_id: &str,
+            _next_cursor: Option<String>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<rmcp::model::ListToolsResult, rmcp::ServiceError> {
+            Ok(rmcp::model::ListToolsResult::default())
+        }
+
+        async fn call_tool(
+            &self,
+            _session_id: &str,
+            _name: &str,
+            _arguments: Option<rmcp::model::JsonObject>,
+            _working_dir: Option<&str>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<CallToolResult, rmcp::ServiceError> {
+            Err(rmcp::ServiceError::TransportClosed)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streamed_resource_chunks_arrive_after_the_call_returns() -> Result<()> {
+        let agent = Agent::new();
+        agent
+            .extension_manager
+            .add_extension(
+                ExtensionConfig::Platform {
+                    name: "extensionmanager".to_string(),
+                    description: "extension manager".to_string(),
+                    display_name: None,
+                    bundled: None,
+                    available_tools: vec![],
+                },
+                None,
+                None,
+                None,
+            )
+            .await?;
+        agent
+            .extension_manager
+            .add_client(
+                "notes".to_string(),
+                ExtensionConfig::Builtin {
+                    name: "notes".to_string(),
+                    display_name: None,
+                    description: "built-in".to_string(),
+                    timeout: None,
+                    bundled: None,
+                    available_tools: vec![],
+                },
+                Arc::new(Mutex::new(Box::new(NotesResourceClient {}))),
+                Some(rmcp::model::ServerInfo {
+                    capabilities: rmcp::model::ServerCapabilities::builder()
+                        .enable_resources()
+                        .build(),
+                    ..Default::default()
+                }),
+                None,
+            )
+            .await;
+        let tool_call = CallToolRequestParams {
+            meta: None,
+            task: None,
+            name: "extensionmanager__read_resource".into(),
+            arguments: serde_json::json!({
+                "uri": "file:///notes.md",
+                "extension_name": "notes",
+                "stream": true,
+                "chunk_bytes": 2,
+            })
+            .as_object()
+            .cloned(),
+        };
+        let (_, result) = agent
+            .dispatch_tool_call(tool_call, "request".to_string(), None, &Session::default())
+            .await;
+        let result = result.unwrap().result.await.unwrap();
+        assert_ne!(result.is_error, Some(true));
+        let started = result.content[0].as_text().unwrap().text.clone();
+        let stream_id = started
+            .split_whitespace()
+            .find(|word| word.starts_with("stream_"))
+            .unwrap()
+            .trim_end_matches(';')
+            .to_string();
+
+        // The chunks arrive as notifications after the call has returned
+        let mut data = String::new();
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            loop {
+                for (extension, notification) in agent.drain_extension_notifications().await {
+                    assert_eq!(extension.as_deref(), Some("notes"));
+                    let ServerNotification::CustomNotification(chunk) = notification else {
+                        panic!("unexpected notification {:?}", notification);
+                    };
+                    let params = chunk.params.unwrap();
+                    assert_eq!(params["streamId"], stream_id.as_str());
+                    data.push_str(params["data"].as_str().unwrap());
+                    if params["last"] == true {
+                        return;
+                    }
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await?;
+        assert_eq!(data, "notes");
+        Ok(())
+    }
+
+    struct LogClient {
+        notifications: std::sync::Mutex<Option<mpsc::Receiver<ServerNotification>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl crate::agents::mcp_client::McpClientTrait for LogClient {
+        fn get_info(&self) -> Option<&rmcp::model::InitializeResult> {
+            None
+        }
+
+        async fn list_tools(
+            &self,
+            _session_id: &str,
+            _next_cursor: Option<String>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<rmcp::model::ListToolsResult, rmcp::ServiceError> {
+            Ok(rmcp::model::ListToolsResult::default())
+        }
+
+        async fn call_tool(
+            &self,
+            _session_id: &str,
+            _name: &str,
+            _arguments: Option<rmcp::model::JsonObject>,
+            _working_dir: Option<&str>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<CallToolResult, rmcp::ServiceError> {
+            Err(rmcp::ServiceError::TransportClosed)
+        }
+
+        async fn subscribe_resource(
+            &self,
+            _session_id: &str,
+            _uri: &str,
+            _cancellation_token: CancellationToken,
+        ) -> Result<(), rmcp::ServiceError> {
+            Ok(())
+        }
+
+        async fn subscribe(&self) -> mpsc::Receiver<ServerNotification> {
+            self.notifications
+                .lock()
+                .unwrap()
+                .take()
+                .unwrap_or_else(|| mpsc::channel(1).1)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resource_watch_outlives_the_watch_call() -> Result<()> {
+        let agent = Agent::new();
+        agent
+            .extension_manager
+            .add_extension(
+                ExtensionConfig::Platform {
+                    name: "extensionmanager".to_string(),
+                    description: "extension manager".to_string(),
+                    display_name: None,
+                    bundled: None,
+                    available_tools: vec![],
+                },
+                None,
+                None,
+                None,
+            )
+            .await?;
+        let (upstream, notifications) = mpsc::channel(16);
+        agent
+            .extension_manager
+            .add_client(
+                "logs".to_string(),
+                ExtensionConfig::Builtin {
+                    name: "logs".to_string(),
+                    display_name: None,
+                    description: "built-in".to_string(),
+                    timeout: None,
+                    bundled: None,
+                    available_tools: vec![],
+                },
+                Arc::new(Mutex::new(Box::new(LogClient {
+                    notifications: std::sync::Mutex::new(Some(notifications)),
+                }))),
+                Some(rmcp::model::ServerInfo {
+                    capabilities: rmcp::model::ServerCapabilities::builder()
+                        .enable_resources()
+                        .enable_resources_subscribe()
+                        .build(),
+                    ..Default::default()
+                }),
+                None,
+            )
+            .await;
+
+        let tool_call = CallToolRequestParams {
+            meta: None,
+            task: None,
+            name: "extensionmanager__watch_resource".into(),
+            arguments: serde_json::json!({
+                "uri": "file:///app.log",
+                "extension_name": "logs",
+            })
+            .as_object()
+            .cloned(),
+        };
+        let (_, result) = agent
+            .dispatch_tool_call(tool_call, "request".to_string(), None, &Session::default())
+            .await;
+        let result = result.unwrap().result.await.unwrap();
+        assert_ne!(result.is_error, Some(true));
+
+        // The call and its notification stream are done; the agent still gets the update
+        upstream
+            .send(ServerNotification::ResourceUpdatedNotification(
+                rmcp::model::ResourceUpdatedNotification {
+                    params: rmcp::model::ResourceUpdatedNotificationParam {
+                        uri: "file:///app.log".to_string(),
+                    },
+                    method: Default::default(),
+                    extensions: Default::default(),
+                },
+            ))
+            .await?;
+        let updates = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            loop {
+                let updates = agent.drain_extension_notifications().await;
+                if !updates.is_empty() {
+                    return updates;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await?;
+        assert_eq!(updates.len(), 1);
+        let (extension, ServerNotification::ResourceUpdatedNotification(updated)) = &updates[0]
+        else {
+            panic!("unexpected notification {:?}", updates[0]);
+        };
+        assert_eq!(extension.as_deref(), Some("logs"));
+        assert_eq!(updated.params.uri, "file:///app.log");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_extension_crash_reaches_the_agent() -> Result<()> {
+        let agent = Agent::new();
+        // The client drops its transport on every call, like a process exiting mid-call
+        agent
+            .extension_manager
+            .add_client(
+                "crashy".to_string(),
+                ExtensionConfig::Builtin {
+                    name: "crashy".to_string(),
+                    display_name: None,
+                    description: "built-in".to_string(),
+                    timeout: None,
+                    bundled: None,
+                    available_tools: vec![],
+                },
+                Arc::new(Mutex::new(Box::new(LogClient {
+                    notifications: std::sync::Mutex::new(None),
+                }))),
+                None,
+                None,
+            )
+            .await;
+
+        let session = Session::default();
+        let call = || {
+            agent.dispatch_tool_call(
+                CallToolRequestParams {
+                    meta: None,
+                    task: None,
+                    name: "crashy__work".into(),
+                    arguments: None,
+                },
+                "request".to_string(),
+                None,
+                &session,
+            )
+        };
+        let (_, result) = call().await;
+        let error = result.unwrap().result.await.unwrap_err();
+        assert!(error.message.contains("appears to have crashed"));
+
+        let notifications = agent.drain_extension_notifications().await;
+        assert_eq!(notifications.len(), 1);
+        let (extension, ServerNotification::CustomNotification(notification)) = &notifications[0]
+        else {
+            panic!("unexpected notification {:?}", notifications[0]);
+        };
+        assert_eq!(extension.as_deref(), Some("crashy"));
+        let params = notification.params.as_ref().unwrap();
+        assert_eq!(params["event_type"], "extension_crashed");
+
+        // The open circuit breaker turns the next call away before it reaches the transport
+        let (_, result) = call().await;
+        let error = result.unwrap().result.await.unwrap_err();
+        assert!(error.message.contains("appears to have crashed"));
+        Ok(())
+    }
 }
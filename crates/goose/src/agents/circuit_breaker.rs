@@ -0,0 +1,108 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Consecutive crash-like failures (transport, timeout, protocol) that open the breaker
+const FAILURE_THRESHOLD: u32 = 3;
+/// How long an open breaker rejects calls before it lets a trial call through
+const COOLDOWN: Duration = Duration::from_secs(30);
+
+#[derive(Default)]
+struct State {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// Stops calls to an extension that crashed or keeps failing, so the agent gets a fast,
+/// clear error instead of waiting on a dead transport.
+///
+/// A crash opens the breaker at once; other crash-like failures open it once
+/// `FAILURE_THRESHOLD` happen in a row. After `COOLDOWN` the breaker lets calls through
+/// again: a success closes it, and another failure opens it for a new cooldown. A successful
+/// call resets the failure count.
+#[derive(Default)]
+pub struct CircuitBreaker {
+    state: Mutex<State>,
+}
+
+impl CircuitBreaker {
+    /// Whether a call may go through now, or how long until the breaker lets one through
+    pub fn check(&self) -> Result<(), Duration> {
+        let state = self.state.lock().expect("circuit breaker poisoned");
+        match state.open_until {
+            Some(open_until) => match open_until.checked_duration_since(Instant::now()) {
+                Some(remaining) if !remaining.is_zero() => Err(remaining),
+                _ => Ok(()),
+            },
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.check().is_err()
+    }
+
+    pub fn record_success(&self) {
+        *self.state.lock().expect("circuit breaker poisoned") = State::default();
+    }
+
+    /// Count a crash-like failure, opening the breaker once there have been enough in a row
+    /// or when a trial call after the cooldown fails
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().expect("circuit breaker poisoned");
+        state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+        if state.consecutive_failures >= FAILURE_THRESHOLD {
+            state.open_until = Some(Instant::now() + COOLDOWN);
+        }
+    }
+
+    /// Open the breaker at once, for an extension whose process died
+    pub fn trip(&self) {
+        let mut state = self.state.lock().expect("circuit breaker poisoned");
+        state.consecutive_failures = state.consecutive_failures.max(FAILURE_THRESHOLD);
+        state.open_until = Some(Instant::now() + COOLDOWN);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opens_after_consecutive_failures() {
+        let breaker = CircuitBreaker::default();
+        for _ in 0..FAILURE_THRESHOLD - 1 {
+            breaker.record_failure();
+            assert!(!breaker.is_open());
+        }
+        breaker.record_success();
+        breaker.record_failure();
+        assert!(!breaker.is_open());
+
+        for _ in 0..FAILURE_THRESHOLD {
+            breaker.record_failure();
+        }
+        let remaining = breaker.check().unwrap_err();
+        assert!(remaining <= COOLDOWN);
+    }
+
+    #[test]
+    fn test_trip_opens_at_once_and_cooldown_allows_a_trial() {
+        let breaker = CircuitBreaker::default();
+        breaker.trip();
+        assert!(breaker.is_open());
+
+        // Pretend the cooldown has passed
+        breaker.state.lock().unwrap().open_until = Some(Instant::now());
+        assert!(breaker.check().is_ok());
+
+        // A failed trial opens it again straight away
+        breaker.record_failure();
+        assert!(breaker.is_open());
+
+        breaker.state.lock().unwrap().open_until = Some(Instant::now());
+        breaker.record_success();
+        assert!(breaker.check().is_ok());
+        breaker.record_failure();
+        assert!(!breaker.is_open());
+    }
+}
@@ -4,7 +4,7 @@ use crate::agents::code_execution_extension;
 use crate::agents::extension_manager_extension;
 use crate::agents::skills_extension;
 use crate::agents::todo_extension;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, VecDeque};
 
 use crate::agents::mcp_client::McpClientTrait;
 use crate::config;
@@ -123,11 +123,259 @@ pub static PLATFORM_EXTENSIONS: Lazy<HashMap<&'static str, PlatformExtensionDef>
     },
 );
 
+/// A session's cap on total tool calls, and how many calls have counted against it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ToolBudget {
+    /// `None` means unlimited
+    pub limit: Option<usize>,
+    pub used: usize,
+}
+
+impl ToolBudget {
+    pub fn remaining(&self) -> Option<usize> {
+        self.limit.map(|limit| limit.saturating_sub(self.used))
+    }
+}
+
+/// Tool call budgets per session, shared between the extension manager and the platform
+/// extensions through [`PlatformExtensionContext`]
+#[derive(Debug, Clone, Default)]
+pub struct ToolBudgets(std::sync::Arc<std::sync::Mutex<HashMap<String, ToolBudget>>>);
+
+impl ToolBudgets {
+    pub fn get(&self, session_id: &str) -> ToolBudget {
+        self.0
+            .lock()
+            .expect("tool budgets poisoned")
+            .get(session_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Set the cap for a session, keeping the calls already made
+    pub fn set_limit(&self, session_id: &str, limit: Option<usize>) -> ToolBudget {
+        let mut budgets = self.0.lock().expect("tool budgets poisoned");
+        let budget = budgets.entry(session_id.to_string()).or_default();
+        budget.limit = limit;
+        *budget
+    }
+
+    /// Count one call against the session's budget. Returns false without counting it if
+    /// the budget is already exhausted.
+    pub fn try_consume(&self, session_id: &str) -> bool {
+        let mut budgets = self.0.lock().expect("tool budgets poisoned");
+        let budget = budgets.entry(session_id.to_string()).or_default();
+        if budget.remaining() == Some(0) {
+            return false;
+        }
+        budget.used += 1;
+        true
+    }
+}
+
+/// The resource URIs each extension returned in a session's latest `list_resources`, in
+/// listing order, so `read_resource` can take an index instead of a URI. A listing is
+/// forgotten when its extension is removed, so an old index can't silently point elsewhere.
+#[derive(Debug, Clone, Default)]
+pub struct ResourceListings(std::sync::Arc<std::sync::Mutex<ResourceListingsBySession>>);
+
+/// Listed URIs by session, then by extension
+type ResourceListingsBySession = HashMap<String, HashMap<String, Vec<String>>>;
+
+impl ResourceListings {
+    pub fn record(&self, session_id: &str, extension_name: &str, uris: Vec<String>) {
+        self.0
+            .lock()
+            .expect("resource listings poisoned")
+            .entry(session_id.to_string())
+            .or_default()
+            .insert(extension_name.to_string(), uris);
+    }
+
+    pub fn get(&self, session_id: &str, extension_name: &str) -> Option<Vec<String>> {
+        self.0
+            .lock()
+            .expect("resource listings poisoned")
+            .get(session_id)
+            .and_then(|listings| listings.get(extension_name))
+            .cloned()
+    }
+
+    /// Drop an extension's listings in every session
+    pub fn forget_extension(&self, extension_name: &str) {
+        self.0
+            .lock()
+            .expect("resource listings poisoned")
+            .values_mut()
+            .for_each(|listings| {
+                listings.remove(extension_name);
+            });
+    }
+}
+
+/// How many extension changes the audit log keeps across all sessions
+const MAX_EXTENSION_AUDIT_ENTRIES: usize = 200;
+
+/// One extension enabled or disabled through `manage_extensions`
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtensionAuditEntry {
+    pub session_id: String,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub action: extension_manager_extension::ManageExtensionAction,
+    pub extension_name: String,
+    pub reason: Option<String>,
+}
+
+/// The latest extension changes, oldest first, shared like [`ToolBudgets`]. Only the last
+/// [`MAX_EXTENSION_AUDIT_ENTRIES`] are kept.
+#[derive(Debug, Clone, Default)]
+pub struct ExtensionAuditLog(std::sync::Arc<std::sync::Mutex<VecDeque<ExtensionAuditEntry>>>);
+
+impl ExtensionAuditLog {
+    pub fn record(&self, entry: ExtensionAuditEntry) {
+        let mut entries = self.0.lock().expect("extension audit log poisoned");
+        if entries.len() == MAX_EXTENSION_AUDIT_ENTRIES {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    pub fn entries(&self, session_id: &str) -> Vec<ExtensionAuditEntry> {
+        self.0
+            .lock()
+            .expect("extension audit log poisoned")
+            .iter()
+            .filter(|entry| entry.session_id == session_id)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Resources read ahead by `prefetch_resources`, which `read_resource` serves until they
+/// expire. Shared like [`ToolBudgets`].
+#[derive(Debug, Clone, Default)]
+pub struct ResourceCache(std::sync::Arc<std::sync::Mutex<CachedResourcesByUri>>);
+
+/// Cached reads by URI, then by the extension that served them
+type CachedResourcesByUri = HashMap<String, HashMap<String, CachedResource>>;
+
+#[derive(Debug, Clone)]
+struct CachedResource {
+    result: rmcp::model::ReadResourceResult,
+    expires_at: std::time::Instant,
+}
+
+impl ResourceCache {
+    pub fn insert(
+        &self,
+        uri: &str,
+        extension_name: &str,
+        result: rmcp::model::ReadResourceResult,
+        ttl: std::time::Duration,
+    ) {
+        self.0
+            .lock()
+            .expect("resource cache poisoned")
+            .entry(uri.to_string())
+            .or_default()
+            .insert(
+                extension_name.to_string(),
+                CachedResource {
+                    result,
+                    expires_at: std::time::Instant::now() + ttl,
+                },
+            );
+    }
+
+    /// A fresh cached read of `uri` from `extension_name`, or from the only extension with
+    /// one when no name is given. Expired reads are dropped.
+    pub fn get(
+        &self,
+        uri: &str,
+        extension_name: Option<&str>,
+    ) -> Option<rmcp::model::ReadResourceResult> {
+        let mut cache = self.0.lock().expect("resource cache poisoned");
+        let entries = cache.get_mut(uri)?;
+        let now = std::time::Instant::now();
+        entries.retain(|_name, cached| cached.expires_at > now);
+        match extension_name {
+            Some(name) => entries.get(name).map(|cached| cached.result.clone()),
+            None if entries.len() == 1 => entries.values().next().map(|c| c.result.clone()),
+            None => None,
+        }
+    }
+
+    /// Drop every cached read of `uri`, e.g. after it was written
+    pub fn remove(&self, uri: &str) {
+        self.0.lock().expect("resource cache poisoned").remove(uri);
+    }
+
+    pub fn forget_extension(&self, extension_name: &str) {
+        self.0
+            .lock()
+            .expect("resource cache poisoned")
+            .values_mut()
+            .for_each(|entries| {
+                entries.remove(extension_name);
+            });
+    }
+}
+
+/// Values for `${VAR}` placeholders in extension configs, looked up before the process
+/// environment when manage_extensions enables a stored extension. Set from
+/// `GOOSE_EXTENSION_VARIABLES`.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigVariables(std::sync::Arc<HashMap<String, String>>);
+
+impl ConfigVariables {
+    pub fn new(variables: HashMap<String, String>) -> Self {
+        Self(std::sync::Arc::new(variables))
+    }
+
+    /// The override for a variable, falling back to the process environment
+    pub fn resolve(&self, name: &str) -> Option<String> {
+        self.0
+            .get(name)
+            .cloned()
+            .or_else(|| std::env::var(name).ok())
+    }
+}
+
+/// How platform tools render their human-readable summaries. Structured content is the same
+/// either way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResultFormat {
+    #[default]
+    Plain,
+    /// Tables and bullet lists, for UIs that render markdown
+    Markdown,
+}
+
+/// A token bucket rate for the extension manager's tools that change state, applied to each
+/// tool separately: up to `burst` calls at once, refilled at `per_minute`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolRateLimit {
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
 #[derive(Clone)]
 pub struct PlatformExtensionContext {
     pub extension_manager:
         Option<std::sync::Weak<crate::agents::extension_manager::ExtensionManager>>,
     pub session_manager: std::sync::Arc<crate::session::SessionManager>,
+    pub tool_budgets: ToolBudgets,
+    pub resource_listings: ResourceListings,
+    pub resource_cache: ResourceCache,
+    pub extension_audit_log: ExtensionAuditLog,
+    pub config_variables: ConfigVariables,
+    pub result_format: ResultFormat,
+    /// Limits how fast the extension manager's tools that change state can be called;
+    /// unlimited when unset
+    pub tool_rate_limit: Option<ToolRateLimit>,
+    /// Notifications platform extensions push to their subscribers, such as a changed tool list
+    pub notifications: tokio::sync::broadcast::Sender<rmcp::model::ServerNotification>,
 }
 
 impl PlatformExtensionContext {
@@ -557,6 +805,74 @@ impl ExtensionConfig {
     }
 }
 
+/// Replace the `${VAR}` placeholders in `value`, and the bare `$VAR` ones too when
+/// `allow_bare` is set, with what `lookup` returns for them. Placeholders `lookup` can't
+/// resolve are left in place and their names added to `unresolved`.
+pub(crate) fn substitute_variables(
+    value: &str,
+    allow_bare: bool,
+    lookup: impl Fn(&str) -> Option<String>,
+    unresolved: &mut BTreeSet<String>,
+) -> String {
+    static BRACED: Lazy<regex::Regex> = Lazy::new(|| {
+        regex::Regex::new(r"\$\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}").expect("valid regex")
+    });
+    static BRACED_OR_BARE: Lazy<regex::Regex> = Lazy::new(|| {
+        regex::Regex::new(r"\$(?:\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}|([A-Za-z_][A-Za-z0-9_]*))")
+            .expect("valid regex")
+    });
+    let placeholder = if allow_bare { &BRACED_OR_BARE } else { &BRACED };
+    placeholder
+        .replace_all(value, |caps: &regex::Captures| {
+            let name = caps
+                .get(1)
+                .or_else(|| caps.get(2))
+                .map_or("", |m| m.as_str());
+            lookup(name).unwrap_or_else(|| {
+                unresolved.insert(name.to_string());
+                caps[0].to_string()
+            })
+        })
+        .into_owned()
+}
+
+impl ExtensionConfig {
+    /// Resolve `${VAR}` placeholders in the command, args, URI and env values of the config.
+    /// A bare `$VAR` is left alone, since it is often meant for a shell or the extension's
+    /// own environment. Headers are left as they are, since they are filled in from the
+    /// extension's own envs when it connects. Returns the sorted names of any variables
+    /// `lookup` couldn't resolve, without the values of those it could.
+    pub fn interpolate_variables(
+        &mut self,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<(), Vec<String>> {
+        let mut unresolved = BTreeSet::new();
+        match self {
+            Self::Stdio {
+                cmd, args, envs, ..
+            } => {
+                for value in std::iter::once(cmd)
+                    .chain(args.iter_mut())
+                    .chain(envs.map.values_mut())
+                {
+                    *value = substitute_variables(value, false, &lookup, &mut unresolved);
+                }
+            }
+            Self::StreamableHttp { uri, envs, .. } => {
+                for value in std::iter::once(uri).chain(envs.map.values_mut()) {
+                    *value = substitute_variables(value, false, &lookup, &mut unresolved);
+                }
+            }
+            _ => {}
+        }
+        if unresolved.is_empty() {
+            Ok(())
+        } else {
+            Err(unresolved.into_iter().collect())
+        }
+    }
+}
+
 impl std::fmt::Display for ExtensionConfig {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -658,6 +974,75 @@ available_tools: []",
         }
     }
 
+    #[test]
+    fn test_config_variables_override_environment() {
+        let _guard = env_lock::lock_env([
+            ("GOOSE_TEST_OVERRIDDEN", Some("from-env")),
+            ("GOOSE_TEST_FROM_ENV", Some("from-env")),
+        ]);
+        let variables = extension::ConfigVariables::new(std::collections::HashMap::from([(
+            "GOOSE_TEST_OVERRIDDEN".to_string(),
+            "from-config".to_string(),
+        )]));
+
+        assert_eq!(
+            variables.resolve("GOOSE_TEST_OVERRIDDEN").as_deref(),
+            Some("from-config")
+        );
+        assert_eq!(
+            variables.resolve("GOOSE_TEST_FROM_ENV").as_deref(),
+            Some("from-env")
+        );
+        assert_eq!(variables.resolve("GOOSE_TEST_UNSET_VARIABLE"), None);
+    }
+
+    #[test]
+    fn test_interpolate_variables() {
+        let mut config = ExtensionConfig::Stdio {
+            name: "templated".to_string(),
+            description: String::new(),
+            cmd: "${TOOLS_DIR}/server".to_string(),
+            args: vec![
+                "--token=${ API_TOKEN }".to_string(),
+                "--user=$USER_NAME".to_string(),
+            ],
+            envs: extension::Envs::new(std::collections::HashMap::from([
+                ("REGION".to_string(), "${REGION}".to_string()),
+                ("PASSWORD".to_string(), "pa$word".to_string()),
+            ])),
+            env_keys: vec![],
+            timeout: None,
+            bundled: None,
+            available_tools: vec![],
+        };
+        let lookup = |name: &str| match name {
+            "TOOLS_DIR" => Some("/opt/tools".to_string()),
+            "API_TOKEN" => Some("secret".to_string()),
+            "USER_NAME" => Some("goose".to_string()),
+            _ => None,
+        };
+
+        assert_eq!(
+            config.clone().interpolate_variables(lookup),
+            Err(vec!["REGION".to_string()])
+        );
+
+        config
+            .interpolate_variables(|name| lookup(name).or_else(|| Some("eu".to_string())))
+            .unwrap();
+        let ExtensionConfig::Stdio {
+            cmd, args, envs, ..
+        } = config
+        else {
+            unreachable!();
+        };
+        assert_eq!(cmd, "/opt/tools/server");
+        // Bare `$NAME` is left for the shell or the extension's own environment
+        assert_eq!(args, vec!["--token=secret", "--user=$USER_NAME"]);
+        assert_eq!(envs.get_env()["REGION"], "eu");
+        assert_eq!(envs.get_env()["PASSWORD"], "pa$word");
+    }
+
     #[test]
     fn test_deserialize_null_description() {
         let config: ExtensionConfig = serde_yaml::from_str(
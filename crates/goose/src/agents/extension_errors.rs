@@ -0,0 +1,70 @@
+use rmcp::model::ErrorCode;
+use rmcp::ServiceError;
+
+/// Categories of failures a tool call to an extension can produce.
+///
+/// MCP servers don't declare an error taxonomy, so these are inferred from the errors the
+/// extension manager observes while dispatching calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ToolErrorCategory {
+    InvalidParams,
+    NotFound,
+    Internal,
+    Crashed,
+    Timeout,
+    Cancelled,
+    Protocol,
+    /// The call completed but the result was flagged with `is_error`
+    ToolError,
+}
+
+impl ToolErrorCategory {
+    pub fn from_service_error(error: &ServiceError) -> Self {
+        match error {
+            ServiceError::McpError(error_data) => match error_data.code {
+                ErrorCode::INVALID_PARAMS => Self::InvalidParams,
+                ErrorCode::METHOD_NOT_FOUND | ErrorCode::RESOURCE_NOT_FOUND => Self::NotFound,
+                _ => Self::Internal,
+            },
+            ServiceError::TransportClosed | ServiceError::TransportSend(_) => Self::Crashed,
+            ServiceError::Timeout { .. } => Self::Timeout,
+            ServiceError::Cancelled { .. } => Self::Cancelled,
+            _ => Self::Protocol,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::InvalidParams => "invalid_params",
+            Self::NotFound => "not_found",
+            Self::Internal => "internal",
+            Self::Crashed => "crashed",
+            Self::Timeout => "timeout",
+            Self::Cancelled => "cancelled",
+            Self::Protocol => "protocol",
+            Self::ToolError => "tool_error",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::InvalidParams => "The tool rejected its arguments; fix them before retrying",
+            Self::NotFound => "The requested tool, method, or resource does not exist",
+            Self::Internal => "The extension reported an internal error",
+            Self::Crashed => {
+                "The extension stopped responding mid-call; it must be re-enabled before retrying"
+            }
+            Self::Timeout => "The call did not complete within the extension timeout",
+            Self::Cancelled => "The call was cancelled before it completed",
+            Self::Protocol => "The extension sent a response that did not match the request",
+            Self::ToolError => "The tool ran but reported a failure in its result",
+        }
+    }
+
+    pub fn retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::Internal | Self::Timeout | Self::Cancelled | Self::ToolError
+        )
+    }
+}
@@ -3,6 +3,7 @@ use axum::http::{HeaderMap, HeaderName};
 use chrono::{DateTime, Utc};
 use futures::stream::{FuturesUnordered, StreamExt};
 use futures::{future, FutureExt};
+use lru::LruCache;
 use rand::{distributions::Alphanumeric, Rng};
 use rmcp::service::{ClientInitializeError, ServiceError};
 use rmcp::transport::streamable_http_client::{
@@ -11,30 +12,40 @@ use rmcp::transport::streamable_http_client::{
 use rmcp::transport::{
     ConfigureCommandExt, DynamicTransportError, StreamableHttpClientTransport, TokioChildProcess,
 };
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
 use std::process::Stdio;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use tempfile::{tempdir, TempDir};
 use tokio::io::AsyncReadExt;
 use tokio::process::Command;
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, mpsc, Mutex};
 use tokio_stream::wrappers::ReceiverStream;
 use tokio_util::sync::CancellationToken;
 use tracing::{error, warn};
 
+use super::circuit_breaker::CircuitBreaker;
 use super::container::Container;
 use super::extension::{
-    ExtensionConfig, ExtensionError, ExtensionInfo, ExtensionResult, PlatformExtensionContext,
-    ToolInfo, PLATFORM_EXTENSIONS,
+    substitute_variables, ConfigVariables, ExtensionAuditLog, ExtensionConfig, ExtensionError,
+    ExtensionInfo, ExtensionResult, PlatformExtensionContext, ResourceCache, ResourceListings,
+    ResultFormat, ToolBudgets, ToolInfo, ToolRateLimit, PLATFORM_EXTENSIONS,
 };
+use super::extension_errors::ToolErrorCategory;
+use super::priority_semaphore::{PrioritySemaphore, ToolCallPriority};
+use super::resource_template::template_variables;
 use super::tool_execution::ToolCallResult;
 use super::types::SharedProvider;
 use crate::agents::extension::{Envs, ProcessExit};
 use crate::agents::extension_malware_check;
-use crate::agents::mcp_client::{McpClient, McpClientTrait};
+use crate::agents::extension_manager_extension;
+use crate::agents::extension_manager_extension::{
+    ListResourcesParams, ReadMultipleResourcesParams, ReadResourceParams, ResourceHashParams,
+};
+use crate::agents::mcp_client::{McpClient, McpClientTrait, WRITE_RESOURCE_METHOD};
 use crate::builtin_extension::get_builtin_extension;
 use crate::config::extensions::name_to_key;
 use crate::config::search_path::SearchPaths;
@@ -43,23 +54,717 @@ use crate::oauth::oauth_flow;
 use crate::prompt_template;
 use crate::subprocess::configure_subprocess;
 use rmcp::model::{
-    CallToolRequestParams, Content, ErrorCode, ErrorData, GetPromptResult, Prompt, Resource,
-    ResourceContents, ServerInfo, Tool,
+    CallToolRequestParams, CallToolResult, Content, CustomNotification, ErrorCode, ErrorData,
+    GetPromptResult, JsonObject, Meta, Prompt, ProtocolVersion, RawContent, Resource,
+    ResourceContents, ResourceTemplate, ServerCapabilities, ServerInfo, ServerNotification, Tool,
 };
 use rmcp::transport::auth::AuthClient;
 use schemars::_private::NoSerialize;
+use serde::Serialize;
 use serde_json::Value;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
 
 type McpClientBox = Arc<Mutex<Box<dyn McpClientTrait>>>;
+/// Resources being watched, as (extension key, uri)
+type ResourceWatches = Arc<std::sync::Mutex<HashSet<(String, String)>>>;
+/// Resources still being streamed, by stream id, as (extension key if named, sending task)
+type ResourceStreams =
+    Arc<std::sync::Mutex<HashMap<String, (Option<String>, tokio::task::AbortHandle)>>>;
+/// Chunk notifications of streamed resources, paired with their stream id
+type ResourceChunk = (Option<String>, ServerNotification);
+
+/// How many chunks of streamed resources may wait to be delivered before the streams pause
+const RESOURCE_STREAM_BUFFER: usize = 8;
+/// How long a paused stream waits for room before it is abandoned
+const RESOURCE_STREAM_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Tool-call argument asking for the result to be kept in the session's result store and
+/// replaced by a reference handle. It is stripped before the call reaches the extension.
+pub const STORE_RESULT_ARG: &str = "store_result_as_reference";
+const MAX_STORED_RESULTS_PER_SESSION: usize = 32;
+
+/// How often a resource watch checks whether anyone is still listening for its updates
+const RESOURCE_WATCH_LISTENER_CHECK: Duration = Duration::from_secs(5);
+/// How many resource updates a subscriber may fall behind before it misses the oldest
+const RESOURCE_UPDATE_BUFFER: usize = 64;
+
+/// Tool-call argument (or request meta key) carrying a `ToolCallPriority`. The argument is
+/// stripped before the call reaches the extension; the meta value wins if both are set.
+pub const PRIORITY_ARG: &str = "tool_call_priority";
+
+type StoredResults = Arc<Mutex<HashMap<String, LruCache<String, CallToolResult>>>>;
+/// Turn one representation of a resource into a content block labeled with its MIME type.
+/// Images are passed through as image content; other binary forms are embedded as-is.
+fn labeled_representation(uri: &str, content: ResourceContents) -> Content {
+    match content {
+        ResourceContents::TextResourceContents {
+            ref mime_type,
+            ref text,
+            ..
+        } => Content::text(format!(
+            "{}\nrepresentation: {}\n\n{}",
+            uri,
+            mime_type.as_deref().unwrap_or("text/plain"),
+            text
+        )),
+        blob => blob_content(blob),
+    }
+}
+
+/// Binary resource contents as an image when the MIME type says so, or as an embedded
+/// resource that keeps its MIME type and base64 payload otherwise
+fn blob_content(content: ResourceContents) -> Content {
+    match content {
+        ResourceContents::BlobResourceContents {
+            mime_type: Some(ref mime_type),
+            ref blob,
+            ..
+        } if mime_type.starts_with("image/") => Content::image(blob.clone(), mime_type.clone()),
+        content => Content::resource(content),
+    }
+}
+
+/// A resource's contents as returned by read_resource: text prefixed with its URI and tagged
+/// with its MIME type, binary forms as blobs
+fn resource_content(uri: &str, contents: Vec<ResourceContents>) -> Vec<Content> {
+    contents
+        .into_iter()
+        .map(|content| match content {
+            ResourceContents::TextResourceContents {
+                text, mime_type, ..
+            } => {
+                let mut content = Content::text(format!("{}\n\n{}", uri, text));
+                if let (RawContent::Text(text), Some(mime_type)) = (&mut content.raw, mime_type) {
+                    text.meta = Some(Meta(JsonObject::from_iter([(
+                        "mimeType".to_string(),
+                        Value::String(mime_type),
+                    )])));
+                }
+                content
+            }
+            blob => blob_content(blob),
+        })
+        .collect()
+}
+
+/// Keys in a resource's `_meta` that tell a client whether its copy is still current
+const RESOURCE_VALIDATOR_META_KEYS: [&str; 2] = ["etag", "lastModified"];
+
+/// The etag and last-modified time an extension attached to a resource's contents, if any.
+/// The first content that has a key wins.
+fn resource_validators(contents: &[ResourceContents]) -> JsonObject {
+    let mut validators = JsonObject::new();
+    for content in contents {
+        let meta = match content {
+            ResourceContents::TextResourceContents { meta, .. }
+            | ResourceContents::BlobResourceContents { meta, .. } => meta,
+        };
+        for key in RESOURCE_VALIDATOR_META_KEYS {
+            if let Some(value) = meta.as_ref().and_then(|meta| meta.0.get(key)) {
+                validators
+                    .entry(key.to_string())
+                    .or_insert_with(|| value.clone());
+            }
+        }
+    }
+    validators
+}
+
+/// Add `entries` to the `_meta` of a content block, keeping what's already there
+fn extend_content_meta(content: &mut Content, entries: &JsonObject) {
+    let meta = match &mut content.raw {
+        RawContent::Text(text) => &mut text.meta,
+        RawContent::Image(image) => &mut image.meta,
+        RawContent::Resource(resource) => &mut resource.meta,
+        _ => return,
+    };
+    let meta = meta.get_or_insert_with(Meta::default);
+    for (key, value) in entries {
+        meta.0.insert(key.clone(), value.clone());
+    }
+}
+
+/// What `gather_resources` collected from the extensions it queried
+struct ResourceGathering {
+    resources: Vec<(String, Resource)>,
+    /// Extensions that answered, including those with no resources
+    listed: Vec<String>,
+    /// Notes about extensions that timed out or a listing that was cut short
+    notes: Vec<Content>,
+}
+
+/// `_meta` entries naming the extension each listed resource came from, in listing order, so
+/// resources with the same URI in several extensions can be told apart
+fn resource_sources(resources: &[(String, Resource)], indices: &[usize]) -> JsonObject {
+    let sources = resources
+        .iter()
+        .zip(indices)
+        .map(|((extension_name, resource), index)| {
+            serde_json::json!({
+                "uri": resource.uri,
+                "name": resource.name,
+                "extensionName": extension_name,
+                "index": index,
+            })
+        })
+        .collect();
+    JsonObject::from_iter([("resources".to_string(), Value::Array(sources))])
+}
+
+/// The largest char boundary in `text` at or before `index`
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let index = index.min(text.len());
+    (0..=index)
+        .rev()
+        .find(|&i| text.is_char_boundary(i))
+        .unwrap_or(0)
+}
+
+/// Cut a text resource down to the byte window starting at `offset`, at most `length` bytes
+/// long, then to `truncate_at` bytes, marking where it was cut. Returns the `_meta` entries
+/// describing the window, or None if the whole text is kept.
+fn window_resource_text(
+    text: &mut String,
+    offset: usize,
+    length: Option<usize>,
+    truncate_at: Option<usize>,
+) -> Option<JsonObject> {
+    let total = text.len();
+    let start = floor_char_boundary(text, offset);
+    let mut end = match length {
+        Some(length) => floor_char_boundary(text, start.saturating_add(length)),
+        None => total,
+    };
+    let truncated = match truncate_at {
+        Some(limit) if end - start > limit => {
+            end = floor_char_boundary(text, start + limit);
+            true
+        }
+        _ => false,
+    };
+    if start == 0 && end == total {
+        return None;
+    }
+
+    // Both ends are on char boundaries, so this always succeeds
+    let mut window = text.get(start..end).unwrap_or_default().to_string();
+    if truncated {
+        window.push_str(&format!(
+            "\n\n[truncated: bytes {}-{} of {}; pass offset={} to read more]",
+            start, end, total, end
+        ));
+    }
+    *text = window;
+    Some(JsonObject::from_iter([
+        ("offset".to_string(), Value::from(start)),
+        ("length".to_string(), Value::from(end - start)),
+        ("totalBytes".to_string(), Value::from(total)),
+        ("truncated".to_string(), Value::Bool(truncated)),
+    ]))
+}
+
+/// Size in bytes of a resource's content, decoding the length of base64 blobs
+fn resource_contents_size(content: &ResourceContents) -> usize {
+    match content {
+        ResourceContents::TextResourceContents { text, .. } => text.len(),
+        ResourceContents::BlobResourceContents { blob, .. } => {
+            blob.trim_end_matches('=').len() * 3 / 4
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdleExtension {
+    pub name: String,
+    /// None if the extension has not handled a successful call since it was enabled
+    pub last_used: Option<DateTime<Utc>>,
+    pub tool_count: usize,
+}
+
+/// A resource in the catalog, attributed to the first extension (by name) that provides it
+#[derive(Debug, Clone, Serialize)]
+pub struct CatalogResource {
+    pub uri: String,
+    pub name: String,
+    pub extension: String,
+    pub mime_type: Option<String>,
+    pub size: Option<u32>,
+    /// Other extensions that list a resource with the same URI
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub also_provided_by: Vec<String>,
+}
+
+/// Every resource across the enabled extensions, deduplicated by URI
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ResourceCatalog {
+    pub total: usize,
+    pub per_extension: BTreeMap<String, usize>,
+    /// Sum of the sizes extensions reported; resources without a size don't count
+    pub total_bytes: u64,
+    /// Extensions whose resources could not be listed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unavailable: Vec<String>,
+    pub resources: Vec<CatalogResource>,
+}
+
+/// Runtime state of an enabled extension, for diagnostics
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtensionStatus {
+    pub name: String,
+    /// Name and version the server reported when it was initialized
+    pub server_name: Option<String>,
+    pub server_version: Option<String>,
+    pub protocol_version: Option<String>,
+    /// Whether the negotiated protocol version is older than
+    /// `GOOSE_MIN_EXTENSION_PROTOCOL_VERSION`
+    pub protocol_outdated: bool,
+    pub capabilities: Option<CapabilityFlags>,
+    pub crashed: bool,
+    /// Whether tool calls are being rejected after a crash or repeated failures
+    pub circuit_open: bool,
+    pub in_flight_calls: usize,
+    pub last_used: Option<DateTime<Utc>>,
+    /// Category of the failure if the most recent tool call failed
+    pub last_call_error: Option<&'static str>,
+    /// Errors observed from its tool calls, by category name
+    pub errors: BTreeMap<&'static str, usize>,
+}
+
+/// The oldest MCP protocol version an extension can negotiate without being flagged as
+/// outdated, from `GOOSE_MIN_EXTENSION_PROTOCOL_VERSION`. Versions are dates, so they
+/// compare as strings.
+fn min_extension_protocol_version() -> String {
+    Config::global()
+        .get_param::<String>("GOOSE_MIN_EXTENSION_PROTOCOL_VERSION")
+        .unwrap_or_else(|_| ProtocolVersion::V_2025_03_26.to_string())
+}
+
+/// The MCP capabilities an extension advertised when it was initialized
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CapabilityFlags {
+    pub tools: bool,
+    pub resources: bool,
+    /// Whether `resources.subscribe` is advertised, so `watch_resource` can be used
+    pub resource_subscriptions: bool,
+    pub prompts: bool,
+    pub completions: bool,
+    pub logging: bool,
+}
+
+impl CapabilityFlags {
+    fn from_server_capabilities(capabilities: &ServerCapabilities) -> Self {
+        Self {
+            tools: capabilities.tools.is_some(),
+            resources: capabilities.resources.is_some(),
+            resource_subscriptions: capabilities
+                .resources
+                .as_ref()
+                .and_then(|resources| resources.subscribe)
+                .unwrap_or(false),
+            prompts: capabilities.prompts.is_some(),
+            completions: capabilities.completions.is_some(),
+            logging: capabilities.logging.is_some(),
+        }
+    }
+
+    /// Capabilities available from at least one of the two
+    pub fn union(self, other: Self) -> Self {
+        Self {
+            tools: self.tools || other.tools,
+            resources: self.resources || other.resources,
+            resource_subscriptions: self.resource_subscriptions || other.resource_subscriptions,
+            prompts: self.prompts || other.prompts,
+            completions: self.completions || other.completions,
+            logging: self.logging || other.logging,
+        }
+    }
+}
+
+const PING_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtensionTransport {
+    /// Reached over the network, e.g. streamable HTTP
+    Remote,
+    /// A child process spoken to over stdio
+    Local,
+    /// A platform extension running inside this process
+    InProcess,
+}
+
+/// Outcome of [`ExtensionManager::ping_extension`]
+#[derive(Debug)]
+pub struct ExtensionPing {
+    pub transport: ExtensionTransport,
+    /// The extension was already marked as crashed by an earlier call
+    pub crashed: bool,
+    /// Round-trip latency, or the error that ended the round-trip
+    pub result: std::result::Result<Duration, ServiceError>,
+}
+
+/// Required properties of a tool's input schema that are absent from the arguments, each with
+/// the type and description the schema gives it
+fn missing_required_arguments(schema: &JsonObject, arguments: Option<&JsonObject>) -> Vec<Value> {
+    let Some(required) = schema.get("required").and_then(|v| v.as_array()) else {
+        return Vec::new();
+    };
+    let properties = schema.get("properties").and_then(|v| v.as_object());
+
+    required
+        .iter()
+        .filter_map(|name| name.as_str())
+        .filter(|name| arguments.is_none_or(|args| !args.contains_key(*name)))
+        .map(|name| {
+            let property = properties.and_then(|props| props.get(name));
+            serde_json::json!({
+                "name": name,
+                "type": property.and_then(|p| p.get("type")).cloned().unwrap_or(Value::Null),
+                "description": property
+                    .and_then(|p| p.get("description"))
+                    .cloned()
+                    .unwrap_or(Value::Null),
+            })
+        })
+        .collect()
+}
+
+/// Tool metadata keys an extension can set to mark a tool as deprecated, optionally naming the
+/// tool that replaces it
+const DEPRECATED_META_KEY: &str = "deprecated";
+const REPLACEMENT_META_KEY: &str = "replacement";
+
+/// Deprecation an extension declared in a tool's metadata
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ToolDeprecation {
+    /// The replacement's name as the extension reports it, without the extension prefix
+    replacement: Option<String>,
+}
+
+impl ToolDeprecation {
+    fn from_meta(meta: Option<&Meta>) -> Option<Self> {
+        let meta = meta?;
+        let deprecated = meta
+            .0
+            .get(DEPRECATED_META_KEY)
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        deprecated.then(|| Self {
+            replacement: meta
+                .0
+                .get(REPLACEMENT_META_KEY)
+                .and_then(|v| v.as_str())
+                .map(str::to_string),
+        })
+    }
+
+    fn notice(&self, prefix: &str) -> String {
+        match &self.replacement {
+            Some(replacement) => format!(
+                "This tool is deprecated; use {}__{} instead.",
+                prefix, replacement
+            ),
+            None => "This tool is deprecated and may be removed.".to_string(),
+        }
+    }
+}
+
+/// Tool metadata key holding the tool's stable id
+pub const TOOL_ID_META_KEY: &str = "goose/toolId";
+const TOOL_ID_PREFIX: &str = "tool_";
+
+/// A stable id for a tool that can be passed to `dispatch_tool_call` in place of its name.
+///
+/// The id is derived from the extension's config key and the tool's name as the extension
+/// reports it, so it survives changes to the tool's description or schema and to how the
+/// extension is displayed, but not a rename of the tool or the extension.
+pub fn stable_tool_id(extension_key: &str, tool_name: &str) -> String {
+    let digest = Sha256::digest(format!("{}__{}", extension_key, tool_name).as_bytes());
+    let short: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}{}", TOOL_ID_PREFIX, short)
+}
+
+/// The budget tools stay callable once the budget runs out, so it can be inspected and raised
+fn is_tool_budget_exempt(client_name: &str, tool_name: &str) -> bool {
+    client_name == name_to_key(extension_manager_extension::EXTENSION_NAME)
+        && [
+            extension_manager_extension::SET_TOOL_BUDGET_TOOL_NAME,
+            extension_manager_extension::GET_TOOL_BUDGET_TOOL_NAME,
+        ]
+        .contains(&tool_name)
+}
+
+/// A template's variables for a listing, e.g. "path, rev (optional)"
+fn describe_template_variables(uri_template: &str) -> String {
+    match template_variables(uri_template) {
+        Ok(variables) if variables.is_empty() => "none".to_string(),
+        Ok(variables) => variables
+            .iter()
+            .map(|variable| match variable.required {
+                true => variable.name.clone(),
+                false => format!("{} (optional)", variable.name),
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+        Err(_) => "invalid template".to_string(),
+    }
+}
+
+/// Whether a template can produce URIs starting with `prefix`, judging by the literal text
+/// before its first expression
+fn template_matches_prefix(uri_template: &str, prefix: &str) -> bool {
+    let literal = uri_template
+        .split_once('{')
+        .map_or(uri_template, |(literal, _)| literal);
+    literal.starts_with(prefix) || prefix.starts_with(literal)
+}
+
+/// Keep a value from breaking out of its markdown table cell
+fn escape_table_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', " ")
+}
+
+/// Keep only the contents with the given MIME type, or None if there are none left
+fn with_mime_type(
+    mut read_result: rmcp::model::ReadResourceResult,
+    mime_type: Option<&str>,
+) -> Option<rmcp::model::ReadResourceResult> {
+    if let Some(mime_type) = mime_type {
+        read_result
+            .contents
+            .retain(|content| resource_contents_mime_type(content) == Some(mime_type));
+    }
+    (!read_result.contents.is_empty()).then_some(read_result)
+}
+
+fn resource_contents_mime_type(content: &ResourceContents) -> Option<&str> {
+    match content {
+        ResourceContents::TextResourceContents { mime_type, .. }
+        | ResourceContents::BlobResourceContents { mime_type, .. } => mime_type.as_deref(),
+    }
+}
+
+/// Run `operation` while passing on the progress notifications an extension sends in the
+/// meantime, so a platform tool waiting on the extension can report its progress
+async fn relay_progress<T>(
+    mut notifications: mpsc::Receiver<ServerNotification>,
+    progress: &broadcast::Sender<ServerNotification>,
+    operation: impl std::future::Future<Output = T>,
+) -> T {
+    let forward = |notification| {
+        if let ServerNotification::ProgressNotification(_) = notification {
+            // Nobody may be listening, which is fine
+            let _ = progress.send(notification);
+        }
+    };
+
+    tokio::pin!(operation);
+    let result = loop {
+        tokio::select! {
+            result = &mut operation => break result,
+            Some(notification) = notifications.recv() => forward(notification),
+        }
+    };
+    // Progress sent just before the operation finished may still be queued
+    while let Ok(notification) = notifications.try_recv() {
+        forward(notification);
+    }
+    result
+}
+
+fn resource_read_cancelled(uri: &str) -> ErrorData {
+    ErrorData::new(
+        ErrorCode::INTERNAL_ERROR,
+        format!("Reading resource {} was cancelled", uri),
+        None,
+    )
+}
+
+/// The config's type tag, e.g. "builtin", "stdio", or "streamable_http"
+fn extension_category(config: &ExtensionConfig) -> String {
+    serde_json::to_value(config)
+        .ok()
+        .and_then(|value| value.get("type")?.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// How well an extension matches a keyword query: each query word found in the name counts
+/// twice, each found in the description once. Zero means no match.
+fn extension_match_score(query: &str, name: &str, description: &str) -> usize {
+    let name = name.to_lowercase();
+    let description = description.to_lowercase();
+    query
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .map(|word| {
+            2 * usize::from(name.contains(&word)) + usize::from(description.contains(&word))
+        })
+        .sum()
+}
+
+/// Method of the notifications that carry the chunks of a streamed resource
+pub const RESOURCE_CHUNK_NOTIFICATION_METHOD: &str = "notifications/resource_chunk";
+
+/// Chunk size for streamed resources when the caller doesn't pick one
+pub const DEFAULT_RESOURCE_CHUNK_BYTES: usize = 64 * 1024;
+
+/// Send one chunk of a streamed resource, paired with the extension it was requested from,
+/// waiting while the buffer is full. Returns false once the stream should stop, because
+/// nobody takes chunks or the buffer stayed full too long.
+async fn send_resource_chunk(
+    chunks: &mpsc::Sender<ResourceChunk>,
+    stream_id: &str,
+    extension_name: Option<&str>,
+    params: Value,
+) -> bool {
+    let notification = ServerNotification::CustomNotification(CustomNotification::new(
+        RESOURCE_CHUNK_NOTIFICATION_METHOD,
+        Some(params),
+    ));
+    match chunks
+        .send_timeout(
+            (extension_name.map(str::to_string), notification),
+            RESOURCE_STREAM_IDLE_TIMEOUT,
+        )
+        .await
+    {
+        Ok(()) => true,
+        Err(e) => {
+            warn!(stream_id = %stream_id, "Abandoning resource stream: {}", e);
+            false
+        }
+    }
+}
+
+/// Split `data` into pieces of at most `chunk_bytes` bytes, cutting only at char boundaries.
+/// A piece is longer only when a single char doesn't fit.
+fn chunk_text(data: &str, chunk_bytes: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < data.len() {
+        let mut end = floor_char_boundary(data, start.saturating_add(chunk_bytes));
+        if end <= start {
+            end = (start + 1..=data.len())
+                .find(|&i| data.is_char_boundary(i))
+                .unwrap_or(data.len());
+        }
+        chunks.push(data.get(start..end).unwrap_or_default());
+        start = end;
+    }
+    chunks
+}
+
+/// How long list_resources waits for each extension by default when listing all of them
+const DEFAULT_RESOURCE_LISTING_TIMEOUT_MS: u64 = 5000;
+/// How many extensions list_resources queries at once
+const MAX_CONCURRENT_RESOURCE_LISTINGS: usize = 8;
+
+/// How many resources prefetch_resources reads at once
+const MAX_CONCURRENT_RESOURCE_PREFETCHES: usize = 8;
+
+/// Compile an extension name pattern: `/.../` is a regex, anything else a glob where `*`
+/// matches any run of characters and `?` a single one
+fn compile_name_pattern(pattern: &str) -> Result<regex::Regex, regex::Error> {
+    if let Some(regex) = pattern
+        .strip_prefix('/')
+        .and_then(|rest| rest.strip_suffix('/'))
+    {
+        return regex::Regex::new(regex);
+    }
+
+    let mut regex = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex::Regex::new(&regex)
+}
+
+type ObservedErrors = Arc<Mutex<HashMap<String, BTreeMap<ToolErrorCategory, usize>>>>;
+
+/// Cancellation tokens of the calls currently dispatched to an extension
+#[derive(Default)]
+struct InFlightCalls {
+    next_id: AtomicU64,
+    tokens: std::sync::Mutex<HashMap<u64, CancellationToken>>,
+}
+
+impl InFlightCalls {
+    fn register(self: &Arc<Self>, token: CancellationToken) -> InFlightCallGuard {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        self.tokens
+            .lock()
+            .expect("in-flight calls poisoned")
+            .insert(id, token);
+        InFlightCallGuard {
+            calls: Arc::clone(self),
+            id,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.tokens.lock().map(|tokens| tokens.len()).unwrap_or(0)
+    }
+
+    fn cancel_all(&self) -> usize {
+        let tokens: Vec<_> = self
+            .tokens
+            .lock()
+            .expect("in-flight calls poisoned")
+            .drain()
+            .map(|(_, token)| token)
+            .collect();
+        for token in &tokens {
+            token.cancel();
+        }
+        tokens.len()
+    }
+}
+
+/// Unregisters a call from `InFlightCalls` when it finishes or is dropped
+struct InFlightCallGuard {
+    calls: Arc<InFlightCalls>,
+    id: u64,
+}
+
+impl Drop for InFlightCallGuard {
+    fn drop(&mut self) {
+        if let Ok(mut tokens) = self.calls.tokens.lock() {
+            tokens.remove(&self.id);
+        }
+    }
+}
 
 struct Extension {
     pub config: ExtensionConfig,
 
     client: McpClientBox,
     server_info: Option<ServerInfo>,
+    call_permits: Arc<PrioritySemaphore>,
+    crashed: Arc<AtomicBool>,
+    breaker: Arc<CircuitBreaker>,
+    in_flight: Arc<InFlightCalls>,
+    enabled_at: DateTime<Utc>,
+    /// Time of the last successful tool call
+    last_used: Arc<std::sync::Mutex<Option<DateTime<Utc>>>>,
+    /// How the most recent tool call failed, or None if it succeeded
+    last_call_error: Arc<std::sync::Mutex<Option<ToolErrorCategory>>>,
+    /// Tools this session narrowed the extension to with max_tools or allow/deny. Kept out of
+    /// the config so it isn't persisted or mistaken for an edit of the stored config.
+    tool_restriction: Option<Vec<String>>,
     _temp_dir: Option<tempfile::TempDir>,
 }
 
+fn restriction_allows(restriction: &Option<Vec<String>>, tool_name: &str) -> bool {
+    restriction
+        .as_ref()
+        .is_none_or(|tools| tools.iter().any(|tool| tool == tool_name))
+}
+
 impl Extension {
     fn new(
         config: ExtensionConfig,
@@ -71,10 +776,27 @@ impl Extension {
             client,
             config,
             server_info,
+            // A call holds the client lock while it runs, so calls to one extension run one
+            // at a time anyway. A single permit makes the waiting calls go in priority order
+            // rather than in the lock's arrival order.
+            call_permits: PrioritySemaphore::new(1),
+            crashed: Arc::new(AtomicBool::new(false)),
+            breaker: Arc::new(CircuitBreaker::default()),
+            in_flight: Arc::new(InFlightCalls::default()),
+            enabled_at: Utc::now(),
+            last_used: Arc::new(std::sync::Mutex::new(None)),
+            last_call_error: Arc::new(std::sync::Mutex::new(None)),
+            tool_restriction: None,
             _temp_dir: temp_dir,
         }
     }
 
+    /// Whether a tool is allowed by the config and by any restriction set this session
+    fn is_tool_available(&self, tool_name: &str) -> bool {
+        self.config.is_tool_available(tool_name)
+            && restriction_allows(&self.tool_restriction, tool_name)
+    }
+
     fn supports_resources(&self) -> bool {
         self.server_info
             .as_ref()
@@ -82,6 +804,19 @@ impl Extension {
             .is_some()
     }
 
+    fn supports_resource_subscriptions(&self) -> bool {
+        self.server_info.as_ref().is_some_and(|info| {
+            CapabilityFlags::from_server_capabilities(&info.capabilities).resource_subscriptions
+        })
+    }
+
+    fn supports_resource_writes(&self) -> bool {
+        self.server_info
+            .as_ref()
+            .and_then(|info| info.capabilities.experimental.as_ref())
+            .is_some_and(|experimental| experimental.contains_key(WRITE_RESOURCE_METHOD))
+    }
+
     fn get_instructions(&self) -> Option<String> {
         self.server_info
             .as_ref()
@@ -100,6 +835,20 @@ pub struct ExtensionManager {
     provider: SharedProvider,
     tools_cache: Mutex<Option<Arc<Vec<Tool>>>>,
     tools_cache_version: AtomicU64,
+    stored_results: StoredResults,
+    /// Lifecycle notifications, such as an extension crashing, paired with the extension
+    lifecycle_tx: broadcast::Sender<(String, ServerNotification)>,
+    resource_hashes: Mutex<HashMap<String, String>>,
+    observed_errors: ObservedErrors,
+    /// Names shown to the agent in place of an extension's key, by key
+    aliases: std::sync::Mutex<HashMap<String, String>>,
+    resource_watches: ResourceWatches,
+    /// Updates from watched resources, paired with the extension they come from
+    resource_updates: broadcast::Sender<(String, ServerNotification)>,
+    resource_streams: ResourceStreams,
+    resource_chunks: mpsc::Sender<ResourceChunk>,
+    /// The receiving end of `resource_chunks`, until the agent takes it
+    resource_chunk_receiver: std::sync::Mutex<Option<mpsc::Receiver<ResourceChunk>>>,
 }
 
 /// A flattened representation of a resource used by the agent to prepare inference
@@ -170,22 +919,152 @@ fn resolve_command(cmd: &str) -> PathBuf {
         })
 }
 
-fn require_str_parameter<'a>(v: &'a serde_json::Value, name: &str) -> Result<&'a str, ErrorData> {
-    let v = v.get(name).ok_or_else(|| {
-        ErrorData::new(
-            ErrorCode::INVALID_PARAMS,
-            format!("The parameter {name} is required"),
-            None,
-        )
-    })?;
-    match v.as_str() {
-        Some(r) => Ok(r),
-        None => Err(ErrorData::new(
-            ErrorCode::INVALID_PARAMS,
-            format!("The parameter {name} must be a string"),
-            None,
+/// Runs the checks `add_extension` performs on a config before any process or connection is
+/// started, so configs can be validated without enabling them.
+#[allow(clippy::result_large_err)]
+pub fn validate_extension_config(config: &ExtensionConfig) -> ExtensionResult<()> {
+    match config {
+        ExtensionConfig::Sse { .. } => Err(ExtensionError::ConfigError(
+            "SSE is unsupported, migrate to streamable_http".to_string(),
+        )),
+        ExtensionConfig::StreamableHttp { uri, headers, .. } => {
+            let url = url::Url::parse(uri).map_err(|e| {
+                ExtensionError::ConfigError(format!("invalid uri '{}': {}", uri, e))
+            })?;
+            if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+                return Err(ExtensionError::ConfigError(format!(
+                    "uri '{}' must be an http or https URL with a host",
+                    uri
+                )));
+            }
+            for (key, value) in headers {
+                HeaderName::try_from(key)
+                    .map_err(|_| ExtensionError::ConfigError(format!("invalid header: {}", key)))?;
+                value.parse::<reqwest::header::HeaderValue>().map_err(|_| {
+                    ExtensionError::ConfigError(format!("invalid header value: {}", key))
+                })?;
+            }
+            Ok(())
+        }
+        ExtensionConfig::Builtin { name, .. } => get_builtin_extension(&name_to_key(name))
+            .map(|_| ())
+            .ok_or_else(|| {
+                ExtensionError::ConfigError(format!("Unknown builtin extension: {}", name))
+            }),
+        ExtensionConfig::Platform { name, .. } => {
+            if PLATFORM_EXTENSIONS.contains_key(name_to_key(name).as_str()) {
+                Ok(())
+            } else {
+                Err(ExtensionError::ConfigError(format!(
+                    "Unknown platform extension: {}",
+                    name
+                )))
+            }
+        }
+        ExtensionConfig::Frontend { .. } => Err(ExtensionError::ConfigError(
+            "Invalid extension type: Frontend extensions cannot be added as server extensions"
+                .to_string(),
         )),
+        ExtensionConfig::Stdio { cmd, .. } => {
+            if cmd.trim().is_empty() {
+                Err(ExtensionError::ConfigError(
+                    "stdio extension has an empty command".to_string(),
+                ))
+            } else {
+                Ok(())
+            }
+        }
+        ExtensionConfig::InlinePython { code, .. } => {
+            if code.trim().is_empty() {
+                Err(ExtensionError::ConfigError(
+                    "inline_python extension has no code".to_string(),
+                ))
+            } else {
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Builds the `platform_event` notification used for extension lifecycle changes
+fn extension_lifecycle_notification(extension_name: &str, event_type: &str) -> ServerNotification {
+    ServerNotification::CustomNotification(CustomNotification::new(
+        "platform_event",
+        Some(serde_json::json!({
+            "extension": extension_name,
+            "event_type": event_type,
+        })),
+    ))
+}
+
+fn extension_crashed_error(extension_name: &str) -> ErrorData {
+    ErrorData::new(
+        ErrorCode::INTERNAL_ERROR,
+        format!(
+            "Extension '{}' stopped responding and appears to have crashed. \
+             Disable and re-enable it with manage_extensions to restart it.",
+            extension_name
+        ),
+        Some(serde_json::json!({ "extension": extension_name, "crashed": true })),
+    )
+}
+
+fn circuit_open_error(extension_name: &str, retry_in: Duration) -> ErrorData {
+    ErrorData::new(
+        ErrorCode::INTERNAL_ERROR,
+        format!(
+            "Extension '{}' failed several calls in a row, so calls to it are paused for \
+             another {} seconds.",
+            extension_name,
+            retry_in.as_secs().max(1)
+        ),
+        Some(serde_json::json!({ "extension": extension_name, "circuit_open": true })),
+    )
+}
+
+/// Mark an extension as crashed, open its circuit breaker and announce the crash to
+/// lifecycle subscribers
+fn mark_extension_crashed(
+    extension_name: &str,
+    crashed: Option<&AtomicBool>,
+    breaker: Option<&CircuitBreaker>,
+    lifecycle_tx: &broadcast::Sender<(String, ServerNotification)>,
+) -> ErrorData {
+    if let Some(crashed) = crashed {
+        crashed.store(true, Ordering::SeqCst);
+    }
+    if let Some(breaker) = breaker {
+        breaker.trip();
+    }
+    warn!(extension = %extension_name, "Extension crashed during tool call");
+    let _ = lifecycle_tx.send((
+        extension_name.to_string(),
+        extension_lifecycle_notification(extension_name, "extension_crashed"),
+    ));
+    extension_crashed_error(extension_name)
+}
+
+/// Stable hash over a resource's contents, covering both text and blob parts
+fn hash_resource_contents(contents: &[ResourceContents]) -> String {
+    let mut hasher = Sha256::new();
+    for content in contents {
+        match content {
+            ResourceContents::TextResourceContents { text, .. } => hasher.update(text.as_bytes()),
+            ResourceContents::BlobResourceContents { blob, .. } => hasher.update(blob.as_bytes()),
+        }
     }
+    format!("{:x}", hasher.finalize())
+}
+
+fn take_tool_call_priority(
+    meta: Option<&Meta>,
+    arguments: Option<&mut JsonObject>,
+) -> ToolCallPriority {
+    let from_args = arguments.and_then(|args| args.remove(PRIORITY_ARG));
+    meta.and_then(|meta| meta.0.get(PRIORITY_ARG).cloned())
+        .or(from_args)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default()
 }
 
 pub fn get_parameter_names(tool: &Tool) -> Vec<String> {
@@ -199,6 +1078,38 @@ pub fn get_parameter_names(tool: &Tool) -> Vec<String> {
     names
 }
 
+async fn store_tool_result(
+    stored_results: &StoredResults,
+    session_id: &str,
+    result: CallToolResult,
+) -> CallToolResult {
+    let handle = format!("result_{}", Uuid::new_v4().simple());
+    let item_count = result.content.len();
+    let char_count: usize = result
+        .content
+        .iter()
+        .filter_map(|c| c.as_text())
+        .map(|t| t.text.chars().count())
+        .sum();
+
+    stored_results
+        .lock()
+        .await
+        .entry(session_id.to_string())
+        .or_insert_with(|| {
+            LruCache::new(
+                NonZeroUsize::new(MAX_STORED_RESULTS_PER_SESSION).expect("capacity is non-zero"),
+            )
+        })
+        .put(handle.clone(), result);
+
+    CallToolResult::success(vec![Content::text(format!(
+        "The result ({} content items, {} characters of text) was stored with handle '{}'. \
+         Use fetch_result with this handle to retrieve it.",
+        item_count, char_count, handle
+    ))])
+}
+
 async fn child_process_client(
     mut command: Command,
     timeout: &Option<u64>,
@@ -333,34 +1244,6 @@ async fn merge_environments(
     Ok(all_envs)
 }
 
-/// Substitute environment variables in a string. Supports both ${VAR} and $VAR syntax.
-fn substitute_env_vars(value: &str, env_map: &HashMap<String, String>) -> String {
-    let mut result = value.to_string();
-
-    let re_braces =
-        regex::Regex::new(r"\$\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}").expect("valid regex");
-    for cap in re_braces.captures_iter(value) {
-        if let Some(var_name) = cap.get(1) {
-            if let Some(env_value) = env_map.get(var_name.as_str()) {
-                result = result.replace(&cap[0], env_value);
-            }
-        }
-    }
-
-    let re_simple = regex::Regex::new(r"\$([A-Za-z_][A-Za-z0-9_]*)").expect("valid regex");
-    for cap in re_simple.captures_iter(&result.clone()) {
-        if let Some(var_name) = cap.get(1) {
-            if !value.contains(&format!("${{{}}}", var_name.as_str())) {
-                if let Some(env_value) = env_map.get(var_name.as_str()) {
-                    result = result.replace(&cap[0], env_value);
-                }
-            }
-        }
-    }
-
-    result
-}
-
 const GOOSE_USER_AGENT: reqwest::header::HeaderValue =
     reqwest::header::HeaderValue::from_static(concat!("goose/", env!("CARGO_PKG_VERSION")));
 
@@ -377,7 +1260,12 @@ async fn create_streamable_http_client(
     default_headers.insert(reqwest::header::USER_AGENT, GOOSE_USER_AGENT);
 
     for (key, value) in headers {
-        let substituted_value = substitute_env_vars(value, all_envs);
+        let substituted_value = substitute_variables(
+            value,
+            true,
+            |name| all_envs.get(name).cloned(),
+            &mut BTreeSet::new(),
+        );
         default_headers.insert(
             HeaderName::try_from(key)
                 .map_err(|_| ExtensionError::ConfigError(format!("invalid header: {}", key)))?,
@@ -438,15 +1326,42 @@ impl ExtensionManager {
         provider: SharedProvider,
         session_manager: Arc<crate::session::SessionManager>,
     ) -> Self {
+        let (resource_chunks, resource_chunk_receiver) = mpsc::channel(RESOURCE_STREAM_BUFFER);
         Self {
             extensions: Mutex::new(HashMap::new()),
             context: PlatformExtensionContext {
                 extension_manager: None,
                 session_manager,
+                tool_budgets: ToolBudgets::default(),
+                resource_listings: ResourceListings::default(),
+                resource_cache: ResourceCache::default(),
+                extension_audit_log: ExtensionAuditLog::default(),
+                config_variables: ConfigVariables::new(
+                    Config::global()
+                        .get_param("GOOSE_EXTENSION_VARIABLES")
+                        .unwrap_or_default(),
+                ),
+                result_format: Config::global()
+                    .get_param::<ResultFormat>("GOOSE_PLATFORM_RESULT_FORMAT")
+                    .unwrap_or_default(),
+                tool_rate_limit: Config::global()
+                    .get_param::<ToolRateLimit>("GOOSE_PLATFORM_TOOL_RATE_LIMIT")
+                    .ok(),
+                notifications: broadcast::channel(16).0,
             },
             provider,
             tools_cache: Mutex::new(None),
             tools_cache_version: AtomicU64::new(0),
+            stored_results: Arc::new(Mutex::new(HashMap::new())),
+            lifecycle_tx: broadcast::channel(16).0,
+            resource_hashes: Mutex::new(HashMap::new()),
+            observed_errors: Arc::new(Mutex::new(HashMap::new())),
+            aliases: std::sync::Mutex::new(HashMap::new()),
+            resource_watches: Arc::new(std::sync::Mutex::new(HashSet::new())),
+            resource_updates: broadcast::channel(RESOURCE_UPDATE_BUFFER).0,
+            resource_streams: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            resource_chunks,
+            resource_chunk_receiver: std::sync::Mutex::new(Some(resource_chunk_receiver)),
         }
     }
 
@@ -464,6 +1379,30 @@ impl ExtensionManager {
         &self.provider
     }
 
+    /// Subscribe to extension lifecycle notifications, such as an extension crashing mid-call,
+    /// each paired with the extension it is about. The agent holds a subscription for as
+    /// long as it lives and passes them on to the client.
+    pub fn subscribe_lifecycle(&self) -> broadcast::Receiver<(String, ServerNotification)> {
+        self.lifecycle_tx.subscribe()
+    }
+
+    /// Subscribe to the resource updated notifications of watched resources, each paired
+    /// with the extension it comes from. Watches last while anyone is subscribed; the agent
+    /// holds a subscription for as long as it lives.
+    pub fn subscribe_resource_updates(&self) -> broadcast::Receiver<(String, ServerNotification)> {
+        self.resource_updates.subscribe()
+    }
+
+    /// Take the receiver of streamed resource chunks, each paired with the extension it was
+    /// requested from. There is one receiver, which the agent takes when it is created;
+    /// resources can't be streamed until it has been taken.
+    pub fn take_resource_chunks(&self) -> Option<mpsc::Receiver<ResourceChunk>> {
+        self.resource_chunk_receiver
+            .lock()
+            .expect("resource chunk receiver poisoned")
+            .take()
+    }
+
     pub async fn supports_resources(&self) -> bool {
         self.extensions
             .lock()
@@ -472,15 +1411,45 @@ impl ExtensionManager {
             .any(|ext| ext.supports_resources())
     }
 
+    pub async fn supports_resource_writes(&self) -> bool {
+        self.extensions
+            .lock()
+            .await
+            .values()
+            .any(|ext| ext.supports_resource_writes())
+    }
+
     /// Add an extension with an optional working directory.
     /// If working_dir is None, falls back to current_dir.
-    #[allow(clippy::too_many_lines)]
     pub async fn add_extension(
         self: &Arc<Self>,
         config: ExtensionConfig,
         working_dir: Option<PathBuf>,
         container: Option<&Container>,
         session_id: Option<&str>,
+    ) -> ExtensionResult<()> {
+        let transport_config = config.clone();
+        self.add_extension_with_transport(
+            config,
+            transport_config,
+            working_dir,
+            container,
+            session_id,
+        )
+        .await
+    }
+
+    /// Add an extension that connects with `transport_config` but is stored as `config`, so
+    /// values resolved into the transport config, which may be secrets, aren't persisted
+    /// with the session or compared when reloading.
+    #[allow(clippy::too_many_lines)]
+    pub async fn add_extension_with_transport(
+        self: &Arc<Self>,
+        config: ExtensionConfig,
+        transport_config: ExtensionConfig,
+        working_dir: Option<PathBuf>,
+        container: Option<&Container>,
+        session_id: Option<&str>,
     ) -> ExtensionResult<()> {
         let config_name = config.key().to_string();
         let sanitized_name = name_to_key(&config_name);
@@ -489,13 +1458,15 @@ impl ExtensionManager {
             return Ok(());
         }
 
+        validate_extension_config(&transport_config)?;
+
         // Resolve working_dir: explicit > current_dir
         let effective_working_dir =
             working_dir.unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
 
         let mut temp_dir = None;
 
-        let client: Box<dyn McpClientTrait> = match &config {
+        let client: Box<dyn McpClientTrait> = match &transport_config {
             ExtensionConfig::Sse { .. } => {
                 return Err(ExtensionError::ConfigError(
                     "SSE is unsupported, migrate to streamable_http".to_string(),
@@ -720,14 +1691,120 @@ impl ExtensionManager {
             .collect()
     }
 
+    /// The instructions an enabled extension sent when it connected. Returns None if the
+    /// extension is not enabled, and Some(None) if it sent no instructions.
+    pub async fn get_extension_instructions(&self, name: &str) -> Option<Option<String>> {
+        let normalized = self.resolve_extension_name(name);
+        self.extensions.lock().await.get(&normalized).map(|ext| {
+            ext.get_instructions()
+                .filter(|text| !text.trim().is_empty())
+        })
+    }
+
     /// Get aggregated usage statistics
     pub async fn remove_extension(&self, name: &str) -> ExtensionResult<()> {
-        let sanitized_name = name_to_key(name);
+        let sanitized_name = self.resolve_extension_name(name);
         self.extensions.lock().await.remove(&sanitized_name);
+        self.aliases
+            .lock()
+            .expect("aliases poisoned")
+            .remove(&sanitized_name);
+        self.context
+            .resource_listings
+            .forget_extension(&sanitized_name);
+        self.context
+            .resource_cache
+            .forget_extension(&sanitized_name);
+        // Streams from the extension stop, and drop the contents they hold
+        self.resource_streams
+            .lock()
+            .expect("resource streams poisoned")
+            .retain(|_, (extension, task)| {
+                let keep = extension.as_deref() != Some(sanitized_name.as_str());
+                if !keep {
+                    task.abort();
+                }
+                keep
+            });
         self.invalidate_tools_cache_and_bump_version().await;
         Ok(())
     }
 
+    /// Show an enabled extension to the agent under another name. Its tools are prefixed with
+    /// the alias and `manage_extensions` accepts either name, while config lookups still use
+    /// the extension's own key. Passing None restores the original name.
+    pub async fn set_extension_alias(
+        &self,
+        extension_name: &str,
+        alias: Option<&str>,
+    ) -> ExtensionResult<()> {
+        let key = self.resolve_extension_name(extension_name);
+        let extensions = self.extensions.lock().await;
+        if !extensions.contains_key(&key) {
+            return Err(ExtensionError::ConfigError(format!(
+                "Extension '{}' is not enabled",
+                extension_name
+            )));
+        }
+
+        {
+            let mut aliases = self.aliases.lock().expect("aliases poisoned");
+            match alias.map(name_to_key) {
+                None => {
+                    aliases.remove(&key);
+                }
+                Some(alias) if alias.is_empty() || alias.contains("__") => {
+                    return Err(ExtensionError::ConfigError(format!(
+                        "'{}' is not a valid extension alias",
+                        alias
+                    )));
+                }
+                Some(alias) => {
+                    let taken = (alias != key && extensions.contains_key(&alias))
+                        || aliases
+                            .iter()
+                            .any(|(other, existing)| *other != key && *existing == alias);
+                    if taken {
+                        return Err(ExtensionError::ConfigError(format!(
+                            "The name '{}' is already used by another extension",
+                            alias
+                        )));
+                    }
+                    aliases.insert(key, alias);
+                }
+            }
+        }
+        drop(extensions);
+
+        self.invalidate_tools_cache_and_bump_version().await;
+        Ok(())
+    }
+
+    /// The key of the extension known to the agent as `name`, which may be an alias
+    pub fn resolve_extension_name(&self, name: &str) -> String {
+        let normalized = name_to_key(name);
+        self.aliased_key(&normalized).unwrap_or(normalized)
+    }
+
+    fn aliased_key(&self, alias: &str) -> Option<String> {
+        self.aliases
+            .lock()
+            .expect("aliases poisoned")
+            .iter()
+            .find(|(_, existing)| *existing == alias)
+            .map(|(key, _)| key.clone())
+    }
+
+    /// The name the agent sees for an extension key
+    fn display_name(&self, key: &str) -> String {
+        self.aliases
+            .lock()
+            .expect("aliases poisoned")
+            .get(key)
+            .cloned()
+            .unwrap_or_else(|| key.to_string())
+    }
+
     pub async fn get_extension_and_tool_counts(&self, session_id: &str) -> (usize, usize) {
         let enabled_extensions_count = self.extensions.lock().await.len();
 
@@ -744,8 +1821,252 @@ impl ExtensionManager {
         Ok(self.extensions.lock().await.keys().cloned().collect())
     }
 
+    /// Start an extension in a throwaway manager and list the tools it would add, leaving the
+    /// active set untouched. The extension is shut down again before this returns.
+    pub async fn preview_extension_tools(
+        &self,
+        session_id: &str,
+        config: ExtensionConfig,
+    ) -> ExtensionResult<Vec<Tool>> {
+        let preview = Arc::new(ExtensionManager::new(
+            self.provider.clone(),
+            Arc::clone(&self.context.session_manager),
+        ));
+        preview.add_extension(config, None, None, None).await?;
+        preview.fetch_all_tools(session_id).await
+    }
+
+    /// Runtime state of every enabled extension, sorted by name
+    pub async fn extension_statuses(&self) -> Vec<ExtensionStatus> {
+        let observed_errors = self.observed_errors.lock().await.clone();
+        let min_protocol_version = min_extension_protocol_version();
+        let mut statuses: Vec<ExtensionStatus> = self
+            .extensions
+            .lock()
+            .await
+            .iter()
+            .map(|(name, extension)| {
+                let info = extension.server_info.as_ref();
+                let protocol_version = info.map(|info| info.protocol_version.to_string());
+                ExtensionStatus {
+                    name: name.clone(),
+                    server_name: info.map(|info| info.server_info.name.clone()),
+                    server_version: info.map(|info| info.server_info.version.clone()),
+                    protocol_outdated: protocol_version
+                        .as_deref()
+                        .is_some_and(|version| version < min_protocol_version.as_str()),
+                    protocol_version,
+                    capabilities: info
+                        .map(|info| CapabilityFlags::from_server_capabilities(&info.capabilities)),
+                    crashed: extension.crashed.load(Ordering::SeqCst),
+                    circuit_open: extension.breaker.is_open(),
+                    in_flight_calls: extension.in_flight.len(),
+                    last_used: *extension.last_used.lock().expect("last used poisoned"),
+                    last_call_error: extension
+                        .last_call_error
+                        .lock()
+                        .expect("last call error poisoned")
+                        .map(|category| category.name()),
+                    errors: observed_errors
+                        .get(name)
+                        .map(|errors| {
+                            errors
+                                .iter()
+                                .map(|(category, count)| (category.name(), *count))
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                }
+            })
+            .collect();
+        statuses.sort_by(|a, b| a.name.cmp(&b.name));
+        statuses
+    }
+
+    /// Advertised capabilities of each enabled extension, or None for an extension that
+    /// reported no server info
+    pub async fn get_capabilities(&self) -> BTreeMap<String, Option<CapabilityFlags>> {
+        self.extensions
+            .lock()
+            .await
+            .iter()
+            .map(|(name, extension)| {
+                let flags = extension
+                    .server_info
+                    .as_ref()
+                    .map(|info| CapabilityFlags::from_server_capabilities(&info.capabilities));
+                (name.clone(), flags)
+            })
+            .collect()
+    }
+
+    /// The names of the tools an enabled extension currently makes available, with its key
+    async fn available_tool_names(
+        &self,
+        session_id: &str,
+        name: &str,
+    ) -> Result<(String, Vec<String>), ErrorData> {
+        let (key, tools) = self.available_tools(session_id, name).await?;
+        let tool_names = tools
+            .into_iter()
+            .map(|tool| tool.name.to_string())
+            .collect();
+        Ok((key, tool_names))
+    }
+
+    /// The tools an enabled extension currently makes available, unprefixed, with its key
+    async fn available_tools(
+        &self,
+        session_id: &str,
+        name: &str,
+    ) -> Result<(String, Vec<Tool>), ErrorData> {
+        let key = self.resolve_extension_name(name);
+        let (config, restriction, client) = self
+            .extensions
+            .lock()
+            .await
+            .get(&key)
+            .map(|ext| {
+                (
+                    ext.config.clone(),
+                    ext.tool_restriction.clone(),
+                    ext.get_client(),
+                )
+            })
+            .ok_or_else(|| {
+                ErrorData::new(
+                    ErrorCode::RESOURCE_NOT_FOUND,
+                    format!("Extension '{}' is not enabled", name),
+                    None,
+                )
+            })?;
+
+        let cancel_token = CancellationToken::default();
+        let mut tools = Vec::new();
+        {
+            let client_guard = client.lock().await;
+            let mut cursor = None;
+            loop {
+                let page = client_guard
+                    .list_tools(session_id, cursor, cancel_token.clone())
+                    .await
+                    .map_err(|e| {
+                        ErrorData::new(
+                            ErrorCode::INTERNAL_ERROR,
+                            format!("Failed to list tools for '{}': {}", name, e),
+                            None,
+                        )
+                    })?;
+                tools.extend(page.tools.into_iter().filter(|tool| {
+                    config.is_tool_available(&tool.name)
+                        && restriction_allows(&restriction, &tool.name)
+                }));
+                cursor = page.next_cursor;
+                if cursor.is_none() {
+                    break;
+                }
+            }
+        }
+
+        Ok((key, tools))
+    }
+
+    /// The full definition of one of an enabled extension's available tools, as the
+    /// extension lists it
+    pub async fn describe_tool(
+        &self,
+        session_id: &str,
+        extension_name: &str,
+        tool_name: &str,
+    ) -> Result<Tool, ErrorData> {
+        let (key, tools) = self.available_tools(session_id, extension_name).await?;
+        let mut tool = tools
+            .into_iter()
+            .find(|tool| tool.name == tool_name)
+            .ok_or_else(|| {
+                ErrorData::new(
+                    ErrorCode::RESOURCE_NOT_FOUND,
+                    format!(
+                        "Extension '{}' has no tool named '{}'",
+                        extension_name, tool_name
+                    ),
+                    None,
+                )
+            })?;
+        // Same id as in the agent's tool list, so either can be used to call it
+        tool.meta.get_or_insert_with(Default::default).0.insert(
+            TOOL_ID_META_KEY.to_string(),
+            stable_tool_id(&key, tool_name).into(),
+        );
+        Ok(tool)
+    }
+
+    /// Narrow an extension's available tools for the rest of its session, without touching
+    /// its config
+    async fn restrict_available_tools(&self, key: &str, tool_names: Vec<String>) {
+        if let Some(ext) = self.extensions.lock().await.get_mut(key) {
+            ext.tool_restriction = Some(tool_names);
+        }
+        self.invalidate_tools_cache_and_bump_version().await;
+    }
+
+    /// Keep only the first `max_tools` tools of an enabled extension.
+    ///
+    /// Returns the number of tools the extension offered when some were dropped, or `None`
+    /// if it was already within the limit.
+    pub async fn limit_extension_tools(
+        &self,
+        session_id: &str,
+        name: &str,
+        max_tools: usize,
+    ) -> Result<Option<usize>, ErrorData> {
+        let (key, mut tool_names) = self.available_tool_names(session_id, name).await?;
+        let total = tool_names.len();
+        if total <= max_tools {
+            return Ok(None);
+        }
+
+        tool_names.truncate(max_tools);
+        self.restrict_available_tools(&key, tool_names).await;
+        Ok(Some(total))
+    }
+
+    /// Hide an enabled extension's tools that are not in `allow`, when given, or that are in
+    /// `deny`; a tool in both is hidden. Hidden tools are left out of the tool list and
+    /// rejected when called. Returns the names of the hidden tools.
+    pub async fn filter_extension_tools(
+        &self,
+        session_id: &str,
+        name: &str,
+        allow: Option<&[String]>,
+        deny: &[String],
+    ) -> Result<Vec<String>, ErrorData> {
+        let (key, tool_names) = self.available_tool_names(session_id, name).await?;
+        let (permitted, hidden): (Vec<String>, Vec<String>) =
+            tool_names.into_iter().partition(|tool_name| {
+                allow.is_none_or(|allow| allow.contains(tool_name)) && !deny.contains(tool_name)
+            });
+        if hidden.is_empty() {
+            return Ok(hidden);
+        }
+        // Leaving none would make the extension pointless to keep enabled
+        if permitted.is_empty() {
+            return Err(ErrorData::new(
+                ErrorCode::INVALID_PARAMS,
+                format!(
+                    "allow_tools and deny_tools would leave '{}' with no tools",
+                    name
+                ),
+                None,
+            ));
+        }
+
+        self.restrict_available_tools(&key, permitted).await;
+        Ok(hidden)
+    }
+
     pub async fn is_extension_enabled(&self, name: &str) -> bool {
-        let normalized = name_to_key(name);
+        let normalized = self.resolve_extension_name(name);
         self.extensions.lock().await.contains_key(&normalized)
     }
 
@@ -758,7 +2079,7 @@ impl ExtensionManager {
             .collect()
     }
 
-    /// Get all tools from all clients with proper prefixing
+    /// Get all tools from all clients with proper prefixing, sorted by prefixed name
     pub async fn get_prefixed_tools(
         &self,
         session_id: &str,
@@ -783,8 +2104,11 @@ impl ExtensionManager {
         extension_name: Option<&str>,
         exclude: Option<&str>,
     ) -> Vec<Tool> {
-        let extension_name_normalized = extension_name.map(name_to_key);
-        let exclude_normalized = exclude.map(name_to_key);
+        // Tools are prefixed with the name the agent sees, which may be an alias
+        let extension_name_normalized =
+            extension_name.map(|name| self.display_name(&self.resolve_extension_name(name)));
+        let exclude_normalized =
+            exclude.map(|name| self.display_name(&self.resolve_extension_name(name)));
 
         tools
             .iter()
@@ -807,6 +2131,21 @@ impl ExtensionManager {
             .collect()
     }
 
+    /// Map a stable tool id back to the tool's current prefixed name
+    async fn resolve_tool_id(&self, session_id: &str, tool_id: &str) -> Option<String> {
+        let tools = self.get_all_tools_cached(session_id).await.ok()?;
+        tools
+            .iter()
+            .find(|tool| {
+                tool.meta
+                    .as_ref()
+                    .and_then(|meta| meta.0.get(TOOL_ID_META_KEY))
+                    .and_then(|id| id.as_str())
+                    == Some(tool_id)
+            })
+            .map(|tool| tool.name.to_string())
+    }
+
     async fn get_all_tools_cached(&self, session_id: &str) -> ExtensionResult<Arc<Vec<Tool>>> {
         {
             let cache = self.tools_cache.lock().await;
@@ -840,62 +2179,97 @@ impl ExtensionManager {
             .lock()
             .await
             .iter()
-            .map(|(name, ext)| (name.clone(), ext.config.clone(), ext.get_client()))
+            .map(|(name, ext)| {
+                (
+                    name.clone(),
+                    ext.config.clone(),
+                    ext.tool_restriction.clone(),
+                    ext.get_client(),
+                )
+            })
             .collect();
 
         let cancel_token = CancellationToken::default();
-        let client_futures = clients.into_iter().map(|(name, config, client)| {
-            let cancel_token = cancel_token.clone();
-            let ext_name = name.clone();
-            async move {
-                let mut tools = Vec::new();
-                let client_guard = client.lock().await;
-                let mut client_tools = match client_guard
-                    .list_tools(session_id, None, cancel_token.clone())
-                    .await
-                {
-                    Ok(t) => t,
-                    Err(e) => {
-                        warn!(extension = %ext_name, error = %e, "Failed to list tools");
-                        return (name, vec![]);
-                    }
-                };
-
-                loop {
-                    for tool in client_tools.tools {
-                        if config.is_tool_available(&tool.name) {
-                            tools.push(Tool {
-                                name: format!("{}__{}", name, tool.name).into(),
-                                description: tool.description,
-                                input_schema: tool.input_schema,
-                                annotations: tool.annotations,
-                                output_schema: tool.output_schema,
-                                icons: tool.icons,
-                                title: tool.title,
-                                meta: tool.meta,
-                            });
-                        }
-                    }
-
-                    if client_tools.next_cursor.is_none() {
-                        break;
-                    }
-
-                    client_tools = match client_guard
-                        .list_tools(session_id, client_tools.next_cursor, cancel_token.clone())
+        let client_futures = clients
+            .into_iter()
+            .map(|(name, config, restriction, client)| {
+                let prefix = self.display_name(&name);
+                let cancel_token = cancel_token.clone();
+                let ext_name = name.clone();
+                async move {
+                    let mut tools = Vec::new();
+                    let client_guard = client.lock().await;
+                    let mut client_tools = match client_guard
+                        .list_tools(session_id, None, cancel_token.clone())
                         .await
                     {
                         Ok(t) => t,
                         Err(e) => {
-                            warn!(extension = %ext_name, error = %e, "Failed to list tools (pagination)");
-                            break;
+                            warn!(extension = %ext_name, error = %e, "Failed to list tools");
+                            return (name, vec![]);
                         }
                     };
-                }
 
-                (name, tools)
-            }
-        });
+                    loop {
+                        for tool in client_tools.tools {
+                            if config.is_tool_available(&tool.name)
+                                && restriction_allows(&restriction, &tool.name)
+                            {
+                                let description =
+                                    match ToolDeprecation::from_meta(tool.meta.as_ref()) {
+                                        Some(deprecation) => Some(
+                                            format!(
+                                                "{} {}",
+                                                deprecation.notice(&prefix),
+                                                tool.description.as_deref().unwrap_or_default()
+                                            )
+                                            .trim_end()
+                                            .to_string()
+                                            .into(),
+                                        ),
+                                        None => tool.description,
+                                    };
+                                let mut meta = tool.meta.unwrap_or_default();
+                                meta.0.insert(
+                                    TOOL_ID_META_KEY.to_string(),
+                                    stable_tool_id(&name, &tool.name).into(),
+                                );
+                                tools.push(Tool {
+                                    name: format!("{}__{}", prefix, tool.name).into(),
+                                    description,
+                                    input_schema: tool.input_schema,
+                                    annotations: tool.annotations,
+                                    output_schema: tool.output_schema,
+                                    icons: tool.icons,
+                                    title: tool.title,
+                                    meta: Some(meta),
+                                });
+                            }
+                        }
+
+                        if client_tools.next_cursor.is_none() {
+                            break;
+                        }
+
+                        client_tools = match client_guard
+                            .list_tools(session_id, client_tools.next_cursor, cancel_token.clone())
+                            .await
+                        {
+                            Ok(t) => t,
+                            Err(e) => {
+                                warn!(
+                                    extension = %ext_name,
+                                    error = %e,
+                                    "Failed to list tools (pagination)"
+                                );
+                                break;
+                            }
+                        };
+                    }
+
+                    (name, tools)
+                }
+            });
 
         let results = future::join_all(client_futures).await;
 
@@ -903,6 +2277,8 @@ impl ExtensionManager {
         for (_, client_tools) in results {
             tools.extend(client_tools);
         }
+        // Extensions are kept in a HashMap; sort so the listing doesn't depend on its order
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
 
         Ok(tools)
     }
@@ -925,38 +2301,38 @@ impl ExtensionManager {
             .map(|(name, extension)| (name.clone(), extension.get_client()))
     }
 
-    // Function that gets executed for read_resource tool
-    pub async fn read_resource_tool(
+    /// Read a resource from the named extension, or from the first extension that has it
+    async fn find_resource(
         &self,
         session_id: &str,
-        params: Value,
+        uri: &str,
+        extension_name: Option<&str>,
+        mime_type: Option<&str>,
         cancellation_token: CancellationToken,
-    ) -> Result<Vec<Content>, ErrorData> {
-        let uri = require_str_parameter(&params, "uri")?;
-
-        let extension_name = params.get("extension_name").and_then(|v| v.as_str());
-
+    ) -> Result<rmcp::model::ReadResourceResult, ErrorData> {
         // If extension name is provided, we can just look it up
         if let Some(ext_name) = extension_name {
             let read_result = self
-                .read_resource(session_id, uri, ext_name, cancellation_token.clone())
+                .read_resource(session_id, uri, ext_name, cancellation_token)
                 .await?;
-
-            let mut result = Vec::new();
-            for content in read_result.contents {
-                if let ResourceContents::TextResourceContents { text, .. } = content {
-                    let content_str = format!("{}\n\n{}", uri, text);
-                    result.push(Content::text(content_str));
-                }
-            }
-            return Ok(result);
+            return with_mime_type(read_result, mime_type).ok_or_else(|| {
+                ErrorData::new(
+                    ErrorCode::RESOURCE_NOT_FOUND,
+                    format!(
+                        "Resource '{}' in {} has no {} representation",
+                        uri,
+                        ext_name,
+                        mime_type.unwrap_or_default()
+                    ),
+                    None,
+                )
+            });
         }
 
-        // If extension name is not provided, we need to search for the resource across all extensions
-        // Loop through each extension and try to read the resource, don't raise an error if the resource is not found
-        // TODO: do we want to find if a provided uri is in multiple extensions?
-        // currently it will return the first match and skip any others
-        let extension_names: Vec<String> = self
+        // If extension name is not provided, we need to search for the resource across all
+        // extensions. Not finding it in an extension isn't an error, but finding it in more
+        // than one is: the caller has to pick, by extension or by MIME type.
+        let mut extension_names: Vec<String> = self
             .extensions
             .lock()
             .await
@@ -964,26 +2340,41 @@ impl ExtensionManager {
             .filter(|(_name, ext)| ext.supports_resources())
             .map(|(name, _)| name.clone())
             .collect();
+        extension_names.sort();
 
+        let mut matches = Vec::new();
         for extension_name in extension_names {
             let read_result = self
                 .read_resource(session_id, uri, &extension_name, cancellation_token.clone())
                 .await;
-            match read_result {
-                Ok(read_result) => {
-                    let mut result = Vec::new();
-                    for content in read_result.contents {
-                        if let ResourceContents::TextResourceContents { text, .. } = content {
-                            let content_str = format!("{}\n\n{}", uri, text);
-                            result.push(Content::text(content_str));
-                        }
-                    }
-                    return Ok(result);
-                }
-                Err(_) => continue,
+            if let Some(read_result) = read_result
+                .ok()
+                .and_then(|read_result| with_mime_type(read_result, mime_type))
+            {
+                matches.push((extension_name, read_result));
+            }
+            if cancellation_token.is_cancelled() {
+                return Err(resource_read_cancelled(uri));
             }
         }
 
+        if matches.len() > 1 {
+            let candidates: Vec<&str> = matches.iter().map(|(name, _)| name.as_str()).collect();
+            return Err(ErrorData::new(
+                ErrorCode::INVALID_PARAMS,
+                format!(
+                    "Resource with uri '{}' is provided by several extensions: {}. Pass \
+                     extension_name or mime_type to choose one.",
+                    uri,
+                    candidates.join(", ")
+                ),
+                None,
+            ));
+        }
+        if let Some((_, read_result)) = matches.pop() {
+            return Ok(read_result);
+        }
+
         // None of the extensions had the resource so we raise an error
         let available_extensions = self
             .extensions
@@ -1005,51 +2396,527 @@ impl ExtensionManager {
         ))
     }
 
-    pub async fn read_resource(
-        &self,
+    /// Read `uris` concurrently in the background into the resource cache, where
+    /// `read_resource` finds them for `ttl`. A URI without `extension_name` is read from the
+    /// one extension that lists it. Failed reads are logged and left uncached, so a later
+    /// read_resource reports the error.
+    pub fn prefetch_resources(
+        self: &Arc<Self>,
+        session_id: &str,
+        uris: Vec<String>,
+        extension_name: Option<&str>,
+        ttl: Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        let manager = Arc::clone(self);
+        let session_id = session_id.to_string();
+        let extension_name = extension_name.map(|name| self.resolve_extension_name(name));
+        tokio::spawn(async move {
+            let prefetch_one = |uri: String| {
+                let manager = &manager;
+                let session_id = session_id.as_str();
+                let extension_name = extension_name.clone();
+                async move {
+                    let token = CancellationToken::new();
+                    let extension_name = match extension_name {
+                        Some(name) => name,
+                        None => manager
+                            .resource_provider(session_id, &uri, token.clone())
+                            .await
+                            .map_err(|e| (uri.clone(), e))?,
+                    };
+                    let result = manager
+                        .find_resource(session_id, &uri, Some(&extension_name), None, token)
+                        .await
+                        .map_err(|e| (uri.clone(), e))?;
+                    manager
+                        .context
+                        .resource_cache
+                        .insert(&uri, &extension_name, result, ttl);
+                    Ok::<(), (String, ErrorData)>(())
+                }
+            };
+
+            let mut prefetches = futures::stream::iter(uris.into_iter().map(prefetch_one))
+                .buffer_unordered(MAX_CONCURRENT_RESOURCE_PREFETCHES);
+            while let Some(result) = prefetches.next().await {
+                if let Err((uri, e)) = result {
+                    warn!(uri = %uri, error = %e.message, "Failed to prefetch resource");
+                }
+            }
+        })
+    }
+
+    /// Read a resource in the background and send it as `notifications/resource_chunk`
+    /// notifications of at most `chunk_bytes` bytes each, returning the stream id right away.
+    /// Each chunk carries the stream id, its `seq` out of `total`, and whether it is the
+    /// `last`; a failed read sends one notification with an `error` instead. The chunks go
+    /// to the receiver from `take_resource_chunks`, paired with `extension_name`.
+    ///
+    /// Extensions return a resource in one response, so it is held in memory once while its
+    /// chunks are sent. At most `RESOURCE_STREAM_BUFFER` chunks wait to be delivered, across
+    /// all streams; a stream pauses while the buffer is full. If it stays full for
+    /// `RESOURCE_STREAM_IDLE_TIMEOUT`, or the extension is disabled, the stream is abandoned
+    /// and its contents dropped. The client sees no `last` chunk then, and can re-read the
+    /// missing range with offset and length.
+    pub fn stream_resource(
+        self: &Arc<Self>,
         session_id: &str,
         uri: &str,
-        extension_name: &str,
-        cancellation_token: CancellationToken,
-    ) -> Result<rmcp::model::ReadResourceResult, ErrorData> {
-        let available_extensions = self
-            .extensions
+        extension_name: Option<&str>,
+        chunk_bytes: usize,
+    ) -> Result<String, ErrorData> {
+        let taken = self
+            .resource_chunk_receiver
             .lock()
-            .await
-            .keys()
-            .map(|s| s.as_str())
-            .collect::<Vec<&str>>()
-            .join(", ");
-        let error_msg = format!(
-            "Extension '{}' not found. Here are the available extensions: {}",
-            extension_name, available_extensions
-        );
+            .expect("resource chunk receiver poisoned")
+            .is_none();
+        if !taken || self.resource_chunks.is_closed() {
+            return Err(ErrorData::new(
+                ErrorCode::INTERNAL_ERROR,
+                "Nothing is receiving resource chunks, so the resource can't be streamed"
+                    .to_string(),
+                None,
+            ));
+        }
 
-        let client = self
-            .get_server_client(extension_name)
-            .await
-            .ok_or(ErrorData::new(ErrorCode::INVALID_PARAMS, error_msg, None))?;
+        let stream_id = format!("stream_{}", Uuid::new_v4().simple());
+        let manager = Arc::clone(self);
+        let chunks_tx = self.resource_chunks.clone();
+        let session_id = session_id.to_string();
+        let uri = uri.to_string();
+        let extension_key = extension_name.map(|name| self.resolve_extension_name(name));
+        let extension_name = extension_name.map(str::to_string);
+        let id = stream_id.clone();
+
+        // Hold the lock while spawning so the task can't finish and forget itself before it
+        // has been recorded
+        let mut streams = self
+            .resource_streams
+            .lock()
+            .expect("resource streams poisoned");
+        let task = tokio::spawn(async move {
+            let open_streams = Arc::clone(&manager.resource_streams);
+            let send = |params: Value| {
+                let chunks_tx = &chunks_tx;
+                let id = &id;
+                let extension_name = extension_name.as_deref();
+                async move { send_resource_chunk(chunks_tx, id, extension_name, params).await }
+            };
 
-        let client_guard = client.lock().await;
-        client_guard
-            .read_resource(session_id, uri, cancellation_token)
-            .await
-            .map_err(|_| {
-                ErrorData::new(
-                    ErrorCode::INTERNAL_ERROR,
-                    format!("Could not read resource with uri: {}", uri),
+            let result = manager
+                .find_resource(
+                    &session_id,
+                    &uri,
+                    extension_name.as_deref(),
                     None,
+                    CancellationToken::new(),
                 )
-            })
+                .await;
+            // The stream only needs the manager for the read
+            drop(manager);
+            match result {
+                Err(e) => {
+                    send(serde_json::json!({
+                        "streamId": id,
+                        "uri": uri,
+                        "error": e.message,
+                    }))
+                    .await;
+                }
+                Ok(result) => {
+                    let chunks: Vec<(&str, &Option<String>, &str)> = result
+                        .contents
+                        .iter()
+                        .flat_map(|contents| {
+                            let (encoding, mime_type, data) = match contents {
+                                ResourceContents::TextResourceContents {
+                                    mime_type, text, ..
+                                } => ("text", mime_type, text),
+                                ResourceContents::BlobResourceContents {
+                                    mime_type, blob, ..
+                                } => ("base64", mime_type, blob),
+                            };
+                            chunk_text(data, chunk_bytes)
+                                .into_iter()
+                                .map(move |data| (encoding, mime_type, data))
+                        })
+                        .collect();
+                    let total = chunks.len();
+                    if total == 0 {
+                        send(serde_json::json!({
+                            "streamId": id,
+                            "uri": uri,
+                            "seq": 0,
+                            "total": 0,
+                            "last": true,
+                        }))
+                        .await;
+                    }
+                    for (seq, (encoding, mime_type, data)) in chunks.into_iter().enumerate() {
+                        let sent = send(serde_json::json!({
+                            "streamId": id,
+                            "uri": uri,
+                            "seq": seq,
+                            "total": total,
+                            "last": seq + 1 == total,
+                            "encoding": encoding,
+                            "mimeType": mime_type,
+                            "data": data,
+                        }))
+                        .await;
+                        if !sent {
+                            break;
+                        }
+                    }
+                }
+            }
+
+            open_streams
+                .lock()
+                .expect("resource streams poisoned")
+                .remove(&id);
+        });
+        streams.insert(stream_id.clone(), (extension_key, task.abort_handle()));
+        Ok(stream_id)
     }
 
-    pub async fn get_ui_resources(
+    /// The URI at `index` in the session's latest listing of an extension's resources
+    fn listed_resource_uri(
         &self,
         session_id: &str,
-    ) -> Result<Vec<(String, Resource)>, ErrorData> {
-        let mut ui_resources = Vec::new();
-
-        let extensions_to_check: Vec<(String, McpClientBox)> = {
+        extension_name: Option<&str>,
+        index: usize,
+    ) -> Result<String, ErrorData> {
+        let extension_name = extension_name.ok_or_else(|| {
+            ErrorData::new(
+                ErrorCode::INVALID_PARAMS,
+                "extension_name is required when reading by index".to_string(),
+                None,
+            )
+        })?;
+        let listing = self
+            .context
+            .resource_listings
+            .get(session_id, &self.resolve_extension_name(extension_name))
+            .ok_or_else(|| {
+                ErrorData::new(
+                    ErrorCode::INVALID_REQUEST,
+                    format!(
+                        "No current listing of resources from '{}'; call list_resources again \
+                         before reading by index",
+                        extension_name
+                    ),
+                    None,
+                )
+            })?;
+        listing.get(index).cloned().ok_or_else(|| {
+            ErrorData::new(
+                ErrorCode::INVALID_PARAMS,
+                format!(
+                    "Index {} is out of range; the latest listing of '{}' has {} resource(s)",
+                    index,
+                    extension_name,
+                    listing.len()
+                ),
+                None,
+            )
+        })
+    }
+
+    // Function that gets executed for read_resource tool
+    pub async fn read_resource_tool(
+        &self,
+        session_id: &str,
+        params: ReadResourceParams,
+        cancellation_token: CancellationToken,
+    ) -> Result<Vec<Content>, ErrorData> {
+        let extension_name = params.extension_name.as_deref();
+        let indexed_uri;
+        let uri = match params.index {
+            Some(_) if params.uri.is_some() => {
+                return Err(ErrorData::new(
+                    ErrorCode::INVALID_PARAMS,
+                    "Pass either uri or index, not both".to_string(),
+                    None,
+                ));
+            }
+            Some(index) => {
+                indexed_uri = self.listed_resource_uri(session_id, extension_name, index)?;
+                indexed_uri.as_str()
+            }
+            None => params.uri.as_deref().ok_or_else(|| {
+                ErrorData::new(
+                    ErrorCode::INVALID_PARAMS,
+                    "The parameter uri is required".to_string(),
+                    None,
+                )
+            })?,
+        };
+        let all_representations = params.all_representations;
+        let max_bytes = params.max_bytes;
+        let if_none_match = params.if_none_match.as_deref();
+        let offset = params.offset.unwrap_or(0);
+        let length = params.length;
+        let truncate_at = params.truncate_at;
+        if truncate_at == Some(0) {
+            return Err(ErrorData::new(
+                ErrorCode::INVALID_PARAMS,
+                "truncate_at must be at least 1".to_string(),
+                None,
+            ));
+        }
+
+        let mime_type = params.mime_type.as_deref();
+
+        // Prefetched reads hold every representation, so only serve them unfiltered
+        let cached = match mime_type {
+            None => self.context.resource_cache.get(
+                uri,
+                extension_name
+                    .map(|name| self.resolve_extension_name(name))
+                    .as_deref(),
+            ),
+            Some(_) => None,
+        };
+        let mut read_result = match cached {
+            Some(read_result) => read_result,
+            None => {
+                self.find_resource(
+                    session_id,
+                    uri,
+                    extension_name,
+                    mime_type,
+                    cancellation_token,
+                )
+                .await?
+            }
+        };
+
+        // Prefer the extension's own etag; fall back to a content hash so every read has one
+        let hash = hash_resource_contents(&read_result.contents);
+        let mut validators = resource_validators(&read_result.contents);
+        let etag = match validators.get("etag").and_then(|v| v.as_str()) {
+            Some(etag) => etag.to_string(),
+            None => {
+                validators.insert("etag".to_string(), Value::String(hash.clone()));
+                hash.clone()
+            }
+        };
+        self.resource_hashes
+            .lock()
+            .await
+            .insert(uri.to_string(), hash);
+
+        if if_none_match == Some(etag.as_str()) {
+            let mut content = Content::text(format!("{}\nunchanged (etag: {})", uri, etag));
+            extend_content_meta(&mut content, &validators);
+            return Ok(vec![content]);
+        }
+
+        if let Some(max_bytes) = max_bytes {
+            let size: usize = read_result
+                .contents
+                .iter()
+                .map(resource_contents_size)
+                .sum();
+            if size > max_bytes {
+                return Err(ErrorData::new(
+                    ErrorCode::INVALID_REQUEST,
+                    format!(
+                        "Resource {} is {} bytes, more than the max_bytes limit of {}",
+                        uri, size, max_bytes
+                    ),
+                    None,
+                ));
+            }
+        }
+
+        // Windows apply to text; binary contents are always returned whole
+        let windows: Vec<Option<JsonObject>> = read_result
+            .contents
+            .iter_mut()
+            .map(|content| match content {
+                ResourceContents::TextResourceContents { text, .. } => {
+                    window_resource_text(text, offset, length, truncate_at)
+                }
+                ResourceContents::BlobResourceContents { .. } => None,
+            })
+            .collect();
+
+        let mut contents = if all_representations {
+            read_result
+                .contents
+                .into_iter()
+                .map(|content| labeled_representation(uri, content))
+                .collect()
+        } else {
+            resource_content(uri, read_result.contents)
+        };
+        for (content, window) in contents.iter_mut().zip(windows) {
+            extend_content_meta(content, &validators);
+            if let Some(window) = window {
+                extend_content_meta(content, &window);
+            }
+        }
+        Ok(contents)
+    }
+
+    /// Read several resources at once. Reads run concurrently; a URI that can't be read, or
+    /// that would push the total past max_total_bytes, gets an inline note instead of failing
+    /// the whole call.
+    pub async fn read_multiple_resources_tool(
+        &self,
+        session_id: &str,
+        params: ReadMultipleResourcesParams,
+        cancellation_token: CancellationToken,
+    ) -> Result<Vec<Content>, ErrorData> {
+        let uris: Vec<&str> = params.uris.iter().map(String::as_str).collect();
+        if uris.is_empty() {
+            return Err(ErrorData::new(
+                ErrorCode::INVALID_PARAMS,
+                "The 'uris' parameter must be a non-empty list of resource URIs".to_string(),
+                None,
+            ));
+        }
+        let extension_name = params.extension_name.as_deref();
+        let max_total_bytes = params.max_total_bytes;
+
+        let reads = future::join_all(uris.iter().map(|uri| {
+            self.find_resource(
+                session_id,
+                uri,
+                extension_name,
+                None,
+                cancellation_token.clone(),
+            )
+        }))
+        .await;
+
+        let mut result = Vec::new();
+        let mut total_bytes = 0;
+        for (uri, read) in uris.into_iter().zip(reads) {
+            let read_result = match read {
+                Ok(read_result) => read_result,
+                Err(e) => {
+                    result.push(Content::text(format!("{}\nerror: {}", uri, e.message)));
+                    continue;
+                }
+            };
+            let size: usize = read_result
+                .contents
+                .iter()
+                .map(resource_contents_size)
+                .sum();
+            if let Some(max_total_bytes) = max_total_bytes {
+                if total_bytes + size > max_total_bytes {
+                    result.push(Content::text(format!(
+                        "{}\nskipped: {} bytes would exceed the max_total_bytes limit of {} \
+                         ({} already returned)",
+                        uri, size, max_total_bytes, total_bytes
+                    )));
+                    continue;
+                }
+            }
+            total_bytes += size;
+            self.resource_hashes.lock().await.insert(
+                uri.to_string(),
+                hash_resource_contents(&read_result.contents),
+            );
+            result.extend(resource_content(uri, read_result.contents));
+        }
+        Ok(result)
+    }
+
+    /// Hash a resource's current content without returning it, and report whether it changed
+    /// since the last time it was read or hashed
+    pub async fn resource_hash_tool(
+        &self,
+        session_id: &str,
+        params: ResourceHashParams,
+        cancellation_token: CancellationToken,
+    ) -> Result<Vec<Content>, ErrorData> {
+        let uri = params.uri.as_str();
+        let extension_name = params.extension_name.as_deref();
+
+        let read_result = self
+            .find_resource(session_id, uri, extension_name, None, cancellation_token)
+            .await?;
+        let hash = hash_resource_contents(&read_result.contents);
+
+        let previous = self
+            .resource_hashes
+            .lock()
+            .await
+            .insert(uri.to_string(), hash.clone());
+        let status = match previous {
+            None => "first time seen",
+            Some(previous) if previous == hash => "unchanged since last seen",
+            Some(_) => "changed since last seen",
+        };
+
+        Ok(vec![Content::text(format!(
+            "{}\nsha256: {}\nstatus: {}",
+            uri, hash, status
+        ))])
+    }
+
+    pub async fn read_resource(
+        &self,
+        session_id: &str,
+        uri: &str,
+        extension_name: &str,
+        cancellation_token: CancellationToken,
+    ) -> Result<rmcp::model::ReadResourceResult, ErrorData> {
+        let available_extensions = self
+            .extensions
+            .lock()
+            .await
+            .keys()
+            .map(|s| s.as_str())
+            .collect::<Vec<&str>>()
+            .join(", ");
+        let error_msg = format!(
+            "Extension '{}' not found. Here are the available extensions: {}",
+            extension_name, available_extensions
+        );
+
+        let client = self
+            .get_server_client(extension_name)
+            .await
+            .ok_or(ErrorData::new(ErrorCode::INVALID_PARAMS, error_msg, None))?;
+
+        // Not every client stops when its token is cancelled, so stop waiting here as well
+        let read = async {
+            let client_guard = client.lock().await;
+            let notifications = client_guard.subscribe().await;
+            relay_progress(
+                notifications,
+                &self.context.notifications,
+                client_guard.read_resource(session_id, uri, cancellation_token.clone()),
+            )
+            .await
+        };
+        tokio::select! {
+            biased;
+            _ = cancellation_token.cancelled() => Err(resource_read_cancelled(uri)),
+            result = read => result.map_err(|e| match e {
+                ServiceError::Cancelled { .. } => resource_read_cancelled(uri),
+                _ => ErrorData::new(
+                    ErrorCode::INTERNAL_ERROR,
+                    format!("Could not read resource with uri: {}", uri),
+                    None,
+                ),
+            }),
+        }
+    }
+
+    pub async fn get_ui_resources(
+        &self,
+        session_id: &str,
+    ) -> Result<Vec<(String, Resource)>, ErrorData> {
+        let mut ui_resources = Vec::new();
+
+        let extensions_to_check: Vec<(String, McpClientBox)> = {
             let extensions = self.extensions.lock().await;
             extensions
                 .iter()
@@ -1084,8 +2951,9 @@ impl ExtensionManager {
         &self,
         session_id: &str,
         extension_name: &str,
+        uri_prefix: Option<&str>,
         cancellation_token: CancellationToken,
-    ) -> Result<Vec<Content>, ErrorData> {
+    ) -> Result<Vec<(String, Resource)>, ErrorData> {
         let client = self
             .get_server_client(extension_name)
             .await
@@ -1109,457 +2977,4022 @@ impl ExtensionManager {
                 )
             })
             .map(|lr| {
-                let resource_list = lr
-                    .resources
+                lr.resources
                     .into_iter()
-                    .map(|r| format!("{} - {}, uri: ({})", extension_name, r.name, r.uri))
-                    .collect::<Vec<String>>()
-                    .join("\n");
-
-                vec![Content::text(resource_list)]
+                    .filter(|r| uri_prefix.is_none_or(|prefix| r.uri.starts_with(prefix)))
+                    .map(|r| (extension_name.to_string(), r))
+                    .collect()
             })
     }
 
-    pub async fn list_resources(
+    /// Known resource URIs starting with a prefix, from one extension or from all of them,
+    /// sorted and without duplicates. Extensions that fail to list are left out.
+    pub async fn complete_resource_uri(
         &self,
         session_id: &str,
-        params: Value,
+        prefix: &str,
+        extension_name: Option<&str>,
         cancellation_token: CancellationToken,
-    ) -> Result<Vec<Content>, ErrorData> {
-        let extension = params.get("extension").and_then(|v| v.as_str());
-
-        match extension {
-            Some(extension_name) => {
-                // Handle single extension case
-                self.list_resources_from_extension(session_id, extension_name, cancellation_token)
-                    .await
-            }
-            None => {
-                // Handle all extensions case using FuturesUnordered
-                let mut futures = FuturesUnordered::new();
-
-                // Create futures for each resource_capable_extension
-                self.extensions
-                    .lock()
-                    .await
-                    .iter()
-                    .filter(|(_name, ext)| ext.supports_resources())
-                    .map(|(name, _ext)| name.clone())
-                    .for_each(|name| {
-                        let token = cancellation_token.clone();
-                        futures.push(async move {
-                            self.list_resources_from_extension(session_id, name.as_str(), token)
-                                .await
-                        });
-                    });
+    ) -> Vec<String> {
+        let names: Vec<String> = match extension_name {
+            Some(name) => vec![name.to_string()],
+            None => self
+                .extensions
+                .lock()
+                .await
+                .iter()
+                .filter(|(_name, ext)| ext.supports_resources())
+                .map(|(name, _ext)| name.clone())
+                .collect(),
+        };
 
-                let mut all_resources = Vec::new();
-                let mut errors = Vec::new();
+        let listings = future::join_all(names.iter().map(|name| {
+            self.list_resources_from_extension(
+                session_id,
+                name,
+                Some(prefix),
+                cancellation_token.clone(),
+            )
+        }))
+        .await;
+
+        listings
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|(_name, resource)| resource.raw.uri)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
 
-                // Process results as they complete
-                while let Some(result) = futures.next().await {
-                    match result {
-                        Ok(content) => {
-                            all_resources.extend(content);
-                        }
-                        Err(tool_error) => {
-                            errors.push(tool_error);
-                        }
-                    }
-                }
+    /// The one extension that lists a resource with exactly this URI
+    async fn resource_provider(
+        &self,
+        session_id: &str,
+        uri: &str,
+        cancellation_token: CancellationToken,
+    ) -> Result<String, ErrorData> {
+        let mut names: Vec<String> = self
+            .extensions
+            .lock()
+            .await
+            .iter()
+            .filter(|(_name, ext)| ext.supports_resources())
+            .map(|(name, _ext)| name.clone())
+            .collect();
+        names.sort();
 
-                if !errors.is_empty() {
-                    tracing::error!(
-                        errors = ?errors
-                            .into_iter()
-                            .map(|e| format!("{:?}", e))
-                            .collect::<Vec<_>>(),
-                        "errors from listing resources"
-                    );
-                }
+        let listings = future::join_all(names.iter().map(|name| {
+            self.list_resources_from_extension(
+                session_id,
+                name,
+                Some(uri),
+                cancellation_token.clone(),
+            )
+        }))
+        .await;
+        let mut providers: Vec<String> = listings
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|(_name, resource)| resource.uri == uri)
+            .map(|(name, _resource)| name)
+            .collect();
+        providers.dedup();
 
-                Ok(all_resources)
-            }
+        match providers.len() {
+            0 => Err(ErrorData::new(
+                ErrorCode::RESOURCE_NOT_FOUND,
+                format!("No extension lists a resource with uri '{}'", uri),
+                None,
+            )),
+            1 => Ok(providers.remove(0)),
+            _ => Err(ErrorData::new(
+                ErrorCode::INVALID_PARAMS,
+                format!(
+                    "Resource with uri '{}' is provided by several extensions: {}. Pass \
+                     extension_name to choose one.",
+                    uri,
+                    providers.join(", ")
+                ),
+                None,
+            )),
         }
     }
 
-    pub async fn dispatch_tool_call(
+    /// Subscribe to a resource and forward its resource updated notifications to the
+    /// subscribers of `subscribe_resource_updates`. The watch stops, and unsubscribes
+    /// upstream, once nobody is subscribed any more or the extension goes away.
+    pub async fn watch_resource(
         &self,
         session_id: &str,
-        tool_call: CallToolRequestParams,
-        working_dir: Option<&std::path::Path>,
+        uri: &str,
+        extension_name: Option<&str>,
         cancellation_token: CancellationToken,
-    ) -> Result<ToolCallResult> {
-        // Some models strip the tool prefix, so auto-add it for known code_execution tools
-        let tool_name_str = tool_call.name.to_string();
-        let prefixed_name = if !tool_name_str.contains("__") {
-            let code_exec_tools = ["execute", "list_functions", "get_function_details"];
-            if code_exec_tools.contains(&tool_name_str.as_str())
-                && self.extensions.lock().await.contains_key("code_execution")
-            {
-                format!("code_execution__{tool_name_str}")
-            } else {
-                tool_name_str
+    ) -> Result<Vec<Content>, ErrorData> {
+        let updates = self.resource_updates.clone();
+        if updates.receiver_count() == 0 {
+            return Err(ErrorData::new(
+                ErrorCode::INTERNAL_ERROR,
+                "Nothing is listening for resource updates, so the watch would end at once"
+                    .to_string(),
+                None,
+            ));
+        }
+        let extension_name = match extension_name {
+            Some(name) => self.resolve_extension_name(name),
+            None => {
+                self.resource_provider(session_id, uri, cancellation_token.clone())
+                    .await?
             }
-        } else {
-            tool_name_str
         };
+        let (client, supports_subscriptions) = {
+            let extensions = self.extensions.lock().await;
+            let extension = extensions.get(&extension_name).ok_or_else(|| {
+                ErrorData::new(
+                    ErrorCode::INVALID_PARAMS,
+                    format!("Extension '{}' is not enabled", extension_name),
+                    None,
+                )
+            })?;
+            (
+                extension.get_client(),
+                extension.supports_resource_subscriptions(),
+            )
+        };
+        if !supports_subscriptions {
+            return Err(ErrorData::new(
+                ErrorCode::INVALID_REQUEST,
+                format!(
+                    "Extension '{}' does not support resource subscriptions",
+                    extension_name
+                ),
+                None,
+            ));
+        }
 
-        // Dispatch tool call based on the prefix naming convention
-        let (client_name, client) =
-            self.get_client_for_tool(&prefixed_name)
-                .await
-                .ok_or_else(|| {
-                    ErrorData::new(
-                        ErrorCode::RESOURCE_NOT_FOUND,
-                        format!("Tool '{}' not found", tool_call.name),
-                        None,
-                    )
-                })?;
-
-        let tool_name = prefixed_name
-            .strip_prefix(client_name.as_str())
-            .and_then(|s| s.strip_prefix("__"))
-            .ok_or_else(|| {
-                ErrorData::new(
-                    ErrorCode::RESOURCE_NOT_FOUND,
-                    format!("Invalid tool name format: '{}'", tool_call.name),
-                    None,
-                )
-            })?
-            .to_string();
+        let watch = (extension_name.clone(), uri.to_string());
+        if !self
+            .resource_watches
+            .lock()
+            .expect("resource watches poisoned")
+            .insert(watch.clone())
+        {
+            return Ok(vec![Content::text(format!(
+                "Already watching {} in {}",
+                uri, extension_name
+            ))]);
+        }
 
-        if let Some(extension) = self.extensions.lock().await.get(&client_name) {
-            if !extension.config.is_tool_available(&tool_name) {
+        let mut notifications = {
+            let client_guard = client.lock().await;
+            let notifications = client_guard.subscribe().await;
+            if let Err(e) = client_guard
+                .subscribe_resource(session_id, uri, cancellation_token)
+                .await
+            {
+                self.resource_watches
+                    .lock()
+                    .expect("resource watches poisoned")
+                    .remove(&watch);
                 return Err(ErrorData::new(
-                    ErrorCode::RESOURCE_NOT_FOUND,
+                    ErrorCode::INTERNAL_ERROR,
                     format!(
-                        "Tool '{}' is not available for extension '{}'",
-                        tool_name, client_name
+                        "Could not subscribe to {} in {}: {}",
+                        uri, extension_name, e
                     ),
                     None,
-                )
-                .into());
+                ));
             }
-        }
+            notifications
+        };
 
-        let arguments = tool_call.arguments.clone();
-        let client = client.clone();
-        let notifications_receiver = client.lock().await.subscribe().await;
+        // Hold the client weakly so the watch doesn't keep a disabled extension alive
+        let client = Arc::downgrade(&client);
+        let watches = Arc::clone(&self.resource_watches);
         let session_id = session_id.to_string();
-        let working_dir_str = working_dir.map(|p| p.to_string_lossy().to_string());
-
-        let fut = async move {
-            tracing::debug!(
-                "dispatch_tool_call fut: calling client.call_tool tool={} session_id={} working_dir={:?}",
-                tool_name,
-                session_id,
-                working_dir_str
+        tokio::spawn(async move {
+            let mut listener_check = tokio::time::interval_at(
+                tokio::time::Instant::now() + RESOURCE_WATCH_LISTENER_CHECK,
+                RESOURCE_WATCH_LISTENER_CHECK,
             );
-            let client_guard = client.lock().await;
-            client_guard
-                .call_tool(
-                    &session_id,
-                    &tool_name,
-                    arguments,
-                    working_dir_str.as_deref(),
-                    cancellation_token,
-                )
-                .await
-                .map_err(|e| match e {
-                    ServiceError::McpError(error_data) => error_data,
-                    _ => {
-                        ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), e.maybe_to_value())
+            loop {
+                tokio::select! {
+                    notification = notifications.recv() => match notification {
+                        Some(ServerNotification::ResourceUpdatedNotification(updated))
+                            if updated.params.uri == watch.1 =>
+                        {
+                            let notification =
+                                ServerNotification::ResourceUpdatedNotification(updated);
+                            if updates.send((watch.0.clone(), notification)).is_err() {
+                                break;
+                            }
+                        }
+                        Some(_) => {}
+                        // The extension's client is gone
+                        None => break,
+                    },
+                    _ = listener_check.tick() => {
+                        if updates.receiver_count() == 0 {
+                            break;
+                        }
                     }
-                })
-        };
+                }
+            }
 
-        Ok(ToolCallResult {
-            result: Box::new(fut.boxed()),
-            notification_stream: Some(Box::new(ReceiverStream::new(notifications_receiver))),
-        })
+            watches
+                .lock()
+                .expect("resource watches poisoned")
+                .remove(&watch);
+            if let Some(client) = client.upgrade() {
+                let (_, uri) = &watch;
+                if let Err(e) = client
+                    .lock()
+                    .await
+                    .unsubscribe_resource(&session_id, uri, CancellationToken::new())
+                    .await
+                {
+                    warn!("Could not unsubscribe from {}: {}", uri, e);
+                }
+            }
+        });
+
+        Ok(vec![Content::text(format!(
+            "Watching {} in {}. Changes arrive as resource updated notifications.",
+            uri, extension_name
+        ))])
     }
 
-    pub async fn list_prompts_from_extension(
+    /// Write `text` to a resource in an extension that accepts resource writes. Without
+    /// `extension_name`, the write goes to the only such extension, if there is just one.
+    pub async fn write_resource(
         &self,
         session_id: &str,
-        extension_name: &str,
+        uri: &str,
+        text: &str,
+        extension_name: Option<&str>,
         cancellation_token: CancellationToken,
-    ) -> Result<Vec<Prompt>, ErrorData> {
-        let client = self
-            .get_server_client(extension_name)
-            .await
-            .ok_or_else(|| {
+    ) -> Result<Vec<Content>, ErrorData> {
+        self.context.resource_cache.remove(uri);
+        let (extension_name, client) = {
+            let extensions = self.extensions.lock().await;
+            let extension_name = match extension_name {
+                Some(name) => self.resolve_extension_name(name),
+                None => {
+                    let mut writable: Vec<&String> = extensions
+                        .iter()
+                        .filter(|(_name, ext)| ext.supports_resource_writes())
+                        .map(|(name, _ext)| name)
+                        .collect();
+                    writable.sort();
+                    match writable.as_slice() {
+                        [name] => (*name).clone(),
+                        [] => {
+                            return Err(ErrorData::new(
+                                ErrorCode::INVALID_REQUEST,
+                                "No enabled extension supports writing resources".to_string(),
+                                None,
+                            ))
+                        }
+                        names => {
+                            return Err(ErrorData::new(
+                                ErrorCode::INVALID_PARAMS,
+                                format!(
+                                    "Several extensions support writing resources: {}. Pass \
+                                     extension_name to choose one.",
+                                    names
+                                        .iter()
+                                        .map(|name| name.as_str())
+                                        .collect::<Vec<_>>()
+                                        .join(", ")
+                                ),
+                                None,
+                            ))
+                        }
+                    }
+                }
+            };
+            let extension = extensions.get(&extension_name).ok_or_else(|| {
                 ErrorData::new(
                     ErrorCode::INVALID_PARAMS,
-                    format!("Extension {} is not valid", extension_name),
+                    format!("Extension '{}' is not enabled", extension_name),
                     None,
                 )
             })?;
+            if !extension.supports_resource_writes() {
+                return Err(ErrorData::new(
+                    ErrorCode::INVALID_REQUEST,
+                    format!(
+                        "Extension '{}' does not support writing resources",
+                        extension_name
+                    ),
+                    None,
+                ));
+            }
+            (extension_name, extension.get_client())
+        };
 
-        let client_guard = client.lock().await;
-        client_guard
-            .list_prompts(session_id, None, cancellation_token)
+        let confirmation = client
+            .lock()
+            .await
+            .write_resource(session_id, uri, text, cancellation_token)
             .await
             .map_err(|e| {
                 ErrorData::new(
                     ErrorCode::INTERNAL_ERROR,
-                    format!("Unable to list prompts for {}, {:?}", extension_name, e),
+                    format!("Could not write {} in {}: {}", uri, extension_name, e),
                     None,
                 )
-            })
-            .map(|lp| lp.prompts)
+            })?;
+
+        Ok(vec![Content::text(format!(
+            "Wrote {} in {}\n{}",
+            uri, extension_name, confirmation
+        ))])
     }
 
-    pub async fn list_prompts(
+    fn format_resource_list(&self, resources: &[(String, Resource)], indices: &[usize]) -> String {
+        match self.context.result_format {
+            ResultFormat::Plain => resources
+                .iter()
+                .zip(indices)
+                .map(|((extension_name, r), index)| {
+                    format!(
+                        "{} - {}, uri: ({}), index: {}",
+                        extension_name, r.name, r.uri, index
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join("\n"),
+            ResultFormat::Markdown => std::iter::once(
+                "| Extension | Index | Resource | URI |\n| --- | --- | --- | --- |".to_string(),
+            )
+            .chain(
+                resources
+                    .iter()
+                    .zip(indices)
+                    .map(|((extension_name, r), index)| {
+                        format!(
+                            "| {} | {} | {} | `{}` |",
+                            extension_name,
+                            index,
+                            escape_table_cell(&r.name),
+                            r.uri
+                        )
+                    }),
+            )
+            .collect::<Vec<String>>()
+            .join("\n"),
+        }
+    }
+
+    fn format_resource_template_list(&self, templates: &[(String, ResourceTemplate)]) -> String {
+        let heading = "Resource templates; fill one in with expand_resource_template, then read \
+                       the URI with read_resource:";
+        let rows: Vec<String> = match self.context.result_format {
+            ResultFormat::Plain => templates
+                .iter()
+                .map(|(extension_name, t)| {
+                    format!(
+                        "{} - {}, template: ({}), variables: {}",
+                        extension_name,
+                        t.name,
+                        t.uri_template,
+                        describe_template_variables(&t.uri_template)
+                    )
+                })
+                .collect(),
+            ResultFormat::Markdown => std::iter::once(
+                "| Extension | Template | URI template | Variables |\n| --- | --- | --- | --- |"
+                    .to_string(),
+            )
+            .chain(templates.iter().map(|(extension_name, t)| {
+                format!(
+                    "| {} | {} | `{}` | {} |",
+                    extension_name,
+                    escape_table_cell(&t.name),
+                    t.uri_template,
+                    describe_template_variables(&t.uri_template)
+                )
+            }))
+            .collect(),
+        };
+        format!("{}\n{}", heading, rows.join("\n"))
+    }
+
+    /// Resource templates from the given extensions, sorted by extension then template.
+    /// Extensions without templates, or that fail to list them, are left out.
+    async fn list_resource_templates(
         &self,
         session_id: &str,
+        extension_names: &[String],
         cancellation_token: CancellationToken,
-    ) -> Result<HashMap<String, Vec<Prompt>>, ErrorData> {
-        let mut futures = FuturesUnordered::new();
-
-        let names: Vec<_> = self.extensions.lock().await.keys().cloned().collect();
-        for extension_name in names {
+    ) -> Vec<(String, ResourceTemplate)> {
+        let listings = future::join_all(extension_names.iter().map(|name| {
             let token = cancellation_token.clone();
-            futures.push(async move {
-                (
-                    extension_name.clone(),
-                    self.list_prompts_from_extension(session_id, extension_name.as_str(), token)
-                        .await,
-                )
-            });
-        }
-
-        let mut all_prompts = HashMap::new();
-        let mut errors = Vec::new();
-
-        // Process results as they complete
-        while let Some(result) = futures.next().await {
-            let (name, prompts) = result;
-            match prompts {
-                Ok(content) => {
-                    all_prompts.insert(name.to_string(), content);
-                }
-                Err(tool_error) => {
-                    errors.push(tool_error);
-                }
+            async move {
+                let result = match self.get_server_client(name).await {
+                    Some(client) => client
+                        .lock()
+                        .await
+                        .list_resource_templates(session_id, None, token)
+                        .await
+                        .map_err(|e| format!("{:?}", e)),
+                    None => Err("extension is not valid".to_string()),
+                };
+                (name, result)
+            }
+        }))
+        .await;
+
+        let mut templates = Vec::new();
+        for (name, result) in listings {
+            match result {
+                Ok(listing) => templates.extend(
+                    listing
+                        .resource_templates
+                        .into_iter()
+                        .map(|template| (name.clone(), template)),
+                ),
+                // Templates are optional, so servers commonly don't implement the method
+                Err(e) => tracing::debug!("No resource templates from {}: {}", name, e),
             }
         }
-
-        if !errors.is_empty() {
-            tracing::debug!(
-                errors = ?errors
-                    .into_iter()
-                    .map(|e| format!("{:?}", e))
-                    .collect::<Vec<_>>(),
-                "errors from listing prompts"
-            );
-        }
-
-        Ok(all_prompts)
+        templates.sort_by(|(a_ext, a), (b_ext, b)| {
+            a_ext
+                .cmp(b_ext)
+                .then_with(|| a.uri_template.cmp(&b.uri_template))
+        });
+        templates
     }
 
-    pub async fn get_prompt(
+    /// Resources from one extension, or from every one that matches `name_pattern`, with the
+    /// extensions that answered and notes about the ones that didn't
+    async fn gather_resources(
         &self,
         session_id: &str,
-        extension_name: &str,
-        name: &str,
-        arguments: Value,
+        params: &ListResourcesParams,
         cancellation_token: CancellationToken,
-    ) -> Result<GetPromptResult> {
-        let client = self
-            .get_server_client(extension_name)
-            .await
-            .ok_or_else(|| anyhow::anyhow!("Extension {} not found", extension_name))?;
+    ) -> Result<ResourceGathering, ErrorData> {
+        let extension = params.extension_name.as_deref();
+        let uri_prefix = params.uri_prefix.as_deref();
+        let per_extension_timeout = Duration::from_millis(
+            params
+                .per_extension_timeout_ms
+                .unwrap_or(DEFAULT_RESOURCE_LISTING_TIMEOUT_MS),
+        );
+        let name_pattern = params
+            .name_pattern
+            .as_deref()
+            .map(|pattern| {
+                compile_name_pattern(pattern).map_err(|e| {
+                    ErrorData::new(
+                        ErrorCode::INVALID_PARAMS,
+                        format!("Invalid name_pattern '{}': {}", pattern, e),
+                        None,
+                    )
+                })
+            })
+            .transpose()?;
 
-        let client_guard = client.lock().await;
-        client_guard
-            .get_prompt(session_id, name, arguments, cancellation_token)
-            .await
-            .map_err(|e| anyhow::anyhow!("Failed to get prompt: {}", e))
-    }
+        let mut notes = Vec::new();
+        let (resources, listed) = match extension {
+            Some(extension_name) => {
+                // Handle single extension case
+                let resources = self
+                    .list_resources_from_extension(
+                        session_id,
+                        extension_name,
+                        uri_prefix,
+                        cancellation_token,
+                    )
+                    .await?;
+                (resources, vec![extension_name.to_string()])
+            }
+            None => {
+                // Handle all extensions case using a bounded FuturesUnordered, so a slow
+                // extension only holds up its own slot
+                let mut pending: VecDeque<String> = self
+                    .extensions
+                    .lock()
+                    .await
+                    .iter()
+                    .filter(|(_name, ext)| ext.supports_resources())
+                    .filter(|(name, _ext)| {
+                        name_pattern
+                            .as_ref()
+                            .is_none_or(|pattern| pattern.is_match(name))
+                    })
+                    .map(|(name, _ext)| name.clone())
+                    .collect();
+                let list_one = |name: String| {
+                    let token = cancellation_token.clone();
+                    async move {
+                        let result = tokio::time::timeout(
+                            per_extension_timeout,
+                            self.list_resources_from_extension(
+                                session_id,
+                                name.as_str(),
+                                uri_prefix,
+                                token,
+                            ),
+                        )
+                        .await;
+                        (name, result)
+                    }
+                };
+                let mut futures = FuturesUnordered::new();
+                while futures.len() < MAX_CONCURRENT_RESOURCE_LISTINGS {
+                    match pending.pop_front() {
+                        Some(name) => futures.push(list_one(name)),
+                        None => break,
+                    }
+                }
 
-    pub async fn search_available_extensions(&self) -> Result<Vec<Content>, ErrorData> {
-        let mut output_parts = vec![];
+                let mut all_resources = Vec::new();
+                let mut listed = Vec::new();
+                let mut errors = Vec::new();
+                let mut timed_out = Vec::new();
+                let queried = futures.len() + pending.len();
+                let mut responded = 0;
+                let mut cancelled = false;
 
-        // First get disabled extensions from current config
-        let mut disabled_extensions: Vec<String> = vec![];
-        for extension in get_all_extensions() {
-            if !extension.enabled {
-                let config = extension.config.clone();
-                let description = match &config {
-                    ExtensionConfig::Builtin {
-                        description,
-                        display_name,
-                        ..
-                    } => {
-                        if description.is_empty() {
-                            display_name.as_deref().unwrap_or("Built-in extension")
-                        } else {
-                            description
+                // Process results as they complete, and stop waiting for the rest as soon as
+                // the call is cancelled
+                loop {
+                    let result = tokio::select! {
+                        biased;
+                        _ = cancellation_token.cancelled() => {
+                            cancelled = true;
+                            break;
                         }
+                        result = futures.next() => match result {
+                            Some(result) => result,
+                            None => break,
+                        },
+                    };
+                    if let Some(name) = pending.pop_front() {
+                        futures.push(list_one(name));
                     }
-                    ExtensionConfig::Sse { .. } => "SSE extension (unsupported)",
-                    ExtensionConfig::Platform { description, .. }
-                    | ExtensionConfig::StreamableHttp { description, .. }
-                    | ExtensionConfig::Stdio { description, .. }
-                    | ExtensionConfig::Frontend { description, .. }
-                    | ExtensionConfig::InlinePython { description, .. } => description,
-                };
-                disabled_extensions.push(format!("- {} - {}", config.name(), description));
-            }
-        }
+                    responded += 1;
+                    match result {
+                        (name, Ok(Ok(resources))) => {
+                            listed.push(name);
+                            all_resources.extend(resources);
+                        }
+                        (_, Ok(Err(tool_error))) => {
+                            errors.push(tool_error);
+                        }
+                        (name, Err(_elapsed)) => {
+                            warn!("Timed out listing resources for {}", name);
+                            timed_out.push(name);
+                        }
+                    }
+                }
 
-        // Get currently enabled extensions that can be disabled
-        let enabled_extensions: Vec<String> =
-            self.extensions.lock().await.keys().cloned().collect();
+                if !timed_out.is_empty() {
+                    timed_out.sort();
+                    notes.push(Content::text(format!(
+                        "Skipped {} extension(s) that did not respond within {} ms: {}",
+                        timed_out.len(),
+                        per_extension_timeout.as_millis(),
+                        timed_out.join(", ")
+                    )));
+                }
 
-        // Build output string
-        if !disabled_extensions.is_empty() {
-            output_parts.push(format!(
-                "Extensions available to enable:\n{}\n",
-                disabled_extensions.join("\n")
-            ));
-        } else {
-            output_parts.push("No extensions available to enable.\n".to_string());
+                if !errors.is_empty() {
+                    tracing::error!(
+                        errors = ?errors
+                            .into_iter()
+                            .map(|e| format!("{:?}", e))
+                            .collect::<Vec<_>>(),
+                        "errors from listing resources"
+                    );
+                }
+
+                if cancelled {
+                    notes.push(Content::text(format!(
+                        "Incomplete: listing was cancelled after {} of {} extensions responded",
+                        responded, queried
+                    )));
+                }
+
+                (all_resources, listed)
+            }
+        };
+
+        Ok(ResourceGathering {
+            resources,
+            listed,
+            notes,
+        })
+    }
+
+    /// How many resources each extension lists, honoring the same extension_name,
+    /// name_pattern and uri_prefix filters as `list_resources`. Extensions that answered with
+    /// no resources count as zero; the notes say which ones didn't answer.
+    pub async fn count_resources(
+        &self,
+        session_id: &str,
+        params: ListResourcesParams,
+        cancellation_token: CancellationToken,
+    ) -> Result<(BTreeMap<String, usize>, Vec<Content>), ErrorData> {
+        let ResourceGathering {
+            resources,
+            listed,
+            notes,
+        } = self
+            .gather_resources(session_id, &params, cancellation_token)
+            .await?;
+        let mut counts: BTreeMap<String, usize> =
+            listed.into_iter().map(|name| (name, 0)).collect();
+        for (extension_name, _resource) in resources {
+            *counts.entry(extension_name).or_default() += 1;
         }
+        Ok((counts, notes))
+    }
 
-        if !enabled_extensions.is_empty() {
-            output_parts.push(format!(
-                "\n\nExtensions available to disable:\n{}\n",
-                enabled_extensions
-                    .iter()
-                    .map(|name| format!("- {}", name))
-                    .collect::<Vec<_>>()
-                    .join("\n")
+    /// List resources from one extension, or all of them, as text.
+    ///
+    /// Passing `limit` or `cursor` returns one page, sorted by extension then URI, and ends the
+    /// result with a JSON item whose `next_cursor` fetches the next page (null on the last one).
+    /// Unpaginated listings also show the extensions' resource templates, after the resources.
+    pub async fn list_resources(
+        &self,
+        session_id: &str,
+        params: ListResourcesParams,
+        cancellation_token: CancellationToken,
+    ) -> Result<Vec<Content>, ErrorData> {
+        let uri_prefix = params.uri_prefix.as_deref();
+        let cursor = params.cursor.as_deref();
+        let limit = params.limit;
+        let paginated = cursor.is_some() || limit.is_some();
+
+        let offset = match cursor {
+            None => 0,
+            Some(cursor) => cursor.parse::<usize>().map_err(|_| {
+                ErrorData::new(
+                    ErrorCode::INVALID_PARAMS,
+                    format!(
+                        "Invalid cursor '{}'; pass the next_cursor from a previous page",
+                        cursor
+                    ),
+                    None,
+                )
+            })?,
+        };
+        if limit == Some(0) {
+            return Err(ErrorData::new(
+                ErrorCode::INVALID_PARAMS,
+                "limit must be at least 1".to_string(),
+                None,
             ));
-        } else {
-            output_parts.push("No extensions that can be disabled.\n".to_string());
         }
 
-        Ok(vec![Content::text(output_parts.join("\n"))])
+        let ResourceGathering {
+            mut resources,
+            listed,
+            notes,
+        } = self
+            .gather_resources(session_id, &params, cancellation_token.clone())
+            .await?;
+        let templates = match paginated {
+            true => Vec::new(),
+            false => self
+                .list_resource_templates(session_id, &listed, cancellation_token)
+                .await
+                .into_iter()
+                .filter(|(_name, template)| {
+                    uri_prefix.is_none_or(|prefix| {
+                        template_matches_prefix(&template.uri_template, prefix)
+                    })
+                })
+                .collect(),
+        };
+
+        // Number each resource within its extension's sorted listing, and remember the
+        // listings so read_resource can take one of these indices
+        resources
+            .sort_by(|(a_ext, a), (b_ext, b)| a_ext.cmp(b_ext).then_with(|| a.uri.cmp(&b.uri)));
+        let mut listings: BTreeMap<String, Vec<String>> =
+            listed.into_iter().map(|name| (name, Vec::new())).collect();
+        let mut indices: Vec<usize> = resources
+            .iter()
+            .map(|(extension_name, resource)| {
+                let uris = listings.entry(extension_name.clone()).or_default();
+                uris.push(resource.uri.clone());
+                uris.len() - 1
+            })
+            .collect();
+        for (extension_name, uris) in listings {
+            self.context.resource_listings.record(
+                session_id,
+                &self.resolve_extension_name(&extension_name),
+                uris,
+            );
+        }
+
+        let matched = resources.len();
+        let mut next_cursor = None;
+        if paginated {
+            if offset > matched || (offset == matched && offset > 0) {
+                return Err(ErrorData::new(
+                    ErrorCode::INVALID_PARAMS,
+                    format!(
+                        "Cursor '{}' is past the end of the {} matching resource(s)",
+                        offset, matched
+                    ),
+                    None,
+                ));
+            }
+            let end = limit.map_or(matched, |limit| offset.saturating_add(limit).min(matched));
+            if end < matched {
+                next_cursor = Some(end.to_string());
+            }
+            resources = resources.drain(offset..end).collect();
+            indices = indices.drain(offset..end).collect();
+        }
+
+        let mut contents = Vec::new();
+        if !resources.is_empty() || (uri_prefix.is_none() && !paginated) {
+            let mut listing = Content::text(self.format_resource_list(&resources, &indices));
+            extend_content_meta(&mut listing, &resource_sources(&resources, &indices));
+            contents.push(listing);
+        }
+        if !templates.is_empty() {
+            contents.push(Content::text(
+                self.format_resource_template_list(&templates),
+            ));
+        }
+        contents.extend(notes);
+
+        if let Some(prefix) = uri_prefix {
+            contents.push(Content::text(format!(
+                "{} resource(s) with a URI starting with '{}'",
+                matched, prefix
+            )));
+        }
+        if paginated {
+            let page = Content::json(serde_json::json!({
+                "total": matched,
+                "next_cursor": next_cursor,
+            }))?;
+            contents.push(page);
+        }
+        Ok(contents)
     }
 
-    async fn get_server_client(&self, name: impl Into<String>) -> Option<McpClientBox> {
-        let normalized = name_to_key(&name.into());
-        self.extensions
+    pub async fn resource_catalog(
+        &self,
+        session_id: &str,
+        cancellation_token: CancellationToken,
+    ) -> ResourceCatalog {
+        let mut names: Vec<String> = self
+            .extensions
             .lock()
             .await
-            .get(&normalized)
-            .map(|ext| ext.get_client())
+            .iter()
+            .filter(|(_name, ext)| ext.supports_resources())
+            .map(|(name, _ext)| name.clone())
+            .collect();
+        names.sort();
+
+        let listings = future::join_all(names.into_iter().map(|name| {
+            let token = cancellation_token.clone();
+            async move {
+                let result = match self.get_server_client(&name).await {
+                    Some(client) => client
+                        .lock()
+                        .await
+                        .list_resources(session_id, None, token)
+                        .await
+                        .map_err(|e| format!("{:?}", e)),
+                    None => Err("extension is not valid".to_string()),
+                };
+                (name, result)
+            }
+        }))
+        .await;
+
+        let mut catalog = ResourceCatalog::default();
+        let mut by_uri: HashMap<String, usize> = HashMap::new();
+        for (name, result) in listings {
+            let listing = match result {
+                Ok(listing) => listing,
+                Err(e) => {
+                    warn!("Unable to list resources for {}: {}", name, e);
+                    catalog.unavailable.push(name);
+                    continue;
+                }
+            };
+            for resource in listing.resources {
+                let resource = resource.raw;
+                if let Some(&index) = by_uri.get(&resource.uri) {
+                    catalog.resources[index].also_provided_by.push(name.clone());
+                    continue;
+                }
+                by_uri.insert(resource.uri.clone(), catalog.resources.len());
+                *catalog.per_extension.entry(name.clone()).or_default() += 1;
+                catalog.total_bytes += resource.size.map(u64::from).unwrap_or(0);
+                catalog.resources.push(CatalogResource {
+                    uri: resource.uri,
+                    name: resource.name,
+                    extension: name.clone(),
+                    mime_type: resource.mime_type,
+                    size: resource.size,
+                    also_provided_by: Vec::new(),
+                });
+            }
+        }
+        catalog.total = catalog.resources.len();
+        catalog.resources.sort_by(|a, b| a.uri.cmp(&b.uri));
+        catalog
     }
 
-    pub async fn collect_moim(
+    pub async fn dispatch_tool_call(
         &self,
         session_id: &str,
-        working_dir: &std::path::Path,
-    ) -> Option<String> {
-        // Use minute-level granularity to prevent conversation changes every second
-        let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:00").to_string();
-        let mut content = format!(
-            "<info-msg>\nIt is currently {}\nWorking directory: {}\n",
-            timestamp,
-            working_dir.display()
-        );
-
-        let platform_clients: Vec<(String, McpClientBox)> = {
-            let extensions = self.extensions.lock().await;
-            extensions
-                .iter()
-                .filter_map(|(name, extension)| {
-                    if let ExtensionConfig::Platform { .. } = &extension.config {
-                        Some((name.clone(), extension.get_client()))
-                    } else {
-                        None
-                    }
-                })
-                .collect()
+        tool_call: CallToolRequestParams,
+        working_dir: Option<&std::path::Path>,
+        cancellation_token: CancellationToken,
+    ) -> Result<ToolCallResult> {
+        // Some models strip the tool prefix, so auto-add it for known code_execution tools
+        let tool_name_str = tool_call.name.to_string();
+        // Stable ids contain no "__", so look them up before treating the name as unprefixed
+        let resolved_id = if tool_name_str.starts_with(TOOL_ID_PREFIX) {
+            self.resolve_tool_id(session_id, &tool_name_str).await
+        } else {
+            None
+        };
+        let prefixed_name = if let Some(resolved) = resolved_id {
+            resolved
+        } else if !tool_name_str.contains("__") {
+            let code_exec_tools = ["execute", "list_functions", "get_function_details"];
+            if code_exec_tools.contains(&tool_name_str.as_str())
+                && self.extensions.lock().await.contains_key("code_execution")
+            {
+                format!("code_execution__{tool_name_str}")
+            } else {
+                tool_name_str
+            }
+        } else {
+            tool_name_str
         };
 
-        for (name, client) in platform_clients {
-            let client_guard = client.lock().await;
-            if let Some(moim_content) = client_guard.get_moim(session_id).await {
-                tracing::debug!("MOIM content from {}: {} chars", name, moim_content.len());
-                content.push('\n');
-                content.push_str(&moim_content);
+        // Tools of an aliased extension are prefixed with the alias
+        let unaliased = prefixed_name.split_once("__").and_then(|(prefix, rest)| {
+            self.aliased_key(prefix)
+                .map(|key| format!("{}__{}", key, rest))
+        });
+        let prefixed_name = unaliased.unwrap_or(prefixed_name);
+
+        // Dispatch tool call based on the prefix naming convention
+        let (client_name, client) =
+            self.get_client_for_tool(&prefixed_name)
+                .await
+                .ok_or_else(|| {
+                    ErrorData::new(
+                        ErrorCode::RESOURCE_NOT_FOUND,
+                        format!("Tool '{}' not found", tool_call.name),
+                        None,
+                    )
+                })?;
+
+        let tool_name = prefixed_name
+            .strip_prefix(client_name.as_str())
+            .and_then(|s| s.strip_prefix("__"))
+            .ok_or_else(|| {
+                ErrorData::new(
+                    ErrorCode::RESOURCE_NOT_FOUND,
+                    format!("Invalid tool name format: '{}'", tool_call.name),
+                    None,
+                )
+            })?
+            .to_string();
+
+        let mut call_permits = None;
+        let mut crashed = None;
+        let mut breaker = None;
+        let mut in_flight = None;
+        let mut last_used = None;
+        let mut last_call_error = None;
+        if let Some(extension) = self.extensions.lock().await.get(&client_name) {
+            if let Err(retry_in) = extension.breaker.check() {
+                let error = if extension.crashed.load(Ordering::SeqCst) {
+                    extension_crashed_error(&client_name)
+                } else {
+                    circuit_open_error(&client_name, retry_in)
+                };
+                return Err(error.into());
+            }
+            call_permits = Some(Arc::clone(&extension.call_permits));
+            crashed = Some(Arc::clone(&extension.crashed));
+            breaker = Some(Arc::clone(&extension.breaker));
+            in_flight = Some(Arc::clone(&extension.in_flight));
+            last_used = Some(Arc::clone(&extension.last_used));
+            last_call_error = Some(Arc::clone(&extension.last_call_error));
+            if !extension.is_tool_available(&tool_name) {
+                return Err(ErrorData::new(
+                    ErrorCode::RESOURCE_NOT_FOUND,
+                    format!(
+                        "Tool '{}' is not available for extension '{}'",
+                        tool_name, client_name
+                    ),
+                    None,
+                )
+                .into());
             }
         }
 
-        content.push_str("\n</info-msg>");
-
-        Some(content)
-    }
-}
+        // Only check against already listed tools; listing them here could block on
+        // extensions that are busy with other calls
+        let cached_tools = self.tools_cache.lock().await.clone();
+        let mut deprecation_notice = None;
+        if let Some(tools) = cached_tools {
+            let prefix = self.display_name(&client_name);
+            let listed_name = format!("{}__{}", prefix, tool_name);
+            let listed_tool = tools.iter().find(|tool| tool.name == listed_name);
+            deprecation_notice = listed_tool
+                .and_then(|tool| ToolDeprecation::from_meta(tool.meta.as_ref()))
+                .map(|deprecation| deprecation.notice(&prefix));
+            let missing = listed_tool
+                .map(|tool| {
+                    missing_required_arguments(&tool.input_schema, tool_call.arguments.as_ref())
+                })
+                .unwrap_or_default();
+            if !missing.is_empty() {
+                return Ok(ToolCallResult::from(Ok(CallToolResult::structured_error(
+                    serde_json::json!({
+                        "error": "missing_arguments",
+                        "message": format!(
+                            "Tool '{}' was called without required arguments; add them and retry",
+                            tool_call.name
+                        ),
+                        "missing_arguments": missing,
+                    }),
+                ))));
+            }
+        }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use rmcp::model::CallToolResult;
-    use rmcp::model::{InitializeResult, JsonObject};
-    use rmcp::{object, ServiceError as Error};
+        if !is_tool_budget_exempt(&client_name, &tool_name)
+            && !self.context.tool_budgets.try_consume(session_id)
+        {
+            return Ok(ToolCallResult::from(Ok(CallToolResult::error(vec![
+                Content::text(
+                    "Tool call budget exhausted for this session. Use get_tool_budget to check \
+                     the budget, or ask the user before raising it with set_tool_budget.",
+                ),
+            ]))));
+        }
 
-    use rmcp::model::ListPromptsResult;
-    use rmcp::model::ListResourcesResult;
-    use rmcp::model::ListToolsResult;
-    use rmcp::model::ReadResourceResult;
-    use rmcp::model::ServerNotification;
+        let mut arguments = tool_call.arguments.clone();
+        let store_result = arguments
+            .as_mut()
+            .and_then(|args| args.remove(STORE_RESULT_ARG))
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let priority = take_tool_call_priority(tool_call.meta.as_ref(), arguments.as_mut());
+        let stored_results = Arc::clone(&self.stored_results);
+        let lifecycle_tx = self.lifecycle_tx.clone();
+        let observed_errors = Arc::clone(&self.observed_errors);
+        let client = client.clone();
+        let notifications_receiver = client.lock().await.subscribe().await;
+        let session_id = session_id.to_string();
+        let working_dir_str = working_dir.map(|p| p.to_string_lossy().to_string());
 
-    use tokio::sync::mpsc;
+        // A child token lets cancel_extension_operations abort this call without affecting
+        // the caller's token
+        let cancellation_token = cancellation_token.child_token();
+        let in_flight_guard = in_flight.map(|calls| calls.register(cancellation_token.clone()));
 
-    impl ExtensionManager {
-        async fn add_mock_extension(&self, name: String, client: McpClientBox) {
-            self.add_mock_extension_with_tools(name, client, vec![])
+        let fut = async move {
+            let _in_flight_guard = in_flight_guard;
+            tracing::debug!(
+                "dispatch_tool_call fut: calling client.call_tool tool={} session_id={} working_dir={:?}",
+                tool_name,
+                session_id,
+                working_dir_str
+            );
+            let _permit = match &call_permits {
+                Some(permits) => Some(permits.acquire(priority).await),
+                None => None,
+            };
+            let client_guard = client.lock().await;
+            let result = client_guard
+                .call_tool(
+                    &session_id,
+                    &tool_name,
+                    arguments,
+                    working_dir_str.as_deref(),
+                    cancellation_token,
+                )
                 .await;
-        }
+            drop(client_guard);
+
+            let error_category = match &result {
+                Ok(result) if result.is_error == Some(true) => Some(ToolErrorCategory::ToolError),
+                Ok(_) => None,
+                Err(e) => Some(ToolErrorCategory::from_service_error(e)),
+            };
+            if let Some(category) = error_category {
+                *observed_errors
+                    .lock()
+                    .await
+                    .entry(client_name.clone())
+                    .or_default()
+                    .entry(category)
+                    .or_insert(0) += 1;
+            }
+
+            if let Some(last_call_error) = last_call_error.as_deref() {
+                *last_call_error.lock().expect("last call error poisoned") = error_category;
+            }
+            // Crashes trip the breaker when they're marked below. Any answer from the
+            // extension shows it is alive again, e.g. a trial call after a cooldown.
+            match (error_category, breaker.as_deref()) {
+                (Some(ToolErrorCategory::Crashed | ToolErrorCategory::Cancelled), _)
+                | (_, None) => {}
+                (Some(ToolErrorCategory::Timeout | ToolErrorCategory::Protocol), Some(breaker)) => {
+                    breaker.record_failure()
+                }
+                (_, Some(breaker)) => {
+                    breaker.record_success();
+                    if let Some(crashed) = crashed.as_deref() {
+                        crashed.store(false, Ordering::SeqCst);
+                    }
+                }
+            }
+            if error_category.is_none() {
+                if let Some(last_used) = last_used.as_deref() {
+                    *last_used.lock().expect("last used poisoned") = Some(Utc::now());
+                }
+            }
+
+            let result = result.map_err(|e| match e {
+                ServiceError::McpError(error_data) => error_data,
+                // The transport going away mid-call means the extension process died
+                ServiceError::TransportClosed | ServiceError::TransportSend(_) => {
+                    mark_extension_crashed(
+                        &client_name,
+                        crashed.as_deref(),
+                        breaker.as_deref(),
+                        &lifecycle_tx,
+                    )
+                }
+                _ => ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), e.maybe_to_value()),
+            })?;
+
+            let mut result = if store_result && result.is_error != Some(true) {
+                store_tool_result(&stored_results, &session_id, result).await
+            } else {
+                result
+            };
+            if let Some(notice) = deprecation_notice {
+                result.content.push(Content::text(notice));
+            }
+            Ok(result)
+        };
+
+        Ok(ToolCallResult {
+            result: Box::new(fut.boxed()),
+            notification_stream: Some(Box::new(ReceiverStream::new(notifications_receiver))),
+        })
+    }
+
+    /// Cancel every in-flight tool call to an extension without disabling it.
+    /// Returns the number of calls cancelled, or None if the extension isn't enabled.
+    pub async fn cancel_extension_operations(&self, extension_name: &str) -> Option<usize> {
+        let normalized = self.resolve_extension_name(extension_name);
+        self.extensions
+            .lock()
+            .await
+            .get(&normalized)
+            .map(|ext| ext.in_flight.cancel_all())
+    }
+
+    /// Enabled extensions without a successful tool call in the last `window`, oldest first.
+    /// An extension that was never used counts as idle once it has been enabled that long.
+    pub async fn idle_extensions(
+        &self,
+        session_id: &str,
+        window: Duration,
+    ) -> ExtensionResult<Vec<IdleExtension>> {
+        let Some(cutoff) = chrono::Duration::from_std(window)
+            .ok()
+            .and_then(|window| Utc::now().checked_sub_signed(window))
+        else {
+            return Ok(Vec::new());
+        };
+        let own_key = name_to_key(extension_manager_extension::EXTENSION_NAME);
+
+        let mut idle: Vec<IdleExtension> = self
+            .extensions
+            .lock()
+            .await
+            .iter()
+            .filter(|(name, _)| **name != own_key)
+            .filter_map(|(name, extension)| {
+                let last_used = *extension.last_used.lock().expect("last used poisoned");
+                (last_used.unwrap_or(extension.enabled_at) < cutoff).then(|| IdleExtension {
+                    name: name.clone(),
+                    last_used,
+                    tool_count: 0,
+                })
+            })
+            .collect();
+        if idle.is_empty() {
+            return Ok(idle);
+        }
+
+        let tools = self.get_all_tools_cached(session_id).await?;
+        for extension in &mut idle {
+            extension.tool_count = self.filter_tools(&tools, Some(&extension.name), None).len();
+        }
+        idle.sort_by_key(|extension| extension.last_used);
+        Ok(idle)
+    }
+
+    /// Make a lightweight round-trip (a tool listing) to an extension and time it.
+    /// Returns None if the extension is not enabled.
+    pub async fn ping_extension(
+        &self,
+        session_id: &str,
+        extension_name: &str,
+    ) -> Option<ExtensionPing> {
+        let normalized = self.resolve_extension_name(extension_name);
+        let (transport, client, crashed) = {
+            let extensions = self.extensions.lock().await;
+            let extension = extensions.get(&normalized)?;
+            let transport = match extension.config {
+                ExtensionConfig::StreamableHttp { .. } | ExtensionConfig::Sse { .. } => {
+                    ExtensionTransport::Remote
+                }
+                ExtensionConfig::Platform { .. } => ExtensionTransport::InProcess,
+                _ => ExtensionTransport::Local,
+            };
+            (
+                transport,
+                extension.get_client(),
+                extension.crashed.load(Ordering::SeqCst),
+            )
+        };
+
+        // Platform extensions run inside this process, and the one serving the ping may
+        // already hold its own client lock, so there is nothing to round-trip to
+        if transport == ExtensionTransport::InProcess {
+            return Some(ExtensionPing {
+                transport,
+                crashed,
+                result: Ok(Duration::ZERO),
+            });
+        }
+
+        let token = CancellationToken::new();
+        let result = tokio::time::timeout(PING_TIMEOUT, async {
+            let client = client.lock().await;
+            let started = std::time::Instant::now();
+            client
+                .list_tools(session_id, None, token.clone())
+                .await
+                .map(|_| started.elapsed())
+        })
+        .await
+        .unwrap_or_else(|_| {
+            token.cancel();
+            Err(ServiceError::Timeout {
+                timeout: PING_TIMEOUT,
+            })
+        });
+
+        Some(ExtensionPing {
+            transport,
+            crashed,
+            result,
+        })
+    }
+
+    /// Error categories observed from calls to an extension, with how often each was seen
+    pub async fn get_observed_errors(
+        &self,
+        extension_name: &str,
+    ) -> Option<BTreeMap<ToolErrorCategory, usize>> {
+        let normalized = self.resolve_extension_name(extension_name);
+        if !self.extensions.lock().await.contains_key(&normalized) {
+            return None;
+        }
+        Some(
+            self.observed_errors
+                .lock()
+                .await
+                .get(&normalized)
+                .cloned()
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Redeem a handle produced by a tool call made with `STORE_RESULT_ARG`. The result comes
+    /// back whole, including any structured content the tool produced.
+    pub async fn fetch_stored_result(
+        &self,
+        session_id: &str,
+        handle: &str,
+    ) -> Result<CallToolResult, ErrorData> {
+        self.stored_results
+            .lock()
+            .await
+            .get_mut(session_id)
+            .and_then(|results| results.get(handle).cloned())
+            .ok_or_else(|| {
+                ErrorData::new(
+                    ErrorCode::RESOURCE_NOT_FOUND,
+                    format!(
+                        "No stored result for handle '{}' in this session. It may have expired.",
+                        handle
+                    ),
+                    None,
+                )
+            })
+    }
+
+    pub async fn list_prompts_from_extension(
+        &self,
+        session_id: &str,
+        extension_name: &str,
+        cancellation_token: CancellationToken,
+    ) -> Result<Vec<Prompt>, ErrorData> {
+        let client = self
+            .get_server_client(extension_name)
+            .await
+            .ok_or_else(|| {
+                ErrorData::new(
+                    ErrorCode::INVALID_PARAMS,
+                    format!("Extension {} is not valid", extension_name),
+                    None,
+                )
+            })?;
+
+        let client_guard = client.lock().await;
+        client_guard
+            .list_prompts(session_id, None, cancellation_token)
+            .await
+            .map_err(|e| {
+                ErrorData::new(
+                    ErrorCode::INTERNAL_ERROR,
+                    format!("Unable to list prompts for {}, {:?}", extension_name, e),
+                    None,
+                )
+            })
+            .map(|lp| lp.prompts)
+    }
+
+    pub async fn list_prompts(
+        &self,
+        session_id: &str,
+        cancellation_token: CancellationToken,
+    ) -> Result<HashMap<String, Vec<Prompt>>, ErrorData> {
+        let mut futures = FuturesUnordered::new();
+
+        let names: Vec<_> = self.extensions.lock().await.keys().cloned().collect();
+        for extension_name in names {
+            let token = cancellation_token.clone();
+            futures.push(async move {
+                (
+                    extension_name.clone(),
+                    self.list_prompts_from_extension(session_id, extension_name.as_str(), token)
+                        .await,
+                )
+            });
+        }
+
+        let mut all_prompts = HashMap::new();
+        let mut errors = Vec::new();
+
+        // Process results as they complete
+        while let Some(result) = futures.next().await {
+            let (name, prompts) = result;
+            match prompts {
+                Ok(content) => {
+                    all_prompts.insert(name.to_string(), content);
+                }
+                Err(tool_error) => {
+                    errors.push(tool_error);
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            tracing::debug!(
+                errors = ?errors
+                    .into_iter()
+                    .map(|e| format!("{:?}", e))
+                    .collect::<Vec<_>>(),
+                "errors from listing prompts"
+            );
+        }
+
+        Ok(all_prompts)
+    }
+
+    pub async fn get_prompt(
+        &self,
+        session_id: &str,
+        extension_name: &str,
+        name: &str,
+        arguments: Value,
+        cancellation_token: CancellationToken,
+    ) -> Result<GetPromptResult> {
+        let client = self
+            .get_server_client(extension_name)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Extension {} not found", extension_name))?;
+
+        let client_guard = client.lock().await;
+        client_guard
+            .get_prompt(session_id, name, arguments, cancellation_token)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to get prompt: {}", e))
+    }
+
+    /// Get a prompt from whichever enabled extension defines it, for the extension manager
+    /// extension, which passes prompts through without knowing where they live. When several
+    /// extensions define the prompt, `name` must be qualified as `extension__prompt`.
+    ///
+    /// The extension manager extension itself is skipped: it is the one asking, and its
+    /// client is locked for the duration of the call.
+    pub async fn route_prompt(
+        &self,
+        session_id: &str,
+        name: &str,
+        arguments: Value,
+        cancellation_token: CancellationToken,
+    ) -> Result<GetPromptResult, ErrorData> {
+        let own_key = name_to_key(extension_manager_extension::EXTENSION_NAME);
+        let mut names: Vec<String> = self
+            .extensions
+            .lock()
+            .await
+            .keys()
+            .filter(|key| **key != own_key)
+            .cloned()
+            .collect();
+        names.sort();
+
+        let qualified = name.split_once("__").and_then(|(extension, prompt)| {
+            let key = self.resolve_extension_name(extension);
+            names.contains(&key).then(|| (key, prompt.to_string()))
+        });
+        let (extension_name, prompt_name) = match qualified {
+            Some(qualified) => qualified,
+            None => {
+                let listings = future::join_all(names.iter().map(|extension_name| {
+                    self.list_prompts_from_extension(
+                        session_id,
+                        extension_name,
+                        cancellation_token.clone(),
+                    )
+                }))
+                .await;
+                let providers: Vec<&String> = names
+                    .iter()
+                    .zip(listings)
+                    .filter(|(_name, prompts)| {
+                        prompts
+                            .as_ref()
+                            .is_ok_and(|prompts| prompts.iter().any(|p| p.name == name))
+                    })
+                    .map(|(extension_name, _prompts)| extension_name)
+                    .collect();
+                match providers.as_slice() {
+                    [] => {
+                        return Err(ErrorData::new(
+                            ErrorCode::INVALID_PARAMS,
+                            format!("Prompt '{}' not found in any enabled extension", name),
+                            None,
+                        ))
+                    }
+                    [extension_name] => ((*extension_name).clone(), name.to_string()),
+                    several => {
+                        return Err(ErrorData::new(
+                            ErrorCode::INVALID_PARAMS,
+                            format!(
+                                "Several extensions define the prompt '{}': {}. Qualify the name \
+                                 with one, e.g. '{}__{}'.",
+                                name,
+                                several
+                                    .iter()
+                                    .map(|name| name.as_str())
+                                    .collect::<Vec<_>>()
+                                    .join(", "),
+                                several[0],
+                                name
+                            ),
+                            None,
+                        ))
+                    }
+                }
+            }
+        };
+
+        let client = self
+            .get_server_client(&extension_name)
+            .await
+            .ok_or_else(|| {
+                ErrorData::new(
+                    ErrorCode::INVALID_PARAMS,
+                    format!("Extension {} is not valid", extension_name),
+                    None,
+                )
+            })?;
+        let client_guard = client.lock().await;
+        client_guard
+            .get_prompt(session_id, &prompt_name, arguments, cancellation_token)
+            .await
+            .map_err(|e| match e {
+                ServiceError::McpError(error_data) => error_data,
+                other => ErrorData::new(
+                    ErrorCode::INTERNAL_ERROR,
+                    format!(
+                        "Failed to get prompt '{}' from {}: {}",
+                        prompt_name, extension_name, other
+                    ),
+                    None,
+                ),
+            })
+    }
+
+    /// Describe the extensions that can be enabled or disabled. A query keeps only the
+    /// extensions to enable whose name or description mention its words, best match first,
+    /// and a category keeps only those of that type (e.g. "stdio" or "builtin").
+    pub async fn search_available_extensions(
+        &self,
+        query: Option<&str>,
+        category: Option<&str>,
+    ) -> Result<Vec<Content>, ErrorData> {
+        let mut output_parts = vec![];
+
+        // First get disabled extensions from current config
+        let mut disabled_extensions: Vec<(usize, String, String)> = vec![];
+        let mut catalog_size = 0;
+        for extension in get_all_extensions() {
+            if !extension.enabled {
+                catalog_size += 1;
+                let config = extension.config.clone();
+                if category.is_some_and(|category| {
+                    !extension_category(&config).eq_ignore_ascii_case(category)
+                }) {
+                    continue;
+                }
+                let description = match &config {
+                    ExtensionConfig::Builtin {
+                        description,
+                        display_name,
+                        ..
+                    } => {
+                        if description.is_empty() {
+                            display_name.as_deref().unwrap_or("Built-in extension")
+                        } else {
+                            description
+                        }
+                    }
+                    ExtensionConfig::Sse { .. } => "SSE extension (unsupported)",
+                    ExtensionConfig::Platform { description, .. }
+                    | ExtensionConfig::StreamableHttp { description, .. }
+                    | ExtensionConfig::Stdio { description, .. }
+                    | ExtensionConfig::Frontend { description, .. }
+                    | ExtensionConfig::InlinePython { description, .. } => description,
+                };
+                let score = match query {
+                    Some(query) => {
+                        match extension_match_score(query, &config.name(), description) {
+                            0 => continue,
+                            score => score,
+                        }
+                    }
+                    None => 0,
+                };
+                let line = match self.context.result_format {
+                    ResultFormat::Plain => format!("- {} - {}", config.name(), description),
+                    ResultFormat::Markdown => {
+                        format!("- **{}** - {}", config.name(), description)
+                    }
+                };
+                disabled_extensions.push((score, config.name(), line));
+            }
+        }
+        if query.is_some() {
+            disabled_extensions.sort_by(|(a_score, a_name, _), (b_score, b_name, _)| {
+                b_score.cmp(a_score).then_with(|| a_name.cmp(b_name))
+            });
+        }
+        let matched = disabled_extensions.len();
+        let disabled_extensions: Vec<String> = disabled_extensions
+            .into_iter()
+            .map(|(_, _, line)| line)
+            .collect();
+
+        // Get currently enabled extensions that can be disabled
+        let enabled_extensions: Vec<String> = self
+            .extensions
+            .lock()
+            .await
+            .keys()
+            .map(|key| match self.display_name(key) {
+                alias if alias != *key => format!("{} (alias of {})", alias, key),
+                name => name,
+            })
+            .collect();
+
+        let heading = match self.context.result_format {
+            ResultFormat::Plain => "",
+            ResultFormat::Markdown => "### ",
+        };
+
+        // Build output string
+        if query.is_some() || category.is_some() {
+            output_parts.push(format!(
+                "{} of {} extension(s) available to enable match the search.",
+                matched, catalog_size
+            ));
+        }
+        if !disabled_extensions.is_empty() {
+            output_parts.push(format!(
+                "{}Extensions available to enable:\n{}\n",
+                heading,
+                disabled_extensions.join("\n")
+            ));
+        } else {
+            output_parts.push("No extensions available to enable.\n".to_string());
+        }
+
+        if !enabled_extensions.is_empty() {
+            output_parts.push(format!(
+                "\n\n{}Extensions available to disable:\n{}\n",
+                heading,
+                enabled_extensions
+                    .iter()
+                    .map(|name| format!("- {}", name))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ));
+        } else {
+            output_parts.push("No extensions that can be disabled.\n".to_string());
+        }
+
+        Ok(vec![Content::text(output_parts.join("\n"))])
+    }
+
+    async fn get_server_client(&self, name: impl Into<String>) -> Option<McpClientBox> {
+        let normalized = self.resolve_extension_name(&name.into());
+        self.extensions
+            .lock()
+            .await
+            .get(&normalized)
+            .map(|ext| ext.get_client())
+    }
+
+    pub async fn collect_moim(
+        &self,
+        session_id: &str,
+        working_dir: &std::path::Path,
+    ) -> Option<String> {
+        // Use minute-level granularity to prevent conversation changes every second
+        let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:00").to_string();
+        let mut content = format!(
+            "<info-msg>\nIt is currently {}\nWorking directory: {}\n",
+            timestamp,
+            working_dir.display()
+        );
+
+        let platform_clients: Vec<(String, McpClientBox)> = {
+            let extensions = self.extensions.lock().await;
+            extensions
+                .iter()
+                .filter_map(|(name, extension)| {
+                    if let ExtensionConfig::Platform { .. } = &extension.config {
+                        Some((name.clone(), extension.get_client()))
+                    } else {
+                        None
+                    }
+                })
+                .collect()
+        };
+
+        for (name, client) in platform_clients {
+            let client_guard = client.lock().await;
+            if let Some(moim_content) = client_guard.get_moim(session_id).await {
+                tracing::debug!("MOIM content from {}: {} chars", name, moim_content.len());
+                content.push('\n');
+                content.push_str(&moim_content);
+            }
+        }
+
+        content.push_str("\n</info-msg>");
+
+        Some(content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::CallToolResult;
+    use rmcp::model::{InitializeResult, JsonObject};
+    use rmcp::{object, ServiceError as Error};
+
+    use rmcp::model::ListPromptsResult;
+    use rmcp::model::ListResourcesResult;
+    use rmcp::model::ListToolsResult;
+    use rmcp::model::ReadResourceResult;
+    use rmcp::model::ServerNotification;
+
+    use std::sync::atomic::AtomicUsize;
+    use tokio::sync::mpsc;
+
+    fn from_json<T: serde::de::DeserializeOwned>(params: Value) -> T {
+        serde_json::from_value(params).unwrap()
+    }
+
+    impl ExtensionManager {
+        async fn add_mock_extension(&self, name: String, client: McpClientBox) {
+            self.add_mock_extension_with_tools(name, client, vec![])
+                .await;
+        }
+
+        async fn add_mock_extension_with_tools(
+            &self,
+            name: String,
+            client: McpClientBox,
+            available_tools: Vec<String>,
+        ) {
+            let sanitized_name = name_to_key(&name);
+            let config = ExtensionConfig::Builtin {
+                name: name.clone(),
+                display_name: Some(name.clone()),
+                description: "built-in".to_string(),
+                timeout: None,
+                bundled: None,
+                available_tools,
+            };
+            let extension = Extension::new(config, client, None, None);
+            self.extensions
+                .lock()
+                .await
+                .insert(sanitized_name, extension);
+            self.invalidate_tools_cache_and_bump_version().await;
+        }
+    }
+
+    struct MockClient {}
+
+    #[async_trait::async_trait]
+    impl McpClientTrait for MockClient {
+        fn get_info(&self) -> Option<&InitializeResult> {
+            None
+        }
+
+        async fn list_resources(
+            &self,
+            _session_id: &str,
+            _next_cursor: Option<String>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<ListResourcesResult, Error> {
+            Err(Error::TransportClosed)
+        }
+
+        async fn read_resource(
+            &self,
+            _session_id: &str,
+            _uri: &str,
+            _cancellation_token: CancellationToken,
+        ) -> Result<ReadResourceResult, Error> {
+            Err(Error::TransportClosed)
+        }
+
+        async fn list_tools(
+            &self,
+            _session_id: &str,
+            _next_cursor: Option<String>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<ListToolsResult, Error> {
+            use serde_json::json;
+            use std::sync::Arc;
+            Ok(ListToolsResult {
+                tools: vec![
+                    Tool::new(
+                        "tool".to_string(),
+                        "A basic tool".to_string(),
+                        Arc::new(json!({}).as_object().unwrap().clone()),
+                    ),
+                    Tool::new(
+                        "available_tool".to_string(),
+                        "An available tool".to_string(),
+                        Arc::new(json!({}).as_object().unwrap().clone()),
+                    ),
+                    Tool::new(
+                        "hidden_tool".to_string(),
+                        "hidden tool".to_string(),
+                        Arc::new(json!({}).as_object().unwrap().clone()),
+                    ),
+                ],
+                next_cursor: None,
+                meta: None,
+            })
+        }
+
+        async fn call_tool(
+            &self,
+            _session_id: &str,
+            name: &str,
+            _arguments: Option<JsonObject>,
+            _working_dir: Option<&str>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<CallToolResult, Error> {
+            match name {
+                "tool" | "test__tool" | "available_tool" | "hidden_tool" => Ok(CallToolResult {
+                    content: vec![],
+                    is_error: None,
+                    structured_content: None,
+                    meta: None,
+                }),
+                _ => Err(Error::TransportClosed),
+            }
+        }
+
+        async fn list_prompts(
+            &self,
+            _session_id: &str,
+            _next_cursor: Option<String>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<ListPromptsResult, Error> {
+            Err(Error::TransportClosed)
+        }
+
+        async fn get_prompt(
+            &self,
+            _session_id: &str,
+            _name: &str,
+            _arguments: Value,
+            _cancellation_token: CancellationToken,
+        ) -> Result<GetPromptResult, Error> {
+            Err(Error::TransportClosed)
+        }
+
+        async fn subscribe(&self) -> mpsc::Receiver<ServerNotification> {
+            mpsc::channel(1).1
+        }
+    }
+
+    /// Serves a fixed resource list, or never answers when `hang` is set
+    struct ResourceClient {
+        hang: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl McpClientTrait for ResourceClient {
+        fn get_info(&self) -> Option<&InitializeResult> {
+            None
+        }
+
+        async fn list_resources(
+            &self,
+            _session_id: &str,
+            _next_cursor: Option<String>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<ListResourcesResult, Error> {
+            use rmcp::model::{AnnotateAble, RawResource};
+
+            if self.hang {
+                future::pending::<()>().await;
+            }
+            Ok(ListResourcesResult {
+                resources: vec![RawResource::new("file:///notes.md", "notes").no_annotation()],
+                next_cursor: None,
+                meta: None,
+            })
+        }
+
+        async fn read_resource(
+            &self,
+            _session_id: &str,
+            uri: &str,
+            _cancellation_token: CancellationToken,
+        ) -> Result<ReadResourceResult, Error> {
+            // Ignores the token, like a client that doesn't support cancellation
+            if self.hang {
+                future::pending::<()>().await;
+            }
+            Ok(ReadResourceResult {
+                contents: vec![ResourceContents::text("notes", uri)],
+            })
+        }
+
+        async fn list_tools(
+            &self,
+            _session_id: &str,
+            _next_cursor: Option<String>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<ListToolsResult, Error> {
+            Ok(ListToolsResult::default())
+        }
+
+        async fn call_tool(
+            &self,
+            _session_id: &str,
+            _name: &str,
+            _arguments: Option<JsonObject>,
+            _working_dir: Option<&str>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<CallToolResult, Error> {
+            Err(Error::TransportClosed)
+        }
+    }
+
+    /// Has a single tool with a required `path` argument
+    struct RequiredArgsClient {}
+
+    #[async_trait::async_trait]
+    impl McpClientTrait for RequiredArgsClient {
+        fn get_info(&self) -> Option<&InitializeResult> {
+            None
+        }
+
+        async fn list_tools(
+            &self,
+            _session_id: &str,
+            _next_cursor: Option<String>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<ListToolsResult, Error> {
+            let schema = object!({
+                "type": "object",
+                "required": ["path"],
+                "properties": { "path": { "type": "string", "description": "File to read" } }
+            });
+            Ok(ListToolsResult {
+                tools: vec![Tool::new("read", "Read a file", Arc::new(schema))],
+                next_cursor: None,
+                meta: None,
+            })
+        }
+
+        async fn call_tool(
+            &self,
+            _session_id: &str,
+            _name: &str,
+            _arguments: Option<JsonObject>,
+            _working_dir: Option<&str>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<CallToolResult, Error> {
+            Ok(CallToolResult::success(vec![]))
+        }
+    }
+
+    /// Blocks every tool call until it is cancelled
+    struct BlockingClient {}
+
+    #[async_trait::async_trait]
+    impl McpClientTrait for BlockingClient {
+        fn get_info(&self) -> Option<&InitializeResult> {
+            None
+        }
+
+        async fn list_tools(
+            &self,
+            _session_id: &str,
+            _next_cursor: Option<String>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<ListToolsResult, Error> {
+            Ok(ListToolsResult {
+                tools: vec![],
+                next_cursor: None,
+                meta: None,
+            })
+        }
+
+        async fn call_tool(
+            &self,
+            _session_id: &str,
+            _name: &str,
+            _arguments: Option<JsonObject>,
+            _working_dir: Option<&str>,
+            cancellation_token: CancellationToken,
+        ) -> Result<CallToolResult, Error> {
+            cancellation_token.cancelled().await;
+            Err(Error::Cancelled { reason: None })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_client_for_tool() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+
+        // Add some mock clients using the helper method
+        extension_manager
+            .add_mock_extension(
+                "test_client".to_string(),
+                Arc::new(Mutex::new(Box::new(MockClient {}))),
+            )
+            .await;
+
+        extension_manager
+            .add_mock_extension(
+                "__client".to_string(),
+                Arc::new(Mutex::new(Box::new(MockClient {}))),
+            )
+            .await;
+
+        extension_manager
+            .add_mock_extension(
+                "__cli__ent__".to_string(),
+                Arc::new(Mutex::new(Box::new(MockClient {}))),
+            )
+            .await;
+
+        extension_manager
+            .add_mock_extension(
+                "client 🚀".to_string(),
+                Arc::new(Mutex::new(Box::new(MockClient {}))),
+            )
+            .await;
+
+        // Test basic case
+        assert!(extension_manager
+            .get_client_for_tool("test_client__tool")
+            .await
+            .is_some());
+
+        // Test leading underscores
+        assert!(extension_manager
+            .get_client_for_tool("__client__tool")
+            .await
+            .is_some());
+
+        // Test multiple underscores in client name, and ending with __
+        assert!(extension_manager
+            .get_client_for_tool("__cli__ent____tool")
+            .await
+            .is_some());
+
+        // Test unicode in tool name, "client 🚀" should become "client_"
+        assert!(extension_manager
+            .get_client_for_tool("client___tool")
+            .await
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_tool_call() {
+        // test that dispatch_tool_call parses out the sanitized name correctly, and extracts
+        // tool_names
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+
+        // Add some mock clients using the helper method
+        extension_manager
+            .add_mock_extension(
+                "test_client".to_string(),
+                Arc::new(Mutex::new(Box::new(MockClient {}))),
+            )
+            .await;
+
+        extension_manager
+            .add_mock_extension(
+                "__cli__ent__".to_string(),
+                Arc::new(Mutex::new(Box::new(MockClient {}))),
+            )
+            .await;
+
+        extension_manager
+            .add_mock_extension(
+                "client 🚀".to_string(),
+                Arc::new(Mutex::new(Box::new(MockClient {}))),
+            )
+            .await;
+
+        // verify a normal tool call
+        let tool_call = CallToolRequestParams {
+            meta: None,
+            task: None,
+            name: "test_client__tool".to_string().into(),
+            arguments: Some(object!({})),
+        };
+
+        let result = extension_manager
+            .dispatch_tool_call(
+                "test-session-id",
+                tool_call,
+                None,
+                CancellationToken::default(),
+            )
+            .await;
+        assert!(result.is_ok());
+
+        let tool_call = CallToolRequestParams {
+            meta: None,
+            task: None,
+            name: "test_client__test__tool".to_string().into(),
+            arguments: Some(object!({})),
+        };
+
+        let result = extension_manager
+            .dispatch_tool_call(
+                "test-session-id",
+                tool_call,
+                None,
+                CancellationToken::default(),
+            )
+            .await;
+        assert!(result.is_ok());
+
+        // verify a multiple underscores dispatch
+        let tool_call = CallToolRequestParams {
+            meta: None,
+            task: None,
+            name: "__cli__ent____tool".to_string().into(),
+            arguments: Some(object!({})),
+        };
+
+        let result = extension_manager
+            .dispatch_tool_call(
+                "test-session-id",
+                tool_call,
+                None,
+                CancellationToken::default(),
+            )
+            .await;
+        assert!(result.is_ok());
+
+        // Test unicode in tool name, "client 🚀" should become "client_"
+        let tool_call = CallToolRequestParams {
+            meta: None,
+            task: None,
+            name: "client___tool".to_string().into(),
+            arguments: Some(object!({})),
+        };
+
+        let result = extension_manager
+            .dispatch_tool_call(
+                "test-session-id",
+                tool_call,
+                None,
+                CancellationToken::default(),
+            )
+            .await;
+        assert!(result.is_ok());
+
+        let tool_call = CallToolRequestParams {
+            meta: None,
+            task: None,
+            name: "client___test__tool".to_string().into(),
+            arguments: Some(object!({})),
+        };
+
+        let result = extension_manager
+            .dispatch_tool_call(
+                "test-session-id",
+                tool_call,
+                None,
+                CancellationToken::default(),
+            )
+            .await;
+        assert!(result.is_ok());
+
+        // this should error out, specifically for an ToolError::ExecutionError
+        let invalid_tool_call = CallToolRequestParams {
+            meta: None,
+            task: None,
+            name: "client___tools".to_string().into(),
+            arguments: Some(object!({})),
+        };
+
+        let result = extension_manager
+            .dispatch_tool_call(
+                "test-session-id",
+                invalid_tool_call,
+                None,
+                CancellationToken::default(),
+            )
+            .await
+            .unwrap()
+            .result
+            .await;
+        assert!(matches!(
+            result,
+            Err(ErrorData {
+                code: ErrorCode::INTERNAL_ERROR,
+                ..
+            })
+        ));
+
+        // this should error out, specifically with an ToolError::NotFound
+        // this client doesn't exist
+        let invalid_tool_call = CallToolRequestParams {
+            meta: None,
+            task: None,
+            name: "_client__tools".to_string().into(),
+            arguments: Some(object!({})),
+        };
+
+        let result = extension_manager
+            .dispatch_tool_call(
+                "test-session-id",
+                invalid_tool_call,
+                None,
+                CancellationToken::default(),
+            )
+            .await;
+        if let Err(err) = result {
+            let tool_err = err.downcast_ref::<ErrorData>().expect("Expected ErrorData");
+            assert_eq!(tool_err.code, ErrorCode::RESOURCE_NOT_FOUND);
+        } else {
+            panic!("Expected ErrorData with ErrorCode::RESOURCE_NOT_FOUND");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_tool_call_stores_result_as_reference() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+
+        extension_manager
+            .add_mock_extension(
+                "test_client".to_string(),
+                Arc::new(Mutex::new(Box::new(MockClient {}))),
+            )
+            .await;
+
+        let tool_call = CallToolRequestParams {
+            meta: None,
+            task: None,
+            name: "test_client__tool".to_string().into(),
+            arguments: Some(object!({ STORE_RESULT_ARG: true })),
+        };
+
+        let result = extension_manager
+            .dispatch_tool_call(
+                "test-session-id",
+                tool_call,
+                None,
+                CancellationToken::default(),
+            )
+            .await
+            .unwrap()
+            .result
+            .await
+            .unwrap();
+
+        let text = result.content[0].as_text().unwrap().text.clone();
+        let handle = regex::Regex::new(r"result_[0-9a-f]+")
+            .unwrap()
+            .find(&text)
+            .unwrap()
+            .as_str()
+            .to_string();
+
+        assert!(extension_manager
+            .fetch_stored_result("test-session-id", &handle)
+            .await
+            .is_ok());
+        assert!(extension_manager
+            .fetch_stored_result("other-session-id", &handle)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stored_result_keeps_structured_content() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+
+        let structured = serde_json::json!({ "rows": [1, 2, 3] });
+        let stored = store_tool_result(
+            &extension_manager.stored_results,
+            "test-session-id",
+            CallToolResult::structured(structured.clone()),
+        )
+        .await;
+        let text = stored.content[0].as_text().unwrap().text.clone();
+        let handle = regex::Regex::new(r"result_[0-9a-f]+")
+            .unwrap()
+            .find(&text)
+            .unwrap()
+            .as_str()
+            .to_string();
+
+        let fetched = extension_manager
+            .fetch_stored_result("test-session-id", &handle)
+            .await
+            .unwrap();
+        assert_eq!(fetched.structured_content, Some(structured));
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_tool_call_detects_crashed_extension() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+        let mut lifecycle = extension_manager.subscribe_lifecycle();
+
+        extension_manager
+            .add_mock_extension(
+                "test_client".to_string(),
+                Arc::new(Mutex::new(Box::new(MockClient {}))),
+            )
+            .await;
+
+        // MockClient drops the transport for tools it doesn't know, like a process exiting
+        let tool_call = CallToolRequestParams {
+            meta: None,
+            task: None,
+            name: "test_client__exits_mid_call".to_string().into(),
+            arguments: Some(object!({})),
+        };
+        let err = extension_manager
+            .dispatch_tool_call(
+                "test-session-id",
+                tool_call,
+                None,
+                CancellationToken::default(),
+            )
+            .await
+            .unwrap()
+            .result
+            .await
+            .unwrap_err();
+        assert!(err.message.contains("appears to have crashed"));
+
+        match lifecycle.try_recv().unwrap() {
+            (extension, ServerNotification::CustomNotification(notification)) => {
+                assert_eq!(extension, "test_client");
+                let params = notification.params.unwrap();
+                assert_eq!(params["extension"], "test_client");
+                assert_eq!(params["event_type"], "extension_crashed");
+            }
+            other => panic!("unexpected notification: {:?}", other),
+        }
+
+        // Further calls fail fast instead of hitting the dead transport
+        let tool_call = CallToolRequestParams {
+            meta: None,
+            task: None,
+            name: "test_client__tool".to_string().into(),
+            arguments: Some(object!({})),
+        };
+        let err = extension_manager
+            .dispatch_tool_call(
+                "test-session-id",
+                tool_call,
+                None,
+                CancellationToken::default(),
+            )
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("appears to have crashed"));
+
+        let observed = extension_manager
+            .get_observed_errors("test_client")
+            .await
+            .unwrap();
+        assert_eq!(observed.get(&ToolErrorCategory::Crashed), Some(&1));
+
+        let status = &extension_manager.extension_statuses().await[0];
+        assert!(status.crashed);
+        assert!(status.circuit_open);
+        assert_eq!(status.last_call_error, Some("crashed"));
+    }
+
+    #[tokio::test]
+    async fn test_extension_status_flags_outdated_protocol() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+        for (name, protocol_version) in [
+            ("current", ProtocolVersion::V_2025_03_26),
+            ("old", ProtocolVersion::V_2024_11_05),
+        ] {
+            let config = ExtensionConfig::Builtin {
+                name: name.to_string(),
+                display_name: None,
+                description: "built-in".to_string(),
+                timeout: None,
+                bundled: None,
+                available_tools: vec![],
+            };
+            let server_info = ServerInfo {
+                protocol_version,
+                ..Default::default()
+            };
+            let client: McpClientBox = Arc::new(Mutex::new(Box::new(MockClient {})));
+            extension_manager.extensions.lock().await.insert(
+                name.to_string(),
+                Extension::new(config, client, Some(server_info), None),
+            );
+        }
+
+        let statuses = extension_manager.extension_statuses().await;
+        let flags: Vec<_> = statuses
+            .iter()
+            .map(|status| {
+                (
+                    status.name.as_str(),
+                    status.protocol_version.as_deref(),
+                    status.protocol_outdated,
+                )
+            })
+            .collect();
+        assert_eq!(
+            flags,
+            vec![
+                ("current", Some("2025-03-26"), false),
+                ("old", Some("2024-11-05"), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing_required_arguments() {
+        let schema = object!({
+            "type": "object",
+            "required": ["path", "mode"],
+            "properties": {
+                "path": { "type": "string", "description": "File to open" },
+                "mode": { "type": "string" },
+            }
+        });
+
+        let missing = missing_required_arguments(&schema, Some(&object!({ "mode": "r" })));
+        assert_eq!(
+            missing,
+            vec![serde_json::json!({
+                "name": "path",
+                "type": "string",
+                "description": "File to open",
+            })]
+        );
+        assert_eq!(missing_required_arguments(&schema, None).len(), 2);
+        assert!(missing_required_arguments(
+            &schema,
+            Some(&object!({ "path": "a.txt", "mode": "r" }))
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_tool_deprecation_from_meta() {
+        let meta = Meta(object!({ "deprecated": true, "replacement": "read_v2" }));
+        let deprecation = ToolDeprecation::from_meta(Some(&meta)).unwrap();
+        assert_eq!(deprecation.replacement.as_deref(), Some("read_v2"));
+        assert_eq!(
+            deprecation.notice("files"),
+            "This tool is deprecated; use files__read_v2 instead."
+        );
+
+        let meta = Meta(object!({ "deprecated": true }));
+        assert_eq!(
+            ToolDeprecation::from_meta(Some(&meta)),
+            Some(ToolDeprecation { replacement: None })
+        );
+        assert_eq!(
+            ToolDeprecation::from_meta(Some(&Meta(object!({ "deprecated": false })))),
+            None
+        );
+        assert_eq!(ToolDeprecation::from_meta(None), None);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_tool_call_reports_missing_arguments() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+
+        extension_manager
+            .add_mock_extension(
+                "files".to_string(),
+                Arc::new(Mutex::new(Box::new(RequiredArgsClient {}))),
+            )
+            .await;
+        // Warm the tools cache the check reads from
+        extension_manager
+            .get_prefixed_tools("test-session-id", None)
+            .await
+            .unwrap();
+
+        let tool_call = CallToolRequestParams {
+            meta: None,
+            task: None,
+            name: "files__read".to_string().into(),
+            arguments: Some(object!({})),
+        };
+        let result = extension_manager
+            .dispatch_tool_call(
+                "test-session-id",
+                tool_call,
+                None,
+                CancellationToken::default(),
+            )
+            .await
+            .unwrap()
+            .result
+            .await
+            .unwrap();
+
+        assert_eq!(result.is_error, Some(true));
+        let structured = result.structured_content.unwrap();
+        assert_eq!(structured["error"], "missing_arguments");
+        assert_eq!(structured["missing_arguments"][0]["name"], "path");
+        assert_eq!(
+            structured["missing_arguments"][0]["description"],
+            "File to read"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_tool_call_enforces_tool_budget() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+
+        extension_manager
+            .add_mock_extension(
+                "test_client".to_string(),
+                Arc::new(Mutex::new(Box::new(MockClient {}))),
+            )
+            .await;
+        extension_manager
+            .get_context()
+            .tool_budgets
+            .set_limit("test-session-id", Some(1));
+
+        let call = |session_id: &'static str| {
+            let tool_call = CallToolRequestParams {
+                meta: None,
+                task: None,
+                name: "test_client__tool".to_string().into(),
+                arguments: Some(object!({})),
+            };
+            let extension_manager = &extension_manager;
+            async move {
+                extension_manager
+                    .dispatch_tool_call(session_id, tool_call, None, CancellationToken::default())
+                    .await
+                    .unwrap()
+                    .result
+                    .await
+                    .unwrap()
+            }
+        };
+
+        assert_ne!(call("test-session-id").await.is_error, Some(true));
+        assert_eq!(call("test-session-id").await.is_error, Some(true));
+        assert_ne!(call("other-session-id").await.is_error, Some(true));
+
+        let budget = extension_manager
+            .get_context()
+            .tool_budgets
+            .get("test-session-id");
+        assert_eq!(budget.used, 1);
+        assert_eq!(budget.remaining(), Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_ping_extension() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+
+        extension_manager
+            .add_mock_extension(
+                "test_client".to_string(),
+                Arc::new(Mutex::new(Box::new(MockClient {}))),
+            )
+            .await;
+
+        let ping = extension_manager
+            .ping_extension("test-session-id", "test_client")
+            .await
+            .unwrap();
+        assert_eq!(ping.transport, ExtensionTransport::Local);
+        assert!(!ping.crashed);
+        assert!(ping.result.is_ok());
+
+        assert!(extension_manager
+            .ping_extension("test-session-id", "missing")
+            .await
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_tool_call_by_stable_id() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+
+        extension_manager
+            .add_mock_extension(
+                "test_client".to_string(),
+                Arc::new(Mutex::new(Box::new(MockClient {}))),
+            )
+            .await;
+
+        let tool_id = stable_tool_id("test_client", "tool");
+        assert_eq!(tool_id, stable_tool_id("test_client", "tool"));
+        assert_ne!(tool_id, stable_tool_id("test_client", "available_tool"));
+
+        let tools = extension_manager
+            .get_prefixed_tools("test-session-id", None)
+            .await
+            .unwrap();
+        let tool = tools
+            .iter()
+            .find(|tool| tool.name == "test_client__tool")
+            .unwrap();
+        assert_eq!(
+            tool.meta.as_ref().unwrap().0.get(TOOL_ID_META_KEY),
+            Some(&Value::String(tool_id.clone()))
+        );
+
+        let tool_call = CallToolRequestParams {
+            meta: None,
+            task: None,
+            name: tool_id.into(),
+            arguments: Some(object!({})),
+        };
+        let output = extension_manager
+            .dispatch_tool_call(
+                "test-session-id",
+                tool_call,
+                None,
+                CancellationToken::default(),
+            )
+            .await
+            .unwrap()
+            .result
+            .await
+            .unwrap();
+        assert_eq!(output.is_error, None);
+        assert!(output.content.is_empty());
+
+        let unknown_id = CallToolRequestParams {
+            meta: None,
+            task: None,
+            name: stable_tool_id("test_client", "no_such_tool").into(),
+            arguments: Some(object!({})),
+        };
+        let error = extension_manager
+            .dispatch_tool_call(
+                "test-session-id",
+                unknown_id,
+                None,
+                CancellationToken::default(),
+            )
+            .await
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("not found"));
+    }
+
+    #[tokio::test]
+    async fn test_preview_extension_tools_leaves_active_set_unchanged() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+
+        let config = ExtensionConfig::Platform {
+            name: "todo".to_string(),
+            description: "todo".to_string(),
+            display_name: None,
+            bundled: None,
+            available_tools: vec![],
+        };
+        let tools = extension_manager
+            .preview_extension_tools("test-session-id", config)
+            .await
+            .unwrap();
+
+        assert!(!tools.is_empty());
+        assert!(tools.iter().all(|tool| tool.name.starts_with("todo__")));
+        assert!(!extension_manager.is_extension_enabled("todo").await);
+    }
+
+    #[tokio::test]
+    async fn test_limit_extension_tools() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+        extension_manager
+            .add_mock_extension(
+                "test_client".to_string(),
+                Arc::new(Mutex::new(Box::new(MockClient {}))),
+            )
+            .await;
+        let configs = extension_manager.get_extension_configs().await;
+
+        let total = extension_manager
+            .limit_extension_tools("test-session-id", "test_client", 2)
+            .await
+            .unwrap();
+        assert_eq!(total, Some(3));
+        // The limit lasts for the session but isn't written into the config
+        assert_eq!(extension_manager.get_extension_configs().await, configs);
+
+        let tools = extension_manager
+            .get_prefixed_tools("test-session-id", Some("test_client".to_string()))
+            .await
+            .unwrap();
+        let names: Vec<_> = tools.iter().map(|tool| tool.name.to_string()).collect();
+        assert_eq!(
+            names,
+            vec!["test_client__tool", "test_client__available_tool"]
+        );
+
+        let total = extension_manager
+            .limit_extension_tools("test-session-id", "test_client", 5)
+            .await
+            .unwrap();
+        assert_eq!(total, None);
+    }
+
+    #[tokio::test]
+    async fn test_prefixed_tools_are_sorted_by_name() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+        for name in ["zeta", "alpha", "mid"] {
+            extension_manager
+                .add_mock_extension(
+                    name.to_string(),
+                    Arc::new(Mutex::new(Box::new(MockClient {}))),
+                )
+                .await;
+        }
+
+        let names: Vec<String> = extension_manager
+            .get_prefixed_tools("test-session-id", None)
+            .await
+            .unwrap()
+            .iter()
+            .map(|tool| tool.name.to_string())
+            .collect();
+        assert_eq!(names.len(), 9);
+        assert!(names.is_sorted());
+        assert!(names[0].starts_with("alpha__"));
+    }
+
+    #[tokio::test]
+    async fn test_describe_tool() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+        extension_manager
+            .add_mock_extension(
+                "test_client".to_string(),
+                Arc::new(Mutex::new(Box::new(MockClient {}))),
+            )
+            .await;
+
+        let tool = extension_manager
+            .describe_tool("test-session-id", "test_client", "available_tool")
+            .await
+            .unwrap();
+        assert_eq!(tool.name, "available_tool");
+        assert_eq!(
+            tool.meta.as_ref().unwrap().0.get(TOOL_ID_META_KEY),
+            Some(&Value::String(stable_tool_id(
+                "test_client",
+                "available_tool"
+            )))
+        );
+
+        for (extension_name, tool_name) in [("test_client", "missing"), ("missing", "tool")] {
+            let error = extension_manager
+                .describe_tool("test-session-id", extension_name, tool_name)
+                .await
+                .unwrap_err();
+            assert_eq!(error.code, ErrorCode::RESOURCE_NOT_FOUND);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_filter_extension_tools_deny_wins() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+        extension_manager
+            .add_mock_extension(
+                "test_client".to_string(),
+                Arc::new(Mutex::new(Box::new(MockClient {}))),
+            )
+            .await;
+
+        let allow = vec!["tool".to_string(), "available_tool".to_string()];
+        let hidden = extension_manager
+            .filter_extension_tools(
+                "test-session-id",
+                "test_client",
+                Some(&allow),
+                &["available_tool".to_string()],
+            )
+            .await
+            .unwrap();
+        assert_eq!(hidden, vec!["available_tool", "hidden_tool"]);
+
+        let tools = extension_manager
+            .get_prefixed_tools("test-session-id", Some("test_client".to_string()))
+            .await
+            .unwrap();
+        let names: Vec<_> = tools.iter().map(|tool| tool.name.to_string()).collect();
+        assert_eq!(names, vec!["test_client__tool"]);
+
+        let error = extension_manager
+            .filter_extension_tools(
+                "test-session-id",
+                "test_client",
+                None,
+                &["tool".to_string()],
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn test_extension_alias() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+
+        extension_manager
+            .add_mock_extension(
+                "test_client".to_string(),
+                Arc::new(Mutex::new(Box::new(MockClient {}))),
+            )
+            .await;
+        extension_manager
+            .add_mock_extension(
+                "other".to_string(),
+                Arc::new(Mutex::new(Box::new(MockClient {}))),
+            )
+            .await;
+
+        assert!(extension_manager
+            .set_extension_alias("test_client", Some("other"))
+            .await
+            .is_err());
+        extension_manager
+            .set_extension_alias("test_client", Some("Docs"))
+            .await
+            .unwrap();
+
+        let tools = extension_manager
+            .get_prefixed_tools("test-session-id", Some("test_client".to_string()))
+            .await
+            .unwrap();
+        assert!(tools.iter().all(|tool| tool.name.starts_with("docs__")));
+        assert_eq!(
+            extension_manager.resolve_extension_name("docs"),
+            "test_client"
+        );
+
+        let tool_call = CallToolRequestParams {
+            meta: None,
+            task: None,
+            name: "docs__tool".to_string().into(),
+            arguments: Some(object!({})),
+        };
+        assert!(extension_manager
+            .dispatch_tool_call(
+                "test-session-id",
+                tool_call,
+                None,
+                CancellationToken::default()
+            )
+            .await
+            .is_ok());
+
+        // The other per-extension lookups accept the alias too
+        assert!(extension_manager
+            .ping_extension("test-session-id", "docs")
+            .await
+            .is_some());
+        assert!(extension_manager
+            .get_observed_errors("docs")
+            .await
+            .is_some());
+        assert_eq!(
+            extension_manager.cancel_extension_operations("docs").await,
+            Some(0)
+        );
+        assert!(extension_manager.get_server_client("docs").await.is_some());
+
+        extension_manager.remove_extension("docs").await.unwrap();
+        assert!(!extension_manager.is_extension_enabled("test_client").await);
+        assert_eq!(extension_manager.resolve_extension_name("docs"), "docs");
+    }
+
+    #[tokio::test]
+    async fn test_idle_extensions() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+
+        extension_manager
+            .add_mock_extension(
+                "test_client".to_string(),
+                Arc::new(Mutex::new(Box::new(MockClient {}))),
+            )
+            .await;
+
+        let idle = extension_manager
+            .idle_extensions("test-session-id", Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(idle.len(), 1);
+        assert_eq!(idle[0].name, "test_client");
+        assert_eq!(idle[0].last_used, None);
+        assert_eq!(idle[0].tool_count, 3);
+
+        let tool_call = CallToolRequestParams {
+            meta: None,
+            task: None,
+            name: "test_client__tool".to_string().into(),
+            arguments: Some(object!({})),
+        };
+        extension_manager
+            .dispatch_tool_call(
+                "test-session-id",
+                tool_call,
+                None,
+                CancellationToken::default(),
+            )
+            .await
+            .unwrap()
+            .result
+            .await
+            .unwrap();
+
+        assert!(extension_manager
+            .idle_extensions("test-session-id", Duration::from_secs(3600))
+            .await
+            .unwrap()
+            .is_empty());
+        let idle = extension_manager
+            .idle_extensions("test-session-id", Duration::ZERO)
+            .await
+            .unwrap();
+        assert!(idle[0].last_used.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_resource_catalog_deduplicates_by_uri() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+
+        for name in ["beta", "alpha"] {
+            let config = ExtensionConfig::Builtin {
+                name: name.to_string(),
+                display_name: None,
+                description: "built-in".to_string(),
+                timeout: None,
+                bundled: None,
+                available_tools: vec![],
+            };
+            let server_info = ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_resources().build(),
+                ..Default::default()
+            };
+            let client: McpClientBox =
+                Arc::new(Mutex::new(Box::new(ResourceClient { hang: false })));
+            extension_manager.extensions.lock().await.insert(
+                name.to_string(),
+                Extension::new(config, client, Some(server_info), None),
+            );
+        }
+
+        let catalog = extension_manager
+            .resource_catalog("test-session-id", CancellationToken::new())
+            .await;
+
+        assert_eq!(catalog.total, 1);
+        assert_eq!(catalog.per_extension.get("alpha"), Some(&1));
+        assert_eq!(catalog.per_extension.get("beta"), None);
+        assert_eq!(catalog.resources[0].extension, "alpha");
+        assert_eq!(
+            catalog.resources[0].also_provided_by,
+            vec!["beta".to_string()]
+        );
+        assert!(catalog.unavailable.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_read_resource_returns_promptly_when_cancelled() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+
+        let config = ExtensionConfig::Builtin {
+            name: "stuck".to_string(),
+            display_name: None,
+            description: "built-in".to_string(),
+            timeout: None,
+            bundled: None,
+            available_tools: vec![],
+        };
+        let server_info = ServerInfo {
+            capabilities: ServerCapabilities::builder().enable_resources().build(),
+            ..Default::default()
+        };
+        let client: McpClientBox = Arc::new(Mutex::new(Box::new(ResourceClient { hang: true })));
+        extension_manager.extensions.lock().await.insert(
+            "stuck".to_string(),
+            Extension::new(config, client, Some(server_info), None),
+        );
+
+        let token = CancellationToken::new();
+        let cancel = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            cancel.cancel();
+        });
+
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            extension_manager.read_resource_tool(
+                "test-session-id",
+                from_json(serde_json::json!({ "uri": "file:///notes.md" })),
+                token,
+            ),
+        )
+        .await
+        .expect("read did not stop when cancelled");
+        assert!(result.unwrap_err().message.contains("cancelled"));
+    }
+
+    #[tokio::test]
+    async fn test_get_extension_instructions() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+        for (name, instructions) in [("guided", Some("Call setup first")), ("plain", None)] {
+            let config = ExtensionConfig::Builtin {
+                name: name.to_string(),
+                display_name: None,
+                description: "built-in".to_string(),
+                timeout: None,
+                bundled: None,
+                available_tools: vec![],
+            };
+            let server_info = ServerInfo {
+                instructions: instructions.map(str::to_string),
+                ..Default::default()
+            };
+            let client: McpClientBox = Arc::new(Mutex::new(Box::new(MockClient {})));
+            extension_manager.extensions.lock().await.insert(
+                name.to_string(),
+                Extension::new(config, client, Some(server_info), None),
+            );
+        }
+
+        assert_eq!(
+            extension_manager.get_extension_instructions("guided").await,
+            Some(Some("Call setup first".to_string()))
+        );
+        assert_eq!(
+            extension_manager.get_extension_instructions("plain").await,
+            Some(None)
+        );
+        assert_eq!(
+            extension_manager
+                .get_extension_instructions("missing")
+                .await,
+            None
+        );
+    }
+
+    /// Reports progress twice while reading a resource
+    struct ProgressClient {
+        notifications: std::sync::Mutex<Option<mpsc::Sender<ServerNotification>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl McpClientTrait for ProgressClient {
+        fn get_info(&self) -> Option<&InitializeResult> {
+            None
+        }
+
+        async fn list_tools(
+            &self,
+            _session_id: &str,
+            _next_cursor: Option<String>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<ListToolsResult, Error> {
+            Ok(ListToolsResult::default())
+        }
+
+        async fn call_tool(
+            &self,
+            _session_id: &str,
+            _name: &str,
+            _arguments: Option<JsonObject>,
+            _working_dir: Option<&str>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<CallToolResult, Error> {
+            Err(Error::TransportClosed)
+        }
+
+        async fn read_resource(
+            &self,
+            _session_id: &str,
+            uri: &str,
+            _cancellation_token: CancellationToken,
+        ) -> Result<ReadResourceResult, Error> {
+            use rmcp::model::{
+                NumberOrString, ProgressNotification, ProgressNotificationMethod,
+                ProgressNotificationParam, ProgressToken,
+            };
+
+            let tx = self.notifications.lock().unwrap().clone().unwrap();
+            for progress in [1.0, 2.0] {
+                tx.send(ServerNotification::ProgressNotification(
+                    ProgressNotification {
+                        params: ProgressNotificationParam {
+                            progress_token: ProgressToken(NumberOrString::Number(1)),
+                            progress,
+                            total: Some(2.0),
+                            message: None,
+                        },
+                        method: ProgressNotificationMethod,
+                        extensions: Default::default(),
+                    },
+                ))
+                .await
+                .unwrap();
+                tokio::task::yield_now().await;
+            }
+            Ok(ReadResourceResult {
+                contents: vec![ResourceContents::text("notes", uri)],
+            })
+        }
+
+        async fn subscribe(&self) -> mpsc::Receiver<ServerNotification> {
+            let (tx, rx) = mpsc::channel(8);
+            *self.notifications.lock().unwrap() = Some(tx);
+            rx
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_resource_relays_progress() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+        let mut progress = extension_manager.get_context().notifications.subscribe();
+
+        let config = ExtensionConfig::Builtin {
+            name: "notes".to_string(),
+            display_name: None,
+            description: "built-in".to_string(),
+            timeout: None,
+            bundled: None,
+            available_tools: vec![],
+        };
+        let client: McpClientBox = Arc::new(Mutex::new(Box::new(ProgressClient {
+            notifications: std::sync::Mutex::new(None),
+        })));
+        extension_manager.extensions.lock().await.insert(
+            "notes".to_string(),
+            Extension::new(config, client, None, None),
+        );
+
+        extension_manager
+            .read_resource(
+                "test-session-id",
+                "file:///a.md",
+                "notes",
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+
+        let mut reported = Vec::new();
+        while let Ok(ServerNotification::ProgressNotification(notification)) = progress.try_recv() {
+            reported.push(notification.params.progress);
+        }
+        assert_eq!(reported, vec![1.0, 2.0]);
+    }
+
+    /// Accepts resource writes and records what was written
+    struct WritableClient {
+        writes: Arc<std::sync::Mutex<Vec<(String, String)>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl McpClientTrait for WritableClient {
+        fn get_info(&self) -> Option<&InitializeResult> {
+            None
+        }
+
+        async fn list_tools(
+            &self,
+            _session_id: &str,
+            _next_cursor: Option<String>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<ListToolsResult, Error> {
+            Ok(ListToolsResult::default())
+        }
+
+        async fn call_tool(
+            &self,
+            _session_id: &str,
+            _name: &str,
+            _arguments: Option<JsonObject>,
+            _working_dir: Option<&str>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<CallToolResult, Error> {
+            Err(Error::TransportClosed)
+        }
+
+        async fn write_resource(
+            &self,
+            _session_id: &str,
+            uri: &str,
+            text: &str,
+            _cancellation_token: CancellationToken,
+        ) -> Result<Value, Error> {
+            self.writes
+                .lock()
+                .unwrap()
+                .push((uri.to_string(), text.to_string()));
+            Ok(serde_json::json!({ "written": true }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_resource() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+        assert!(!extension_manager.supports_resource_writes().await);
+
+        let writes = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut capabilities = ServerCapabilities::builder().enable_resources().build();
+        capabilities.experimental = Some(BTreeMap::from([(
+            WRITE_RESOURCE_METHOD.to_string(),
+            JsonObject::new(),
+        )]));
+        let server_info = ServerInfo {
+            capabilities,
+            ..Default::default()
+        };
+        let config = ExtensionConfig::Builtin {
+            name: "notes".to_string(),
+            display_name: None,
+            description: "built-in".to_string(),
+            timeout: None,
+            bundled: None,
+            available_tools: vec![],
+        };
+        let client: McpClientBox = Arc::new(Mutex::new(Box::new(WritableClient {
+            writes: Arc::clone(&writes),
+        })));
+        extension_manager.extensions.lock().await.insert(
+            "notes".to_string(),
+            Extension::new(config, client, Some(server_info), None),
+        );
+        assert!(extension_manager.supports_resource_writes().await);
+
+        let contents = extension_manager
+            .write_resource(
+                "test-session-id",
+                "file:///a.md",
+                "new notes",
+                None,
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            contents[0].as_text().unwrap().text,
+            "Wrote file:///a.md in notes\n{\"written\":true}"
+        );
+        assert_eq!(
+            *writes.lock().unwrap(),
+            vec![("file:///a.md".to_string(), "new notes".to_string())]
+        );
+
+        let error = extension_manager
+            .write_resource(
+                "test-session-id",
+                "file:///a.md",
+                "new notes",
+                Some("missing"),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+    }
+
+    struct WatchClient {
+        notifications: std::sync::Mutex<Option<mpsc::Receiver<ServerNotification>>>,
+        calls: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl McpClientTrait for WatchClient {
+        fn get_info(&self) -> Option<&InitializeResult> {
+            None
+        }
+
+        async fn list_tools(
+            &self,
+            _session_id: &str,
+            _next_cursor: Option<String>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<ListToolsResult, Error> {
+            Err(Error::TransportClosed)
+        }
+
+        async fn call_tool(
+            &self,
+            _session_id: &str,
+            _name: &str,
+            _arguments: Option<JsonObject>,
+            _working_dir: Option<&str>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<CallToolResult, Error> {
+            Err(Error::TransportClosed)
+        }
+
+        async fn subscribe_resource(
+            &self,
+            _session_id: &str,
+            uri: &str,
+            _cancellation_token: CancellationToken,
+        ) -> Result<(), Error> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("subscribe {}", uri));
+            Ok(())
+        }
+
+        async fn unsubscribe_resource(
+            &self,
+            _session_id: &str,
+            uri: &str,
+            _cancellation_token: CancellationToken,
+        ) -> Result<(), Error> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("unsubscribe {}", uri));
+            Ok(())
+        }
+
+        async fn subscribe(&self) -> mpsc::Receiver<ServerNotification> {
+            self.notifications
+                .lock()
+                .unwrap()
+                .take()
+                .unwrap_or_else(|| mpsc::channel(1).1)
+        }
+    }
+
+    fn resource_updated(uri: &str) -> ServerNotification {
+        ServerNotification::ResourceUpdatedNotification(rmcp::model::ResourceUpdatedNotification {
+            params: rmcp::model::ResourceUpdatedNotificationParam {
+                uri: uri.to_string(),
+            },
+            method: Default::default(),
+            extensions: Default::default(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_watch_resource_forwards_updates_until_unwatched() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+
+        let (upstream, notifications) = mpsc::channel(16);
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let config = ExtensionConfig::Builtin {
+            name: "logs".to_string(),
+            display_name: None,
+            description: "built-in".to_string(),
+            timeout: None,
+            bundled: None,
+            available_tools: vec![],
+        };
+        let server_info = ServerInfo {
+            capabilities: ServerCapabilities::builder()
+                .enable_resources()
+                .enable_resources_subscribe()
+                .build(),
+            ..Default::default()
+        };
+        let client: McpClientBox = Arc::new(Mutex::new(Box::new(WatchClient {
+            notifications: std::sync::Mutex::new(Some(notifications)),
+            calls: Arc::clone(&calls),
+        })));
+        extension_manager.extensions.lock().await.insert(
+            "logs".to_string(),
+            Extension::new(config, client, Some(server_info), None),
+        );
+
+        let watch = || {
+            extension_manager.watch_resource(
+                "test-session-id",
+                "file:///app.log",
+                Some("logs"),
+                CancellationToken::new(),
+            )
+        };
+        // Without a subscriber the watch would end at once, so it isn't started
+        assert_eq!(watch().await.unwrap_err().code, ErrorCode::INTERNAL_ERROR);
+
+        let mut listener = extension_manager.subscribe_resource_updates();
+        watch().await.unwrap();
+        let again = watch().await.unwrap();
+        assert!(again[0]
+            .as_text()
+            .unwrap()
+            .text
+            .starts_with("Already watching"));
+
+        upstream
+            .send(resource_updated("file:///other"))
+            .await
+            .unwrap();
+        upstream
+            .send(resource_updated("file:///app.log"))
+            .await
+            .unwrap();
+        match listener.recv().await.unwrap() {
+            (extension, ServerNotification::ResourceUpdatedNotification(updated)) => {
+                assert_eq!(extension, "logs");
+                assert_eq!(updated.params.uri, "file:///app.log")
+            }
+            other => panic!("unexpected notification {:?}", other),
+        }
+
+        // Nobody listening any more: the next update ends the watch and unsubscribes upstream
+        drop(listener);
+        upstream
+            .send(resource_updated("file:///app.log"))
+            .await
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !calls
+                .lock()
+                .unwrap()
+                .contains(&"unsubscribe file:///app.log".to_string())
+            {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("watch did not unsubscribe");
+        assert_eq!(calls.lock().unwrap()[0], "subscribe file:///app.log");
+    }
+
+    #[tokio::test]
+    async fn test_complete_resource_uri() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+
+        for name in ["alpha", "beta"] {
+            let config = ExtensionConfig::Builtin {
+                name: name.to_string(),
+                display_name: None,
+                description: "built-in".to_string(),
+                timeout: None,
+                bundled: None,
+                available_tools: vec![],
+            };
+            let server_info = ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_resources().build(),
+                ..Default::default()
+            };
+            let client: McpClientBox =
+                Arc::new(Mutex::new(Box::new(ResourceClient { hang: false })));
+            extension_manager.extensions.lock().await.insert(
+                name.to_string(),
+                Extension::new(config, client, Some(server_info), None),
+            );
+        }
+
+        let complete = |prefix: &'static str, extension_name: Option<&'static str>| {
+            let extension_manager = &extension_manager;
+            async move {
+                extension_manager
+                    .complete_resource_uri(
+                        "test-session-id",
+                        prefix,
+                        extension_name,
+                        CancellationToken::new(),
+                    )
+                    .await
+            }
+        };
+
+        assert_eq!(complete("file:///n", None).await, vec!["file:///notes.md"]);
+        assert_eq!(
+            complete("file:///", Some("beta")).await,
+            vec!["file:///notes.md"]
+        );
+        assert!(complete("https://", None).await.is_empty());
+        assert!(complete("file:///", Some("missing")).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_read_multiple_resources_reports_failures_inline() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+
+        let config = ExtensionConfig::Builtin {
+            name: "notes".to_string(),
+            display_name: None,
+            description: "built-in".to_string(),
+            timeout: None,
+            bundled: None,
+            available_tools: vec![],
+        };
+        let server_info = ServerInfo {
+            capabilities: ServerCapabilities::builder().enable_resources().build(),
+            ..Default::default()
+        };
+        let client: McpClientBox = Arc::new(Mutex::new(Box::new(ResourceClient { hang: false })));
+        extension_manager.extensions.lock().await.insert(
+            "notes".to_string(),
+            Extension::new(config, client, Some(server_info), None),
+        );
+
+        let contents = extension_manager
+            .read_multiple_resources_tool(
+                "test-session-id",
+                from_json(serde_json::json!({
+                    "uris": ["file:///a.md", "file:///b.md"],
+                    "extension_name": "notes",
+                    "max_total_bytes": 7,
+                })),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        let texts: Vec<&str> = contents
+            .iter()
+            .filter_map(|content| content.as_text().map(|text| text.text.as_str()))
+            .collect();
+        assert_eq!(texts[0], "file:///a.md\n\nnotes");
+        assert!(texts[1].starts_with("file:///b.md\nskipped: 5 bytes"));
+
+        let contents = extension_manager
+            .read_multiple_resources_tool(
+                "test-session-id",
+                from_json(
+                    serde_json::json!({ "uris": ["file:///a.md"], "extension_name": "missing" }),
+                ),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        let text = &contents[0].as_text().unwrap().text;
+        assert!(text.starts_with("file:///a.md\nerror: Extension 'missing' not found"));
+
+        let error = extension_manager
+            .read_multiple_resources_tool(
+                "test-session-id",
+                from_json(serde_json::json!({ "uris": [] })),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_window_resource_text() {
+        let mut text = "0123456789".to_string();
+        assert!(window_resource_text(&mut text, 0, None, Some(20)).is_none());
+        assert_eq!(text, "0123456789");
+
+        let window = window_resource_text(&mut text, 2, None, Some(4)).unwrap();
+        assert_eq!(
+            text,
+            "2345\n\n[truncated: bytes 2-6 of 10; pass offset=6 to read more]"
+        );
+        assert_eq!(window["truncated"], true);
+        assert_eq!(window["totalBytes"], 10);
+
+        let mut text = "0123456789".to_string();
+        let window = window_resource_text(&mut text, 6, Some(10), None).unwrap();
+        assert_eq!(text, "6789");
+        assert_eq!(window["truncated"], false);
+        assert_eq!(window["length"], 4);
+
+        // Never splits a multi-byte character
+        let mut text = "héllo".to_string();
+        window_resource_text(&mut text, 0, Some(2), None);
+        assert_eq!(text, "h");
+    }
+
+    #[tokio::test]
+    async fn test_read_resource_if_none_match() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+
+        let config = ExtensionConfig::Builtin {
+            name: "notes".to_string(),
+            display_name: None,
+            description: "built-in".to_string(),
+            timeout: None,
+            bundled: None,
+            available_tools: vec![],
+        };
+        let server_info = ServerInfo {
+            capabilities: ServerCapabilities::builder().enable_resources().build(),
+            ..Default::default()
+        };
+        let client: McpClientBox = Arc::new(Mutex::new(Box::new(ResourceClient { hang: false })));
+        extension_manager.extensions.lock().await.insert(
+            "notes".to_string(),
+            Extension::new(config, client, Some(server_info), None),
+        );
+
+        let contents = extension_manager
+            .read_resource_tool(
+                "test-session-id",
+                from_json(serde_json::json!({ "uri": "file:///a.md" })),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        let text = contents[0].as_text().unwrap();
+        assert_eq!(text.text, "file:///a.md\n\nnotes");
+        let etag = text.meta.as_ref().unwrap().0["etag"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        let contents = extension_manager
+            .read_resource_tool(
+                "test-session-id",
+                from_json(serde_json::json!({ "uri": "file:///a.md", "if_none_match": etag })),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            contents[0].as_text().unwrap().text,
+            format!("file:///a.md\nunchanged (etag: {})", etag)
+        );
+
+        let contents = extension_manager
+            .read_resource_tool(
+                "test-session-id",
+                from_json(serde_json::json!({ "uri": "file:///a.md", "if_none_match": "stale" })),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(contents[0].as_text().unwrap().text, "file:///a.md\n\nnotes");
+    }
+
+    #[test]
+    fn test_resource_validators_prefer_extension_meta() {
+        let contents = vec![ResourceContents::TextResourceContents {
+            uri: "file:///a.md".to_string(),
+            mime_type: None,
+            text: "notes".to_string(),
+            meta: Some(Meta(JsonObject::from_iter([
+                ("etag".to_string(), Value::String("v2".to_string())),
+                (
+                    "lastModified".to_string(),
+                    Value::String("2025-01-01T00:00:00Z".to_string()),
+                ),
+                ("other".to_string(), Value::Bool(true)),
+            ]))),
+        }];
+
+        let validators = resource_validators(&contents);
+        assert_eq!(validators.len(), 2);
+        assert_eq!(validators["etag"], "v2");
+        assert_eq!(validators["lastModified"], "2025-01-01T00:00:00Z");
+    }
+
+    #[tokio::test]
+    async fn test_read_resource_rejects_ambiguous_uri() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+
+        for name in ["alpha", "beta"] {
+            let config = ExtensionConfig::Builtin {
+                name: name.to_string(),
+                display_name: None,
+                description: "built-in".to_string(),
+                timeout: None,
+                bundled: None,
+                available_tools: vec![],
+            };
+            let server_info = ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_resources().build(),
+                ..Default::default()
+            };
+            let client: McpClientBox =
+                Arc::new(Mutex::new(Box::new(ResourceClient { hang: false })));
+            extension_manager.extensions.lock().await.insert(
+                name.to_string(),
+                Extension::new(config, client, Some(server_info), None),
+            );
+        }
+
+        let read = |params: Value| {
+            let extension_manager = &extension_manager;
+            async move {
+                extension_manager
+                    .read_resource_tool(
+                        "test-session-id",
+                        from_json(params),
+                        CancellationToken::new(),
+                    )
+                    .await
+            }
+        };
+
+        let error = read(serde_json::json!({ "uri": "file:///notes.md" }))
+            .await
+            .unwrap_err();
+        assert!(error.message.contains("several extensions: alpha, beta"));
+
+        let contents = read(serde_json::json!({
+            "uri": "file:///notes.md",
+            "extension_name": "beta",
+            "mime_type": "text",
+        }))
+        .await
+        .unwrap();
+        let text = contents[0].as_text().unwrap();
+        assert_eq!(text.text, "file:///notes.md\n\nnotes");
+        assert_eq!(text.meta.as_ref().unwrap().0["mimeType"], "text");
+
+        assert!(read(serde_json::json!({
+            "uri": "file:///notes.md",
+            "mime_type": "text/markdown",
+        }))
+        .await
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_list_resources_paginates_with_cursor() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+
+        for name in ["a", "b", "c"] {
+            let config = ExtensionConfig::Builtin {
+                name: name.to_string(),
+                display_name: None,
+                description: "built-in".to_string(),
+                timeout: None,
+                bundled: None,
+                available_tools: vec![],
+            };
+            let server_info = ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_resources().build(),
+                ..Default::default()
+            };
+            let client: McpClientBox =
+                Arc::new(Mutex::new(Box::new(ResourceClient { hang: false })));
+            extension_manager.extensions.lock().await.insert(
+                name.to_string(),
+                Extension::new(config, client, Some(server_info), None),
+            );
+        }
+
+        let page = |params: Value| {
+            let extension_manager = &extension_manager;
+            async move {
+                extension_manager
+                    .list_resources(
+                        "test-session-id",
+                        from_json(params),
+                        CancellationToken::new(),
+                    )
+                    .await
+            }
+        };
+        let next_cursor = |contents: &[Content]| -> Value {
+            let text = &contents.last().unwrap().as_text().unwrap().text;
+            serde_json::from_str::<Value>(text).unwrap()["next_cursor"].clone()
+        };
+
+        let first = page(serde_json::json!({ "limit": 2 })).await.unwrap();
+        let listing = &first[0].as_text().unwrap().text;
+        assert!(listing.starts_with("a - notes") && listing.contains("b - notes"));
+        assert!(!listing.contains("c - notes"));
+        assert_eq!(next_cursor(&first), "2");
+
+        let second = page(serde_json::json!({ "limit": 2, "cursor": "2" }))
+            .await
+            .unwrap();
+        assert!(second[0].as_text().unwrap().text.starts_with("c - notes"));
+        assert_eq!(next_cursor(&second), Value::Null);
+
+        assert!(page(serde_json::json!({ "cursor": "" })).await.is_err());
+        assert!(page(serde_json::json!({ "cursor": "5" })).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_list_resources_tags_each_resource_with_its_extension() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+
+        for name in ["first", "second"] {
+            let config = ExtensionConfig::Builtin {
+                name: name.to_string(),
+                display_name: None,
+                description: "built-in".to_string(),
+                timeout: None,
+                bundled: None,
+                available_tools: vec![],
+            };
+            let server_info = ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_resources().build(),
+                ..Default::default()
+            };
+            let client: McpClientBox =
+                Arc::new(Mutex::new(Box::new(ResourceClient { hang: false })));
+            extension_manager.extensions.lock().await.insert(
+                name.to_string(),
+                Extension::new(config, client, Some(server_info), None),
+            );
+        }
+
+        let contents = extension_manager
+            .list_resources(
+                "test-session-id",
+                from_json(serde_json::json!({ "limit": 10 })),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        let meta = contents[0].as_text().unwrap().meta.as_ref().unwrap();
+        assert_eq!(
+            meta.0["resources"],
+            serde_json::json!([
+                {
+                    "uri": "file:///notes.md",
+                    "name": "notes",
+                    "extensionName": "first",
+                    "index": 0,
+                },
+                {
+                    "uri": "file:///notes.md",
+                    "name": "notes",
+                    "extensionName": "second",
+                    "index": 0,
+                },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_count_resources_respects_filters() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+
+        for name in ["docs", "drafts", "other"] {
+            let config = ExtensionConfig::Builtin {
+                name: name.to_string(),
+                display_name: None,
+                description: "built-in".to_string(),
+                timeout: None,
+                bundled: None,
+                available_tools: vec![],
+            };
+            let server_info = ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_resources().build(),
+                ..Default::default()
+            };
+            let client: McpClientBox =
+                Arc::new(Mutex::new(Box::new(ResourceClient { hang: false })));
+            extension_manager.extensions.lock().await.insert(
+                name.to_string(),
+                Extension::new(config, client, Some(server_info), None),
+            );
+        }
+
+        let count = |params: Value| {
+            let extension_manager = &extension_manager;
+            async move {
+                extension_manager
+                    .count_resources(
+                        "test-session-id",
+                        from_json(params),
+                        CancellationToken::new(),
+                    )
+                    .await
+                    .unwrap()
+                    .0
+            }
+        };
+
+        let counts = count(serde_json::json!({ "name_pattern": "d*" })).await;
+        assert_eq!(
+            counts,
+            BTreeMap::from([("docs".to_string(), 1), ("drafts".to_string(), 1)])
+        );
+
+        let counts = count(serde_json::json!({ "extension_name": "other" })).await;
+        assert_eq!(counts, BTreeMap::from([("other".to_string(), 1)]));
+
+        let counts = count(serde_json::json!({ "uri_prefix": "file:///missing" })).await;
+        assert_eq!(counts.values().sum::<usize>(), 0);
+        assert_eq!(counts.len(), 3);
+    }
+
+    /// Serves one text resource and counts how often it is read
+    struct CountingResourceClient {
+        reads: Arc<AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl McpClientTrait for CountingResourceClient {
+        fn get_info(&self) -> Option<&InitializeResult> {
+            None
+        }
+
+        async fn list_resources(
+            &self,
+            _session_id: &str,
+            _next_cursor: Option<String>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<ListResourcesResult, Error> {
+            use rmcp::model::{AnnotateAble, RawResource};
+
+            Ok(ListResourcesResult {
+                resources: vec![RawResource::new("file:///notes.md", "notes").no_annotation()],
+                next_cursor: None,
+                meta: None,
+            })
+        }
+
+        async fn read_resource(
+            &self,
+            _session_id: &str,
+            uri: &str,
+            _cancellation_token: CancellationToken,
+        ) -> Result<ReadResourceResult, Error> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            Ok(ReadResourceResult {
+                contents: vec![ResourceContents::text("notes", uri)],
+            })
+        }
+
+        async fn list_tools(
+            &self,
+            _session_id: &str,
+            _next_cursor: Option<String>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<ListToolsResult, Error> {
+            Ok(ListToolsResult::default())
+        }
+
+        async fn call_tool(
+            &self,
+            _session_id: &str,
+            _name: &str,
+            _arguments: Option<JsonObject>,
+            _working_dir: Option<&str>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<CallToolResult, Error> {
+            Err(Error::TransportClosed)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prefetched_resources_are_served_from_cache() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager = Arc::new(ExtensionManager::new_without_provider(
+            temp_dir.path().to_path_buf(),
+        ));
+        let reads = Arc::new(AtomicUsize::new(0));
+        let config = ExtensionConfig::Builtin {
+            name: "notes".to_string(),
+            display_name: None,
+            description: "built-in".to_string(),
+            timeout: None,
+            bundled: None,
+            available_tools: vec![],
+        };
+        let server_info = ServerInfo {
+            capabilities: ServerCapabilities::builder().enable_resources().build(),
+            ..Default::default()
+        };
+        let client: McpClientBox = Arc::new(Mutex::new(Box::new(CountingResourceClient {
+            reads: Arc::clone(&reads),
+        })));
+        extension_manager.extensions.lock().await.insert(
+            "notes".to_string(),
+            Extension::new(config, client, Some(server_info), None),
+        );
 
-        async fn add_mock_extension_with_tools(
-            &self,
-            name: String,
-            client: McpClientBox,
-            available_tools: Vec<String>,
-        ) {
-            let sanitized_name = name_to_key(&name);
-            let config = ExtensionConfig::Builtin {
-                name: name.clone(),
-                display_name: Some(name.clone()),
-                description: "built-in".to_string(),
-                timeout: None,
-                bundled: None,
-                available_tools,
-            };
-            let extension = Extension::new(config, client, None, None);
-            self.extensions
-                .lock()
+        extension_manager
+            .prefetch_resources(
+                "test-session-id",
+                vec!["file:///notes.md".to_string()],
+                None,
+                Duration::from_secs(60),
+            )
+            .await
+            .unwrap();
+        assert_eq!(reads.load(Ordering::SeqCst), 1);
+
+        for _ in 0..2 {
+            extension_manager
+                .read_resource_tool(
+                    "test-session-id",
+                    from_json(serde_json::json!({ "uri": "file:///notes.md" })),
+                    CancellationToken::new(),
+                )
                 .await
-                .insert(sanitized_name, extension);
-            self.invalidate_tools_cache_and_bump_version().await;
+                .unwrap();
         }
+        assert_eq!(reads.load(Ordering::SeqCst), 1);
+
+        // An expired entry is read again
+        extension_manager.context.resource_cache.insert(
+            "file:///notes.md",
+            "notes",
+            ReadResourceResult { contents: vec![] },
+            Duration::ZERO,
+        );
+        extension_manager
+            .read_resource_tool(
+                "test-session-id",
+                from_json(
+                    serde_json::json!({ "uri": "file:///notes.md", "extension_name": "notes" }),
+                ),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(reads.load(Ordering::SeqCst), 2);
     }
 
-    struct MockClient {}
+    /// Defines the given prompts, describing each result with the extension's label and the
+    /// arguments it was given
+    struct PromptClient {
+        label: &'static str,
+        prompts: Vec<&'static str>,
+    }
 
     #[async_trait::async_trait]
-    impl McpClientTrait for MockClient {
+    impl McpClientTrait for PromptClient {
         fn get_info(&self) -> Option<&InitializeResult> {
             None
         }
 
-        async fn list_resources(
+        async fn list_prompts(
             &self,
             _session_id: &str,
             _next_cursor: Option<String>,
             _cancellation_token: CancellationToken,
-        ) -> Result<ListResourcesResult, Error> {
-            Err(Error::TransportClosed)
+        ) -> Result<ListPromptsResult, Error> {
+            Ok(ListPromptsResult::with_all_items(
+                self.prompts
+                    .iter()
+                    .map(|name| Prompt::new(*name, None::<String>, None))
+                    .collect(),
+            ))
         }
 
-        async fn read_resource(
+        async fn get_prompt(
             &self,
             _session_id: &str,
-            _uri: &str,
+            name: &str,
+            arguments: Value,
             _cancellation_token: CancellationToken,
-        ) -> Result<ReadResourceResult, Error> {
-            Err(Error::TransportClosed)
+        ) -> Result<GetPromptResult, Error> {
+            Ok(GetPromptResult {
+                description: Some(format!("{} {} {}", self.label, name, arguments)),
+                messages: vec![],
+            })
         }
 
         async fn list_tools(
@@ -1568,302 +7001,540 @@ mod tests {
             _next_cursor: Option<String>,
             _cancellation_token: CancellationToken,
         ) -> Result<ListToolsResult, Error> {
-            use serde_json::json;
-            use std::sync::Arc;
-            Ok(ListToolsResult {
-                tools: vec![
-                    Tool::new(
-                        "tool".to_string(),
-                        "A basic tool".to_string(),
-                        Arc::new(json!({}).as_object().unwrap().clone()),
-                    ),
-                    Tool::new(
-                        "available_tool".to_string(),
-                        "An available tool".to_string(),
-                        Arc::new(json!({}).as_object().unwrap().clone()),
-                    ),
-                    Tool::new(
-                        "hidden_tool".to_string(),
-                        "hidden tool".to_string(),
-                        Arc::new(json!({}).as_object().unwrap().clone()),
-                    ),
-                ],
-                next_cursor: None,
-                meta: None,
-            })
+            Ok(ListToolsResult::default())
         }
 
         async fn call_tool(
             &self,
             _session_id: &str,
-            name: &str,
+            _name: &str,
             _arguments: Option<JsonObject>,
             _working_dir: Option<&str>,
             _cancellation_token: CancellationToken,
         ) -> Result<CallToolResult, Error> {
-            match name {
-                "tool" | "test__tool" | "available_tool" | "hidden_tool" => Ok(CallToolResult {
-                    content: vec![],
-                    is_error: None,
-                    structured_content: None,
-                    meta: None,
-                }),
-                _ => Err(Error::TransportClosed),
+            Err(Error::TransportClosed)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_route_prompt_to_defining_extension() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+        for (label, prompts) in [
+            ("alpha", vec!["review", "summarize"]),
+            ("beta", vec!["review"]),
+        ] {
+            let client: McpClientBox =
+                Arc::new(Mutex::new(Box::new(PromptClient { label, prompts })));
+            extension_manager
+                .add_mock_extension(label.to_string(), client)
+                .await;
+        }
+
+        let route = |name: &'static str| {
+            let extension_manager = &extension_manager;
+            async move {
+                extension_manager
+                    .route_prompt(
+                        "test-session-id",
+                        name,
+                        serde_json::json!({ "topic": "tests" }),
+                        CancellationToken::new(),
+                    )
+                    .await
             }
+        };
+
+        let result = route("summarize").await.unwrap();
+        assert_eq!(
+            result.description.as_deref(),
+            Some(r#"alpha summarize {"topic":"tests"}"#)
+        );
+
+        let error = route("review").await.unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+        assert!(error.message.contains("alpha, beta"));
+        let result = route("beta__review").await.unwrap();
+        assert_eq!(
+            result.description.as_deref(),
+            Some(r#"beta review {"topic":"tests"}"#)
+        );
+
+        let error = route("missing").await.unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+    }
+
+    /// Lists one resource and one resource template
+    struct TemplateClient;
+
+    #[async_trait::async_trait]
+    impl McpClientTrait for TemplateClient {
+        fn get_info(&self) -> Option<&InitializeResult> {
+            None
         }
 
-        async fn list_prompts(
+        async fn list_resources(
             &self,
             _session_id: &str,
             _next_cursor: Option<String>,
             _cancellation_token: CancellationToken,
-        ) -> Result<ListPromptsResult, Error> {
-            Err(Error::TransportClosed)
+        ) -> Result<ListResourcesResult, Error> {
+            use rmcp::model::{AnnotateAble, RawResource};
+
+            Ok(ListResourcesResult::with_all_items(vec![RawResource::new(
+                "file:///readme.md",
+                "readme",
+            )
+            .no_annotation()]))
         }
 
-        async fn get_prompt(
+        async fn list_resource_templates(
+            &self,
+            _session_id: &str,
+            _next_cursor: Option<String>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<rmcp::model::ListResourceTemplatesResult, Error> {
+            use rmcp::model::{AnnotateAble, RawResourceTemplate};
+
+            Ok(rmcp::model::ListResourceTemplatesResult::with_all_items(
+                vec![RawResourceTemplate {
+                    uri_template: "file:///{+path}{?rev}".to_string(),
+                    name: "file".to_string(),
+                    title: None,
+                    description: None,
+                    mime_type: None,
+                    icons: None,
+                }
+                .no_annotation()],
+            ))
+        }
+
+        async fn list_tools(
+            &self,
+            _session_id: &str,
+            _next_cursor: Option<String>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<ListToolsResult, Error> {
+            Ok(ListToolsResult::default())
+        }
+
+        async fn call_tool(
             &self,
             _session_id: &str,
             _name: &str,
-            _arguments: Value,
+            _arguments: Option<JsonObject>,
+            _working_dir: Option<&str>,
             _cancellation_token: CancellationToken,
-        ) -> Result<GetPromptResult, Error> {
+        ) -> Result<CallToolResult, Error> {
             Err(Error::TransportClosed)
         }
+    }
 
-        async fn subscribe(&self) -> mpsc::Receiver<ServerNotification> {
-            mpsc::channel(1).1
+    #[tokio::test]
+    async fn test_list_resources_shows_templates_with_variables() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+        let config = ExtensionConfig::Builtin {
+            name: "docs".to_string(),
+            display_name: None,
+            description: "built-in".to_string(),
+            timeout: None,
+            bundled: None,
+            available_tools: vec![],
+        };
+        let server_info = ServerInfo {
+            capabilities: ServerCapabilities::builder().enable_resources().build(),
+            ..Default::default()
+        };
+        let client: McpClientBox = Arc::new(Mutex::new(Box::new(TemplateClient)));
+        extension_manager.extensions.lock().await.insert(
+            "docs".to_string(),
+            Extension::new(config, client, Some(server_info), None),
+        );
+
+        let list = |params: Value| {
+            let extension_manager = &extension_manager;
+            async move {
+                extension_manager
+                    .list_resources(
+                        "test-session-id",
+                        from_json(params),
+                        CancellationToken::new(),
+                    )
+                    .await
+                    .unwrap()
+                    .iter()
+                    .map(|content| content.as_text().unwrap().text.clone())
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        let contents = list(serde_json::json!({})).await;
+        assert_eq!(contents.len(), 2);
+        assert_eq!(
+            contents[0],
+            "docs - readme, uri: (file:///readme.md), index: 0"
+        );
+        assert!(contents[1].ends_with(
+            "\ndocs - file, template: (file:///{+path}{?rev}), variables: path, rev (optional)"
+        ));
+
+        // A prefix the template can't produce leaves it out
+        let contents = list(serde_json::json!({ "uri_prefix": "https://" })).await;
+        assert!(contents.iter().all(|text| !text.contains("template:")));
+        let contents = list(serde_json::json!({ "uri_prefix": "file:///src/" })).await;
+        assert!(contents.iter().any(|text| text.contains("template:")));
+    }
+
+    #[test]
+    fn test_chunk_text() {
+        assert_eq!(chunk_text("notes", 2), vec!["no", "te", "s"]);
+        assert_eq!(chunk_text("héllo", 2), vec!["h", "é", "ll", "o"]);
+        assert_eq!(chunk_text("é", 1), vec!["é"]);
+        assert!(chunk_text("", 4).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stream_resource_sends_chunks() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager = Arc::new(ExtensionManager::new_without_provider(
+            temp_dir.path().to_path_buf(),
+        ));
+        for (name, hang) in [("notes", false), ("slow", true)] {
+            let config = ExtensionConfig::Builtin {
+                name: name.to_string(),
+                display_name: None,
+                description: "built-in".to_string(),
+                timeout: None,
+                bundled: None,
+                available_tools: vec![],
+            };
+            let server_info = ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_resources().build(),
+                ..Default::default()
+            };
+            let client: McpClientBox = Arc::new(Mutex::new(Box::new(ResourceClient { hang })));
+            extension_manager.extensions.lock().await.insert(
+                name.to_string(),
+                Extension::new(config, client, Some(server_info), None),
+            );
+        }
+        let stream = |extension_name: &str| {
+            extension_manager.stream_resource(
+                "test-session-id",
+                "file:///notes.md",
+                Some(extension_name),
+                2,
+            )
+        };
+
+        // Nothing takes the chunks yet, so there is no stream
+        assert_eq!(stream("notes").unwrap_err().code, ErrorCode::INTERNAL_ERROR);
+        let mut chunks = extension_manager.take_resource_chunks().unwrap();
+
+        let stream_id = stream("notes").unwrap();
+        let mut data = String::new();
+        for seq in 0..3 {
+            let (extension, notification) = chunks.recv().await.unwrap();
+            assert_eq!(extension.as_deref(), Some("notes"));
+            let ServerNotification::CustomNotification(notification) = notification else {
+                panic!("unexpected notification {:?}", notification);
+            };
+            assert_eq!(notification.method, RESOURCE_CHUNK_NOTIFICATION_METHOD);
+            let params = notification.params.unwrap();
+            assert_eq!(params["streamId"], stream_id.as_str());
+            assert_eq!(params["seq"], seq);
+            assert_eq!(params["total"], 3);
+            assert_eq!(params["last"], seq == 2);
+            data.push_str(params["data"].as_str().unwrap());
         }
+        assert_eq!(data, "notes");
+
+        // Disabling the extension stops its stream and forgets it
+        let slow_id = stream("slow").unwrap();
+        assert!(extension_manager
+            .resource_streams
+            .lock()
+            .unwrap()
+            .contains_key(&slow_id));
+        extension_manager.remove_extension("slow").await.unwrap();
+        assert!(!extension_manager
+            .resource_streams
+            .lock()
+            .unwrap()
+            .contains_key(&slow_id));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(chunks.try_recv().is_err());
     }
 
     #[tokio::test]
-    async fn test_get_client_for_tool() {
+    async fn test_read_resource_by_index_from_latest_listing() {
         let temp_dir = tempfile::tempdir().unwrap();
         let extension_manager =
             ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
 
-        // Add some mock clients using the helper method
-        extension_manager
-            .add_mock_extension(
-                "test_client".to_string(),
-                Arc::new(Mutex::new(Box::new(MockClient {}))),
-            )
-            .await;
+        let config = ExtensionConfig::Builtin {
+            name: "notes".to_string(),
+            display_name: None,
+            description: "built-in".to_string(),
+            timeout: None,
+            bundled: None,
+            available_tools: vec![],
+        };
+        let server_info = ServerInfo {
+            capabilities: ServerCapabilities::builder().enable_resources().build(),
+            ..Default::default()
+        };
+        let client: McpClientBox = Arc::new(Mutex::new(Box::new(ResourceClient { hang: false })));
+        extension_manager.extensions.lock().await.insert(
+            "notes".to_string(),
+            Extension::new(config, client, Some(server_info), None),
+        );
 
-        extension_manager
-            .add_mock_extension(
-                "__client".to_string(),
-                Arc::new(Mutex::new(Box::new(MockClient {}))),
-            )
-            .await;
+        let read = |params: Value| {
+            let extension_manager = &extension_manager;
+            async move {
+                extension_manager
+                    .read_resource_tool(
+                        "test-session-id",
+                        from_json(params),
+                        CancellationToken::new(),
+                    )
+                    .await
+            }
+        };
 
-        extension_manager
-            .add_mock_extension(
-                "__cli__ent__".to_string(),
-                Arc::new(Mutex::new(Box::new(MockClient {}))),
-            )
-            .await;
+        let error = read(serde_json::json!({ "extension_name": "notes", "index": 0 }))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_REQUEST);
 
-        extension_manager
-            .add_mock_extension(
-                "client 🚀".to_string(),
-                Arc::new(Mutex::new(Box::new(MockClient {}))),
+        let listing = extension_manager
+            .list_resources(
+                "test-session-id",
+                from_json(serde_json::json!({})),
+                CancellationToken::new(),
             )
-            .await;
+            .await
+            .unwrap();
+        assert!(listing[0].as_text().unwrap().text.ends_with("index: 0"));
 
-        // Test basic case
-        assert!(extension_manager
-            .get_client_for_tool("test_client__tool")
+        let contents = read(serde_json::json!({ "extension_name": "notes", "index": 0 }))
             .await
-            .is_some());
+            .unwrap();
+        assert_eq!(
+            contents[0].as_text().unwrap().text,
+            "file:///notes.md\n\nnotes"
+        );
 
-        // Test leading underscores
-        assert!(extension_manager
-            .get_client_for_tool("__client__tool")
+        let error = read(serde_json::json!({ "extension_name": "notes", "index": 1 }))
             .await
-            .is_some());
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+
+        extension_manager.remove_extension("notes").await.unwrap();
+        let error = read(serde_json::json!({ "extension_name": "notes", "index": 0 }))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_list_resources_skips_extensions_that_time_out() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+
+        for (name, hang) in [("fast", false), ("stuck", true)] {
+            let config = ExtensionConfig::Builtin {
+                name: name.to_string(),
+                display_name: None,
+                description: "built-in".to_string(),
+                timeout: None,
+                bundled: None,
+                available_tools: vec![],
+            };
+            let server_info = ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_resources().build(),
+                ..Default::default()
+            };
+            let client: McpClientBox = Arc::new(Mutex::new(Box::new(ResourceClient { hang })));
+            extension_manager.extensions.lock().await.insert(
+                name.to_string(),
+                Extension::new(config, client, Some(server_info), None),
+            );
+        }
 
-        // Test multiple underscores in client name, and ending with __
-        assert!(extension_manager
-            .get_client_for_tool("__cli__ent____tool")
+        let contents = extension_manager
+            .list_resources(
+                "test-session-id",
+                from_json(serde_json::json!({ "per_extension_timeout_ms": 50 })),
+                CancellationToken::new(),
+            )
             .await
-            .is_some());
+            .unwrap();
+        let texts: Vec<String> = contents
+            .iter()
+            .map(|content| content.as_text().unwrap().text.clone())
+            .collect();
 
-        // Test unicode in tool name, "client 🚀" should become "client_"
-        assert!(extension_manager
-            .get_client_for_tool("client___tool")
-            .await
-            .is_some());
+        assert!(texts[0].contains("fast - notes"));
+        assert_eq!(
+            texts.last().unwrap(),
+            "Skipped 1 extension(s) that did not respond within 50 ms: stuck"
+        );
     }
 
     #[tokio::test]
-    async fn test_dispatch_tool_call() {
-        // test that dispatch_tool_call parses out the sanitized name correctly, and extracts
-        // tool_names
+    async fn test_list_resources_returns_partial_results_when_cancelled() {
         let temp_dir = tempfile::tempdir().unwrap();
         let extension_manager =
             ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
 
-        // Add some mock clients using the helper method
-        extension_manager
-            .add_mock_extension(
-                "test_client".to_string(),
-                Arc::new(Mutex::new(Box::new(MockClient {}))),
-            )
-            .await;
-
-        extension_manager
-            .add_mock_extension(
-                "__cli__ent__".to_string(),
-                Arc::new(Mutex::new(Box::new(MockClient {}))),
-            )
-            .await;
+        for (name, hang) in [("fast", false), ("stuck", true)] {
+            let config = ExtensionConfig::Builtin {
+                name: name.to_string(),
+                display_name: None,
+                description: "built-in".to_string(),
+                timeout: None,
+                bundled: None,
+                available_tools: vec![],
+            };
+            let server_info = ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_resources().build(),
+                ..Default::default()
+            };
+            let client: McpClientBox = Arc::new(Mutex::new(Box::new(ResourceClient { hang })));
+            extension_manager.extensions.lock().await.insert(
+                name.to_string(),
+                Extension::new(config, client, Some(server_info), None),
+            );
+        }
 
-        extension_manager
-            .add_mock_extension(
-                "client 🚀".to_string(),
-                Arc::new(Mutex::new(Box::new(MockClient {}))),
-            )
-            .await;
+        let token = CancellationToken::new();
+        let cancel = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            cancel.cancel();
+        });
 
-        // verify a normal tool call
-        let tool_call = CallToolRequestParams {
-            meta: None,
-            task: None,
-            name: "test_client__tool".to_string().into(),
-            arguments: Some(object!({})),
-        };
+        let contents = extension_manager
+            .list_resources("test-session-id", from_json(serde_json::json!({})), token)
+            .await
+            .unwrap();
+        let texts: Vec<String> = contents
+            .iter()
+            .map(|content| content.as_text().unwrap().text.clone())
+            .collect();
 
-        let result = extension_manager
-            .dispatch_tool_call(
-                "test-session-id",
-                tool_call,
-                None,
-                CancellationToken::default(),
-            )
-            .await;
-        assert!(result.is_ok());
+        assert!(texts.iter().any(|text| text.contains("file:///notes.md")));
+        assert!(texts
+            .last()
+            .unwrap()
+            .starts_with("Incomplete: listing was cancelled after 1 of 2"));
+    }
 
-        let tool_call = CallToolRequestParams {
-            meta: None,
-            task: None,
-            name: "test_client__test__tool".to_string().into(),
-            arguments: Some(object!({})),
+    #[tokio::test]
+    async fn test_disabling_extension_notifies_tool_list_changed() {
+        use crate::agents::extension_manager_extension::{
+            ExtensionManagerClient, MANAGE_EXTENSIONS_TOOL_NAME,
         };
 
-        let result = extension_manager
-            .dispatch_tool_call(
-                "test-session-id",
-                tool_call,
-                None,
-                CancellationToken::default(),
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager = Arc::new(ExtensionManager::new_without_provider(
+            temp_dir.path().to_path_buf(),
+        ));
+        extension_manager
+            .add_mock_extension(
+                "test_client".to_string(),
+                Arc::new(Mutex::new(Box::new(MockClient {}))),
             )
             .await;
-        assert!(result.is_ok());
-
-        // verify a multiple underscores dispatch
-        let tool_call = CallToolRequestParams {
-            meta: None,
-            task: None,
-            name: "__cli__ent____tool".to_string().into(),
-            arguments: Some(object!({})),
-        };
+        let mut context = extension_manager.get_context().clone();
+        context.extension_manager = Some(Arc::downgrade(&extension_manager));
+        let client = ExtensionManagerClient::new(context).unwrap();
+        let mut notifications = client.subscribe().await;
 
-        let result = extension_manager
-            .dispatch_tool_call(
+        let result = client
+            .call_tool(
                 "test-session-id",
-                tool_call,
+                MANAGE_EXTENSIONS_TOOL_NAME,
+                Some(object!({ "action": "disable", "extension_name": "test_client" })),
                 None,
-                CancellationToken::default(),
+                CancellationToken::new(),
             )
-            .await;
-        assert!(result.is_ok());
+            .await
+            .unwrap();
+        assert_ne!(result.is_error, Some(true));
 
-        // Test unicode in tool name, "client 🚀" should become "client_"
-        let tool_call = CallToolRequestParams {
-            meta: None,
-            task: None,
-            name: "client___tool".to_string().into(),
-            arguments: Some(object!({})),
-        };
+        let notification = tokio::time::timeout(Duration::from_secs(1), notifications.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            notification,
+            ServerNotification::ToolListChangedNotification(_)
+        ));
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), notifications.recv())
+                .await
+                .is_err()
+        );
+    }
 
-        let result = extension_manager
-            .dispatch_tool_call(
-                "test-session-id",
-                tool_call,
-                None,
-                CancellationToken::default(),
+    #[tokio::test]
+    async fn test_cancel_extension_operations() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager =
+            ExtensionManager::new_without_provider(temp_dir.path().to_path_buf());
+
+        extension_manager
+            .add_mock_extension(
+                "blocking".to_string(),
+                Arc::new(Mutex::new(Box::new(BlockingClient {}))),
             )
             .await;
-        assert!(result.is_ok());
 
         let tool_call = CallToolRequestParams {
             meta: None,
             task: None,
-            name: "client___test__tool".to_string().into(),
+            name: "blocking__wait".to_string().into(),
             arguments: Some(object!({})),
         };
-
-        let result = extension_manager
+        let call = extension_manager
             .dispatch_tool_call(
                 "test-session-id",
                 tool_call,
                 None,
                 CancellationToken::default(),
             )
-            .await;
-        assert!(result.is_ok());
-
-        // this should error out, specifically for an ToolError::ExecutionError
-        let invalid_tool_call = CallToolRequestParams {
-            meta: None,
-            task: None,
-            name: "client___tools".to_string().into(),
-            arguments: Some(object!({})),
-        };
-
-        let result = extension_manager
-            .dispatch_tool_call(
-                "test-session-id",
-                invalid_tool_call,
-                None,
-                CancellationToken::default(),
-            )
             .await
-            .unwrap()
-            .result
-            .await;
-        assert!(matches!(
-            result,
-            Err(ErrorData {
-                code: ErrorCode::INTERNAL_ERROR,
-                ..
-            })
-        ));
-
-        // this should error out, specifically with an ToolError::NotFound
-        // this client doesn't exist
-        let invalid_tool_call = CallToolRequestParams {
-            meta: None,
-            task: None,
-            name: "_client__tools".to_string().into(),
-            arguments: Some(object!({})),
-        };
+            .unwrap();
+        let pending = tokio::spawn(call.result);
 
-        let result = extension_manager
-            .dispatch_tool_call(
-                "test-session-id",
-                invalid_tool_call,
-                None,
-                CancellationToken::default(),
-            )
-            .await;
-        if let Err(err) = result {
-            let tool_err = err.downcast_ref::<ErrorData>().expect("Expected ErrorData");
-            assert_eq!(tool_err.code, ErrorCode::RESOURCE_NOT_FOUND);
-        } else {
-            panic!("Expected ErrorData with ErrorCode::RESOURCE_NOT_FOUND");
-        }
+        assert_eq!(
+            extension_manager
+                .cancel_extension_operations("blocking")
+                .await,
+            Some(1)
+        );
+        assert!(pending.await.unwrap().is_err());
+
+        // Finished calls are no longer tracked, and the extension stays enabled
+        assert_eq!(
+            extension_manager
+                .cancel_extension_operations("blocking")
+                .await,
+            Some(0)
+        );
+        assert_eq!(
+            extension_manager
+                .cancel_extension_operations("missing")
+                .await,
+            None
+        );
     }
 
     #[tokio::test]
@@ -1996,6 +7667,14 @@ mod tests {
         let mut env_map = HashMap::new();
         env_map.insert("AUTH_TOKEN".to_string(), "secret123".to_string());
         env_map.insert("API_KEY".to_string(), "key456".to_string());
+        let substitute_env_vars = |value: &str, env_map: &HashMap<String, String>| {
+            substitute_variables(
+                value,
+                true,
+                |name| env_map.get(name).cloned(),
+                &mut BTreeSet::new(),
+            )
+        };
 
         // Test ${VAR} syntax
         let result = substitute_env_vars("Bearer ${ AUTH_TOKEN }", &env_map);
@@ -2014,8 +7693,18 @@ mod tests {
         assert_eq!(result, "Key: key456, Token: secret123");
 
         // Test no substitution when variable doesn't exist
-        let result = substitute_env_vars("Bearer ${UNKNOWN_VAR}", &env_map);
-        assert_eq!(result, "Bearer ${UNKNOWN_VAR}");
+        let mut unresolved = BTreeSet::new();
+        let result = substitute_variables(
+            "Bearer ${UNKNOWN_VAR} $OTHER_UNKNOWN",
+            true,
+            |name| env_map.get(name).cloned(),
+            &mut unresolved,
+        );
+        assert_eq!(result, "Bearer ${UNKNOWN_VAR} $OTHER_UNKNOWN");
+        assert_eq!(
+            unresolved.into_iter().collect::<Vec<_>>(),
+            vec!["OTHER_UNKNOWN", "UNKNOWN_VAR"]
+        );
 
         // Test mixed content
         let result = substitute_env_vars(
@@ -2054,6 +7743,212 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_validate_extension_config() {
+        assert!(
+            validate_extension_config(&ExtensionConfig::stdio("s", "echo", "d", 10u64)).is_ok()
+        );
+        assert!(validate_extension_config(&ExtensionConfig::Platform {
+            name: "todo".to_string(),
+            description: String::new(),
+            display_name: None,
+            bundled: None,
+            available_tools: vec![],
+        })
+        .is_ok());
+
+        let sse = ExtensionConfig::Sse {
+            name: "sse".to_string(),
+            description: String::new(),
+            uri: None,
+        };
+        assert!(validate_extension_config(&sse)
+            .unwrap_err()
+            .to_string()
+            .contains("SSE is unsupported"));
+
+        let unknown_platform = ExtensionConfig::Platform {
+            name: "not_a_platform".to_string(),
+            description: String::new(),
+            display_name: None,
+            bundled: None,
+            available_tools: vec![],
+        };
+        assert!(validate_extension_config(&unknown_platform)
+            .unwrap_err()
+            .to_string()
+            .contains("Unknown platform extension"));
+
+        let mut bad_header = ExtensionConfig::streamable_http("h", "http://localhost", "d", 10u64);
+        if let ExtensionConfig::StreamableHttp { headers, .. } = &mut bad_header {
+            headers.insert("bad header".to_string(), "value".to_string());
+        }
+        assert!(validate_extension_config(&bad_header)
+            .unwrap_err()
+            .to_string()
+            .contains("invalid header"));
+
+        assert!(
+            validate_extension_config(&ExtensionConfig::stdio("s", "  ", "d", 10u64))
+                .unwrap_err()
+                .to_string()
+                .contains("empty command")
+        );
+        for uri in ["localhost:3000/mcp", "ftp://example.com/mcp", "not a url"] {
+            assert!(
+                validate_extension_config(&ExtensionConfig::streamable_http("h", uri, "d", 10u64))
+                    .is_err(),
+                "{} should be rejected",
+                uri
+            );
+        }
+    }
+
+    #[test]
+    fn test_hash_resource_contents() {
+        let a = vec![ResourceContents::text("hello", "file:///a")];
+        let same_text_other_uri = vec![ResourceContents::text("hello", "file:///b")];
+        let changed = vec![ResourceContents::text("hello!", "file:///a")];
+
+        assert_eq!(
+            hash_resource_contents(&a),
+            hash_resource_contents(&same_text_other_uri)
+        );
+        assert_ne!(hash_resource_contents(&a), hash_resource_contents(&changed));
+    }
+
+    #[test]
+    fn test_capability_flags() {
+        let tools_only = CapabilityFlags::from_server_capabilities(
+            &ServerCapabilities::builder().enable_tools().build(),
+        );
+        let resources_only = CapabilityFlags::from_server_capabilities(
+            &ServerCapabilities::builder().enable_resources().build(),
+        );
+        assert!(tools_only.tools && !tools_only.resources);
+        assert!(resources_only.resources && !resources_only.resource_subscriptions);
+
+        let subscribable = CapabilityFlags::from_server_capabilities(
+            &ServerCapabilities::builder()
+                .enable_resources()
+                .enable_resources_subscribe()
+                .build(),
+        );
+        assert!(subscribable.resources && subscribable.resource_subscriptions);
+
+        let session = tools_only.union(resources_only);
+        assert_eq!(
+            session,
+            CapabilityFlags {
+                tools: true,
+                resources: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_labeled_representation() {
+        let csv = labeled_representation(
+            "table://users",
+            ResourceContents::TextResourceContents {
+                uri: "table://users".to_string(),
+                mime_type: Some("text/csv".to_string()),
+                text: "id,name\n1,ada".to_string(),
+                meta: None,
+            },
+        );
+        assert_eq!(
+            csv.as_text().unwrap().text,
+            "table://users\nrepresentation: text/csv\n\nid,name\n1,ada"
+        );
+
+        let chart = labeled_representation(
+            "table://users",
+            ResourceContents::BlobResourceContents {
+                uri: "table://users".to_string(),
+                mime_type: Some("image/png".to_string()),
+                blob: "aGVsbG8=".to_string(),
+                meta: None,
+            },
+        );
+        assert_eq!(chart.as_image().unwrap().mime_type, "image/png");
+
+        let archive = labeled_representation(
+            "table://users",
+            ResourceContents::BlobResourceContents {
+                uri: "table://users".to_string(),
+                mime_type: Some("application/zip".to_string()),
+                blob: "aGVsbG8=".to_string(),
+                meta: None,
+            },
+        );
+        assert!(archive.as_resource().is_some());
+    }
+
+    #[test]
+    fn test_extension_match_score() {
+        assert_eq!(
+            extension_match_score("git", "github", "Work with GitHub repos"),
+            3
+        );
+        assert_eq!(
+            extension_match_score("Issues git", "jira", "Track issues"),
+            1
+        );
+        assert_eq!(extension_match_score("slack", "jira", "Track issues"), 0);
+    }
+
+    #[test]
+    fn test_extension_category() {
+        let config = ExtensionConfig::Builtin {
+            name: "developer".to_string(),
+            display_name: None,
+            description: String::new(),
+            timeout: None,
+            bundled: None,
+            available_tools: vec![],
+        };
+        assert_eq!(extension_category(&config), "builtin");
+    }
+
+    #[test]
+    fn test_compile_name_pattern() {
+        let glob = compile_name_pattern("git*").unwrap();
+        assert!(glob.is_match("github"));
+        assert!(glob.is_match("git"));
+        assert!(!glob.is_match("legit"));
+
+        let single = compile_name_pattern("db?").unwrap();
+        assert!(single.is_match("db1"));
+        assert!(!single.is_match("db12"));
+
+        let literal = compile_name_pattern("a.b").unwrap();
+        assert!(!literal.is_match("axb"));
+
+        let regex = compile_name_pattern("/^(git|jira)/").unwrap();
+        assert!(regex.is_match("jira_cloud"));
+        assert!(!regex.is_match("slack"));
+
+        assert!(compile_name_pattern("/(unclosed/").is_err());
+    }
+
+    #[test]
+    fn test_resource_contents_size() {
+        let text = ResourceContents::text("hello", "file:///a.txt");
+        assert_eq!(resource_contents_size(&text), 5);
+
+        for (blob, size) in [("aGVsbG8=", 5), ("aGVsbA==", 4), ("aGVs", 3)] {
+            let content = ResourceContents::BlobResourceContents {
+                uri: "file:///a.bin".to_string(),
+                mime_type: None,
+                blob: blob.to_string(),
+                meta: None,
+            };
+            assert_eq!(resource_contents_size(&content), size);
+        }
+    }
+
     #[tokio::test]
     async fn test_collect_moim_uses_minute_granularity() {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -1,22 +1,37 @@
-use crate::agents::extension::PlatformExtensionContext;
+use crate::agents::extension::{
+    ExtensionConfig, ExtensionError, PlatformExtensionContext, ToolBudget, ToolRateLimit,
+    PLATFORM_EXTENSIONS,
+};
+use crate::agents::extension_manager::{
+    validate_extension_config, CapabilityFlags, ExtensionManager, ExtensionPing, ExtensionStatus,
+    ExtensionTransport, DEFAULT_RESOURCE_CHUNK_BYTES, RESOURCE_CHUNK_NOTIFICATION_METHOD,
+};
 use crate::agents::mcp_client::{Error, McpClientTrait};
-use crate::config::get_extension_by_name;
+use crate::agents::resource_template::expand_template;
+use crate::config::extensions::name_to_key;
+use crate::config::{get_all_extensions, get_extension_by_name, Config};
+use crate::token_counter::create_token_counter;
 use anyhow::Result;
 use async_trait::async_trait;
 use indoc::indoc;
 use rmcp::model::{
-    CallToolResult, Content, ErrorCode, ErrorData, GetPromptResult, Implementation,
-    InitializeResult, JsonObject, ListPromptsResult, ListResourcesResult, ListToolsResult,
-    ProtocolVersion, ReadResourceResult, ServerCapabilities, ServerNotification, Tool,
-    ToolAnnotations, ToolsCapability,
+    CallToolResult, CompleteRequestParams, CompleteResult, CompletionInfo, Content, ErrorCode,
+    ErrorData, GetPromptResult, Implementation, InitializeResult, JsonObject, ListPromptsResult,
+    ListResourcesResult, ListToolsResult, ProtocolVersion, ReadResourceResult, ServerCapabilities,
+    ServerNotification, Tool, ToolAnnotations, ToolListChangedNotification, ToolsCapability,
 };
+use rmcp::ServiceError;
 use schemars::{schema_for, JsonSchema};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::sync::Arc;
-use tokio::sync::mpsc;
+use std::time::Instant;
+use tokio::sync::{broadcast, mpsc};
 use tokio_util::sync::CancellationToken;
-use tracing::error;
+use tracing::{error, info, warn, Instrument};
 
 pub static EXTENSION_NAME: &str = "Extension Manager";
 // pub static DISPLAY_NAME: &str = "Extension Manager";
@@ -38,8 +53,77 @@ pub enum ExtensionManagerToolError {
     #[error("Extension operation failed: {message}")]
     OperationFailed { message: String },
 
+    /// A failure that keeps the MCP error code, so callers can tell e.g. a missing extension
+    /// from one that failed to start
+    #[error("Extension operation failed: {message}")]
+    CodedFailure { code: ErrorCode, message: String },
+
     #[error("Failed to deserialize parameters: {0}")]
     DeserializationError(#[from] serde_json::Error),
+
+    #[error(
+        "{tool_name} was called too often and did not run. Wait about {retry_after_secs}s \
+         before calling it again, and check whether repeating the call will help."
+    )]
+    RateLimited {
+        tool_name: String,
+        retry_after_secs: u64,
+    },
+
+    /// Arguments that don't fit a tool's parameters, with the fields it expects so the model
+    /// can correct them
+    #[error(
+        "Invalid arguments for {tool_name}: {source}. Required fields: {}. Optional fields: {}",
+        list_or_none(required),
+        list_or_none(optional)
+    )]
+    InvalidArguments {
+        tool_name: String,
+        source: serde_json::Error,
+        required: Vec<String>,
+        optional: Vec<String>,
+    },
+}
+
+fn list_or_none(fields: &[String]) -> String {
+    if fields.is_empty() {
+        "none".to_string()
+    } else {
+        fields.join(", ")
+    }
+}
+
+/// Deserialize a tool's arguments, reporting failures with the required and optional fields
+/// from the parameters' schema
+fn parse_params<T: DeserializeOwned + JsonSchema>(
+    tool_name: &str,
+    arguments: JsonObject,
+) -> Result<T, ExtensionManagerToolError> {
+    serde_json::from_value(Value::Object(arguments)).map_err(|source| {
+        let schema = serde_json::to_value(schema_for!(T)).unwrap_or_default();
+        let required: Vec<String> = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect();
+        let optional = schema
+            .get("properties")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flat_map(|properties| properties.keys())
+            .filter(|name| !required.contains(name))
+            .cloned()
+            .collect();
+        ExtensionManagerToolError::InvalidArguments {
+            tool_name: tool_name.to_string(),
+            source,
+            required,
+            optional,
+        }
+    })
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
@@ -53,31 +137,880 @@ pub enum ManageExtensionAction {
 pub struct ManageExtensionsParams {
     pub action: ManageExtensionAction,
     pub extension_name: String,
+    /// How long to wait for the extension to start when enabling it, in milliseconds.
+    /// Capped by the GOOSE_MAX_EXTENSION_INIT_TIMEOUT_MS setting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub init_timeout_ms: Option<u64>,
+    /// Load at most this many of the extension's tools when enabling it, to keep the
+    /// context small. Tools beyond the limit are left out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tools: Option<usize>,
+    /// How many more times to try connecting when enabling an extension fails, with
+    /// exponential backoff between attempts. Defaults to 2; set to 0 to give up on the
+    /// first failure. At most 5.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+    /// Only make these of the extension's tools available when enabling it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow_tools: Option<Vec<String>>,
+    /// Never make these of the extension's tools available; wins over allow_tools when a
+    /// tool is in both
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny_tools: Vec<String>,
+    /// Why the extension is being enabled or disabled, kept in the extension audit log
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl ManageExtensionsParams {
+    fn new(action: ManageExtensionAction, extension_name: String) -> Self {
+        Self {
+            action,
+            extension_name,
+            init_timeout_ms: None,
+            max_tools: None,
+            retries: None,
+            allow_tools: None,
+            deny_tools: Vec::new(),
+            reason: None,
+        }
+    }
+}
+
+/// Upper bound for `init_timeout_ms` unless GOOSE_MAX_EXTENSION_INIT_TIMEOUT_MS is set
+const DEFAULT_MAX_INIT_TIMEOUT_MS: u64 = 10 * 60 * 1000;
+
+/// Retries after a failed connection when enabling, unless the caller sets `retries`
+const DEFAULT_ENABLE_RETRIES: u32 = 2;
+
+/// Upper bound for `retries`, so a bad value can't keep the agent waiting indefinitely
+const MAX_ENABLE_RETRIES: u32 = 5;
+
+/// Delay before the first retry; it doubles on each further attempt
+const ENABLE_RETRY_BASE_DELAY_MS: u64 = 500;
+
+/// Whether enabling `config` is worth retrying after it failed with `error`. Only
+/// extensions that connect through a transport can fail transiently, and a stdio command
+/// that doesn't exist won't appear on a second try.
+fn is_retryable_enable_error(config: &ExtensionConfig, error: &ExtensionError) -> bool {
+    let transport_backed = matches!(
+        config,
+        ExtensionConfig::StreamableHttp { .. } | ExtensionConfig::Stdio { .. }
+    );
+    let permanent = match error {
+        ExtensionError::ConfigError(_) => true,
+        ExtensionError::IoError(e) => e.kind() == std::io::ErrorKind::NotFound,
+        _ => false,
+    };
+    transport_backed && !permanent
+}
+
+/// How long listing or reading resources may take unless GOOSE_RESOURCE_TOOL_TIMEOUT_MS is set
+const DEFAULT_RESOURCE_TOOL_TIMEOUT_MS: u64 = 10 * 1000;
+
+fn resource_tool_timeout_ms() -> u64 {
+    Config::global()
+        .get_param::<u64>("GOOSE_RESOURCE_TOOL_TIMEOUT_MS")
+        .unwrap_or(DEFAULT_RESOURCE_TOOL_TIMEOUT_MS)
+}
+
+/// A count_only listing: a one-line summary, plus the per-extension counts as structured
+/// content
+fn resource_count_result(counts: BTreeMap<String, usize>, notes: Vec<Content>) -> CallToolResult {
+    let total: usize = counts.values().sum();
+    let summary = format!(
+        "{} resource(s) across {} extension(s){}{}",
+        total,
+        counts.len(),
+        if counts.is_empty() { "" } else { ": " },
+        counts
+            .iter()
+            .map(|(name, count)| format!("{} {}", name, count))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let mut content = vec![Content::text(summary)];
+    content.extend(notes);
+    CallToolResult {
+        structured_content: Some(serde_json::json!({ "counts": counts, "total": total })),
+        ..CallToolResult::success(content)
+    }
+}
+
+/// A successful manage_extensions call: the text for the model, plus the outcome as
+/// structured content so callers don't have to parse it
+fn manage_extensions_result(
+    action: &ManageExtensionAction,
+    extension_name: &str,
+    tools_added: &[String],
+    content: Vec<Content>,
+) -> CallToolResult {
+    CallToolResult {
+        structured_content: Some(serde_json::json!({
+            "action": action,
+            "extension_name": extension_name,
+            "success": true,
+            "tools_added": tools_added,
+        })),
+        ..CallToolResult::success(content)
+    }
+}
+
+/// The result of enabling an extension: how long it took and which tools it added, named in
+/// the text and listed in full as structured content
+fn enabled_result(
+    extension_name: &str,
+    load_time: std::time::Duration,
+    added_tools: Vec<String>,
+    notes: Vec<Content>,
+) -> CallToolResult {
+    let mut content = vec![Content::text(format!(
+        "The extension '{}' has been installed successfully in {:.1}s, adding {} tool(s){}{}",
+        extension_name,
+        load_time.as_secs_f64(),
+        added_tools.len(),
+        if added_tools.is_empty() { "" } else { ": " },
+        added_tools.join(", ")
+    ))];
+    content.extend(notes);
+    let mut result = manage_extensions_result(
+        &ManageExtensionAction::Enable,
+        extension_name,
+        &added_tools,
+        content,
+    );
+    if let Some(Value::Object(outcome)) = &mut result.structured_content {
+        outcome.insert(
+            "load_time_ms".to_string(),
+            Value::from(load_time.as_millis() as u64),
+        );
+    }
+    result
+}
+
+/// Start streaming a resource for `read_resource` with `stream: true`
+fn stream_resource(
+    extension_manager: &Arc<ExtensionManager>,
+    session_id: &str,
+    params: &ReadResourceParams,
+) -> Result<Vec<Content>, ExtensionManagerToolError> {
+    let uri = params
+        .uri
+        .as_deref()
+        .ok_or_else(|| ExtensionManagerToolError::MissingParameter {
+            param_name: "uri".to_string(),
+        })?;
+    let chunk_bytes = match params.chunk_bytes {
+        Some(0) => {
+            return Err(ExtensionManagerToolError::CodedFailure {
+                code: ErrorCode::INVALID_PARAMS,
+                message: "chunk_bytes must be at least 1".to_string(),
+            })
+        }
+        Some(chunk_bytes) => chunk_bytes,
+        None => DEFAULT_RESOURCE_CHUNK_BYTES,
+    };
+    let extension_name = params.extension_name.as_deref();
+
+    let stream_id = extension_manager
+        .stream_resource(session_id, uri, extension_name, chunk_bytes)
+        .map_err(|e| ExtensionManagerToolError::CodedFailure {
+            code: e.code,
+            message: e.message.to_string(),
+        })?;
+    Ok(vec![Content::text(format!(
+        "Streaming {} as {}; its chunks arrive as {} notifications",
+        uri, stream_id, RESOURCE_CHUNK_NOTIFICATION_METHOD
+    ))])
+}
+
+/// Run a resource operation, giving up after `timeout_ms` so a hung extension can't stall the
+/// whole turn
+async fn with_resource_timeout<T>(
+    operation: &str,
+    timeout_ms: u64,
+    operation_future: impl std::future::Future<Output = Result<T, ErrorData>>,
+) -> Result<T, ExtensionManagerToolError> {
+    match tokio::time::timeout(
+        std::time::Duration::from_millis(timeout_ms),
+        operation_future,
+    )
+    .await
+    {
+        Ok(result) => result.map_err(|e| ExtensionManagerToolError::OperationFailed {
+            message: format!("Failed to {}: {}", operation, e.message),
+        }),
+        Err(_) => Err(ExtensionManagerToolError::OperationFailed {
+            message: format!("Failed to {}: timed out after {} ms", operation, timeout_ms),
+        }),
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ReadResourceParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+    /// Instead of uri, read the resource at this index in the latest list_resources of
+    /// extension_name in this session
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extension_name: Option<String>,
+    /// Return every representation the extension offers (e.g. CSV text and JSON), each
+    /// labeled with its MIME type, instead of only the text content
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub all_representations: bool,
+    /// Only return the representation with this MIME type, e.g. "text/markdown". Also picks
+    /// between extensions that serve the same URI when extension_name is not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    /// Fail instead of returning the content if the resource is larger than this many bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_bytes: Option<usize>,
+    /// Return at most this many bytes of a text resource, marking the content as truncated
+    /// instead of failing like max_bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub truncate_at: Option<usize>,
+    /// Start reading a text resource at this byte offset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+    /// Read at most this many bytes of a text resource, starting at offset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length: Option<usize>,
+    /// The etag from an earlier read. If the resource still has it, only a short
+    /// "unchanged" note is returned instead of the content.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub if_none_match: Option<String>,
+    /// Return a stream id right away and send the content as notifications/resource_chunk
+    /// notifications instead
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub stream: bool,
+    /// Largest chunk to send when streaming, in bytes (default 65536)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chunk_bytes: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReadMultipleResourcesParams {
+    pub uris: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extension_name: Option<String>,
+    /// Stop adding resources once their combined size would exceed this many bytes; the
+    /// rest are listed as skipped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_total_bytes: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ExpandResourceTemplateParams {
+    /// An RFC 6570 URI template from list_resources, e.g. "file:///{+path}"
+    pub uri_template: String,
+    /// Values for the template's variables, by name
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PrefetchResourcesParams {
+    pub uris: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extension_name: Option<String>,
+    /// How long read_resource serves the prefetched content, in seconds (default 300)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl_secs: Option<u64>,
+}
+
+/// How long prefetched resources stay cached when the caller doesn't say
+const DEFAULT_PREFETCH_TTL_SECS: u64 = 300;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WriteResourceParams {
     pub uri: String,
+    /// The new text content of the resource
+    pub content: String,
+    /// The extension to write to; may be omitted when only one extension supports writes
     #[serde(skip_serializing_if = "Option::is_none")]
     pub extension_name: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
-pub struct ListResourcesParams {
+pub struct WatchResourceParams {
+    pub uri: String,
+    /// The extension serving the resource; found from the extensions' resource lists if
+    /// omitted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extension_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ResourceHashParams {
+    pub uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extension_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SearchAvailableExtensionsParams {
+    /// Keywords to look for in extension names and descriptions; best matches come first
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    /// Only list extensions of this type, e.g. "builtin", "stdio", or "streamable_http"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ListResourcesParams {
+    #[serde(alias = "extension", skip_serializing_if = "Option::is_none")]
     pub extension_name: Option<String>,
+    /// Only list resources whose URI starts with this prefix, e.g. "file:///project/src/"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uri_prefix: Option<String>,
+    /// Only query extensions whose name matches this pattern: a glob such as "git*", or a
+    /// regex wrapped in slashes such as "/^(git|jira)/". Ignored when extension_name is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name_pattern: Option<String>,
+    /// When listing all extensions, skip any that take longer than this to respond
+    /// (default 5000)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_extension_timeout_ms: Option<u64>,
+    /// The next_cursor from a previous page, to continue listing from there
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    /// Maximum number of resources to return in this page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    /// Only return how many resources each matching extension lists, in structured content
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub count_only: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ValidateConfigsParams {
+    /// Extension configs to validate, in the same format used by the extensions config
+    pub configs: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FetchResultParams {
+    /// The handle returned by a tool call made with store_result_as_reference
+    pub handle: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ToolBaselineParams {
+    /// Name the baseline is stored under
+    pub name: String,
+}
+
+const TOOL_BASELINES_CONFIG_KEY: &str = "tool_baselines";
+
+/// The parts of a tool that are compared when diffing against a baseline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ToolSignature {
+    description: String,
+    schema_hash: String,
+}
+
+type ToolBaseline = BTreeMap<String, ToolSignature>;
+
+impl ToolSignature {
+    fn from_tool(tool: &Tool) -> Self {
+        let schema = serde_json::to_string(&tool.input_schema).unwrap_or_default();
+        Self {
+            description: tool.description.as_deref().unwrap_or_default().to_string(),
+            schema_hash: format!("{:x}", Sha256::digest(schema.as_bytes())),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct ToolBaselineDiff {
+    added: Vec<String>,
+    removed: Vec<String>,
+    changed: Vec<(String, Vec<&'static str>)>,
+}
+
+fn diff_tool_baseline(baseline: &ToolBaseline, current: &ToolBaseline) -> ToolBaselineDiff {
+    let mut diff = ToolBaselineDiff::default();
+
+    for (name, signature) in current {
+        match baseline.get(name) {
+            None => diff.added.push(name.clone()),
+            Some(saved) if saved != signature => {
+                let mut fields = Vec::new();
+                if saved.description != signature.description {
+                    fields.push("description");
+                }
+                if saved.schema_hash != signature.schema_hash {
+                    fields.push("schema");
+                }
+                diff.changed.push((name.clone(), fields));
+            }
+            Some(_) => {}
+        }
+    }
+
+    diff.removed = baseline
+        .keys()
+        .filter(|name| !current.contains_key(*name))
+        .cloned()
+        .collect();
+
+    diff
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ListExtensionErrorsParams {
+    pub extension_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CancelExtensionOperationsParams {
+    pub extension_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SetExtensionAliasParams {
+    pub extension_name: String,
+    /// The name to show instead. Omit to go back to the extension's own name.
+    #[serde(default)]
+    pub alias: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PreviewEnableImpactParams {
+    pub extension_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct IdleExtensionsParams {
+    /// Report extensions without a successful tool call in this many minutes. Defaults to 30.
+    #[serde(default)]
+    pub idle_minutes: Option<u64>,
+}
+
+const DEFAULT_IDLE_MINUTES: u64 = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PingExtensionParams {
+    pub extension_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GetExtensionInstructionsParams {
+    pub extension_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DescribeToolParams {
+    pub extension_name: String,
+    /// The tool's name within the extension, without the extension prefix
+    pub tool_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SetToolBudgetParams {
+    /// Maximum number of tool calls for this session, counting calls already made. Omit to
+    /// remove the cap.
+    #[serde(default)]
+    pub limit: Option<usize>,
 }
 
-pub const READ_RESOURCE_TOOL_NAME: &str = "read_resource";
-pub const LIST_RESOURCES_TOOL_NAME: &str = "list_resources";
-pub const SEARCH_AVAILABLE_EXTENSIONS_TOOL_NAME: &str = "search_available_extensions";
-pub const MANAGE_EXTENSIONS_TOOL_NAME: &str = "manage_extensions";
+/// The tools this extension serves. `call_tool` dispatches on this, so a new tool has to be
+/// added here before it can be called.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExtensionManagerTool {
+    ReadResource,
+    ReadMultipleResources,
+    WatchResource,
+    ResourceHash,
+    ListResources,
+    SearchAvailableExtensions,
+    ListEnabledExtensions,
+    ManageExtensions,
+    ValidateConfigs,
+    FetchResult,
+    SaveToolBaseline,
+    DiffToolBaseline,
+    ListExtensionErrors,
+    CancelExtensionOperations,
+    SetExtensionAlias,
+    GetCapabilities,
+    PreviewEnableImpact,
+    IdleExtensions,
+    PingExtension,
+    SetToolBudget,
+    GetToolBudget,
+    ReloadChangedExtensions,
+    ResourceCatalog,
+    GenerateSupportBundle,
+    GetExtensionStatus,
+    DisableAllExtensions,
+    WriteResource,
+    GetExtensionInstructions,
+    DescribeTool,
+    RestoreExtensions,
+    GetExtensionAuditLog,
+    PrefetchResources,
+    ExpandResourceTemplate,
+}
+
+impl ExtensionManagerTool {
+    pub const ALL: [ExtensionManagerTool; 33] = [
+        Self::ReadResource,
+        Self::ReadMultipleResources,
+        Self::WatchResource,
+        Self::ResourceHash,
+        Self::ListResources,
+        Self::SearchAvailableExtensions,
+        Self::ListEnabledExtensions,
+        Self::ManageExtensions,
+        Self::ValidateConfigs,
+        Self::FetchResult,
+        Self::SaveToolBaseline,
+        Self::DiffToolBaseline,
+        Self::ListExtensionErrors,
+        Self::CancelExtensionOperations,
+        Self::SetExtensionAlias,
+        Self::GetCapabilities,
+        Self::PreviewEnableImpact,
+        Self::IdleExtensions,
+        Self::PingExtension,
+        Self::SetToolBudget,
+        Self::GetToolBudget,
+        Self::ReloadChangedExtensions,
+        Self::ResourceCatalog,
+        Self::GenerateSupportBundle,
+        Self::GetExtensionStatus,
+        Self::DisableAllExtensions,
+        Self::WriteResource,
+        Self::GetExtensionInstructions,
+        Self::DescribeTool,
+        Self::RestoreExtensions,
+        Self::GetExtensionAuditLog,
+        Self::PrefetchResources,
+        Self::ExpandResourceTemplate,
+    ];
+
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::ReadResource => "read_resource",
+            Self::ReadMultipleResources => "read_multiple_resources",
+            Self::WatchResource => "watch_resource",
+            Self::ResourceHash => "resource_hash",
+            Self::ListResources => "list_resources",
+            Self::SearchAvailableExtensions => "search_available_extensions",
+            Self::ListEnabledExtensions => "list_enabled_extensions",
+            Self::ManageExtensions => "manage_extensions",
+            Self::ValidateConfigs => "validate_configs",
+            Self::FetchResult => "fetch_result",
+            Self::SaveToolBaseline => "save_tool_baseline",
+            Self::DiffToolBaseline => "diff_tool_baseline",
+            Self::ListExtensionErrors => "list_extension_errors",
+            Self::CancelExtensionOperations => "cancel_extension_operations",
+            Self::SetExtensionAlias => "set_extension_alias",
+            Self::GetCapabilities => "get_capabilities",
+            Self::PreviewEnableImpact => "preview_enable_impact",
+            Self::IdleExtensions => "idle_extensions",
+            Self::PingExtension => "ping_extension",
+            Self::SetToolBudget => "set_tool_budget",
+            Self::GetToolBudget => "get_tool_budget",
+            Self::ReloadChangedExtensions => "reload_changed_extensions",
+            Self::ResourceCatalog => "resource_catalog",
+            Self::GenerateSupportBundle => "generate_support_bundle",
+            Self::GetExtensionStatus => "get_extension_status",
+            Self::DisableAllExtensions => "disable_all_extensions",
+            Self::WriteResource => "write_resource",
+            Self::GetExtensionInstructions => "get_extension_instructions",
+            Self::DescribeTool => "describe_tool",
+            Self::RestoreExtensions => "restore_extensions",
+            Self::GetExtensionAuditLog => "get_extension_audit_log",
+            Self::PrefetchResources => "prefetch_resources",
+            Self::ExpandResourceTemplate => "expand_resource_template",
+        }
+    }
+}
+
+impl std::str::FromStr for ExtensionManagerTool {
+    type Err = ExtensionManagerToolError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|tool| tool.as_str() == name)
+            .ok_or_else(|| ExtensionManagerToolError::UnknownTool {
+                tool_name: name.to_string(),
+            })
+    }
+}
+
+pub const READ_RESOURCE_TOOL_NAME: &str = ExtensionManagerTool::ReadResource.as_str();
+pub const READ_MULTIPLE_RESOURCES_TOOL_NAME: &str =
+    ExtensionManagerTool::ReadMultipleResources.as_str();
+pub const WATCH_RESOURCE_TOOL_NAME: &str = ExtensionManagerTool::WatchResource.as_str();
+pub const RESOURCE_HASH_TOOL_NAME: &str = ExtensionManagerTool::ResourceHash.as_str();
+pub const LIST_RESOURCES_TOOL_NAME: &str = ExtensionManagerTool::ListResources.as_str();
+pub const SEARCH_AVAILABLE_EXTENSIONS_TOOL_NAME: &str =
+    ExtensionManagerTool::SearchAvailableExtensions.as_str();
+pub const LIST_ENABLED_EXTENSIONS_TOOL_NAME: &str =
+    ExtensionManagerTool::ListEnabledExtensions.as_str();
+pub const MANAGE_EXTENSIONS_TOOL_NAME: &str = ExtensionManagerTool::ManageExtensions.as_str();
+pub const VALIDATE_CONFIGS_TOOL_NAME: &str = ExtensionManagerTool::ValidateConfigs.as_str();
+pub const FETCH_RESULT_TOOL_NAME: &str = ExtensionManagerTool::FetchResult.as_str();
+pub const SAVE_TOOL_BASELINE_TOOL_NAME: &str = ExtensionManagerTool::SaveToolBaseline.as_str();
+pub const DIFF_TOOL_BASELINE_TOOL_NAME: &str = ExtensionManagerTool::DiffToolBaseline.as_str();
+pub const LIST_EXTENSION_ERRORS_TOOL_NAME: &str =
+    ExtensionManagerTool::ListExtensionErrors.as_str();
+pub const CANCEL_EXTENSION_OPERATIONS_TOOL_NAME: &str =
+    ExtensionManagerTool::CancelExtensionOperations.as_str();
+pub const SET_EXTENSION_ALIAS_TOOL_NAME: &str = ExtensionManagerTool::SetExtensionAlias.as_str();
+pub const GET_CAPABILITIES_TOOL_NAME: &str = ExtensionManagerTool::GetCapabilities.as_str();
+pub const PREVIEW_ENABLE_IMPACT_TOOL_NAME: &str =
+    ExtensionManagerTool::PreviewEnableImpact.as_str();
+pub const IDLE_EXTENSIONS_TOOL_NAME: &str = ExtensionManagerTool::IdleExtensions.as_str();
+pub const PING_EXTENSION_TOOL_NAME: &str = ExtensionManagerTool::PingExtension.as_str();
+pub const SET_TOOL_BUDGET_TOOL_NAME: &str = ExtensionManagerTool::SetToolBudget.as_str();
+pub const GET_TOOL_BUDGET_TOOL_NAME: &str = ExtensionManagerTool::GetToolBudget.as_str();
+pub const RELOAD_CHANGED_EXTENSIONS_TOOL_NAME: &str =
+    ExtensionManagerTool::ReloadChangedExtensions.as_str();
+pub const RESOURCE_CATALOG_TOOL_NAME: &str = ExtensionManagerTool::ResourceCatalog.as_str();
+pub const GENERATE_SUPPORT_BUNDLE_TOOL_NAME: &str =
+    ExtensionManagerTool::GenerateSupportBundle.as_str();
+pub const GET_EXTENSION_STATUS_TOOL_NAME: &str = ExtensionManagerTool::GetExtensionStatus.as_str();
+pub const DISABLE_ALL_EXTENSIONS_TOOL_NAME: &str =
+    ExtensionManagerTool::DisableAllExtensions.as_str();
+pub const WRITE_RESOURCE_TOOL_NAME: &str = ExtensionManagerTool::WriteResource.as_str();
+pub const GET_EXTENSION_INSTRUCTIONS_TOOL_NAME: &str =
+    ExtensionManagerTool::GetExtensionInstructions.as_str();
+pub const DESCRIBE_TOOL_TOOL_NAME: &str = ExtensionManagerTool::DescribeTool.as_str();
+pub const RESTORE_EXTENSIONS_TOOL_NAME: &str = ExtensionManagerTool::RestoreExtensions.as_str();
+pub const GET_EXTENSION_AUDIT_LOG_TOOL_NAME: &str =
+    ExtensionManagerTool::GetExtensionAuditLog.as_str();
+pub const PREFETCH_RESOURCES_TOOL_NAME: &str = ExtensionManagerTool::PrefetchResources.as_str();
+pub const EXPAND_RESOURCE_TEMPLATE_TOOL_NAME: &str =
+    ExtensionManagerTool::ExpandResourceTemplate.as_str();
 pub const MANAGE_EXTENSIONS_TOOL_NAME_COMPLETE: &str = "extensionmanager__manage_extensions";
 
+/// The extensions to enable, in order, so that `name` has everything it depends on: its
+/// dependencies (recursively, each once) first and `name` itself last. Fails with the
+/// offending path, e.g. "a -> b -> a", if the dependencies form a cycle.
+fn dependency_chain(
+    name: &str,
+    depends_on: &impl Fn(&str) -> Vec<String>,
+) -> Result<Vec<String>, String> {
+    fn visit(
+        name: &str,
+        depends_on: &impl Fn(&str) -> Vec<String>,
+        path: &mut Vec<String>,
+        chain: &mut Vec<String>,
+    ) -> Result<(), String> {
+        let key = name_to_key(name);
+        if let Some(start) = path
+            .iter()
+            .position(|visiting| name_to_key(visiting) == key)
+        {
+            let mut cycle = path.split_off(start);
+            cycle.push(name.to_string());
+            return Err(cycle.join(" -> "));
+        }
+        if chain.iter().any(|done| name_to_key(done) == key) {
+            return Ok(());
+        }
+        path.push(name.to_string());
+        for dependency in depends_on(name) {
+            visit(&dependency, depends_on, path, chain)?;
+        }
+        path.pop();
+        chain.push(name.to_string());
+        Ok(())
+    }
+
+    let mut chain = Vec::new();
+    visit(name, depends_on, &mut Vec::new(), &mut chain)?;
+    Ok(chain)
+}
+
+/// Levenshtein distance between two strings, counted in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// The candidate closest to a mistyped extension name, if one is close enough to be a
+/// plausible typo: at most a third of the name's length (and at least one edit) away
+fn closest_extension_name<'a>(name: &str, candidates: &'a [String]) -> Option<&'a str> {
+    let key = name_to_key(name);
+    let max_distance = (key.chars().count() / 3).max(1);
+    candidates
+        .iter()
+        .map(|candidate| (edit_distance(&key, &name_to_key(candidate)), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.as_str())
+}
+
+/// One enabled extension as reported by get_extension_status
+#[derive(Debug, Serialize)]
+struct ExtensionHealth {
+    #[serde(flatten)]
+    status: ExtensionStatus,
+    /// "connected", or "crashed" once its transport has gone away
+    connection: &'static str,
+    /// None if the tools couldn't be listed
+    tool_count: Option<usize>,
+}
+
+fn format_ping(extension_name: &str, ping: &ExtensionPing) -> String {
+    let status = match (ping.transport, &ping.result) {
+        (ExtensionTransport::InProcess, _) => {
+            "runs in-process as a platform extension and is always reachable".to_string()
+        }
+        (ExtensionTransport::Remote, Ok(latency)) => {
+            format!("reachable, round-trip took {} ms", latency.as_millis())
+        }
+        (ExtensionTransport::Local, Ok(latency)) => {
+            format!("process is alive, responded in {} ms", latency.as_millis())
+        }
+        (_, Err(ServiceError::Timeout { timeout })) => {
+            format!("did not respond within {} s", timeout.as_secs())
+        }
+        (
+            ExtensionTransport::Remote,
+            Err(e @ (ServiceError::TransportClosed | ServiceError::TransportSend(_))),
+        ) => format!("unreachable, the network connection failed: {}", e),
+        (
+            ExtensionTransport::Local,
+            Err(ServiceError::TransportClosed | ServiceError::TransportSend(_)),
+        ) => "process is not running or has closed its stdio".to_string(),
+        (_, Err(e)) => format!("reachable, but responded with a protocol error: {}", e),
+    };
+
+    let mut text = format!("Extension '{}': {}", extension_name, status);
+    if ping.crashed {
+        text.push_str(
+            "\nIt is marked as crashed, so tool calls fail fast; disable and re-enable it with \
+             manage_extensions to restart it.",
+        );
+    }
+    text
+}
+
+fn format_tool_budget(budget: &ToolBudget) -> String {
+    match (budget.limit, budget.remaining()) {
+        (Some(limit), Some(remaining)) => format!(
+            "Tool call budget: {} of {} used, {} remaining",
+            budget.used, limit, remaining
+        ),
+        _ => format!("Tool call budget: unlimited, {} call(s) made", budget.used),
+    }
+}
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Serialize an extension config for a support bundle, with env values, header values, and
+/// URI query strings (which often carry tokens) replaced by a placeholder
+fn redacted_config(config: &ExtensionConfig) -> Value {
+    let mut value = serde_json::to_value(config).unwrap_or(Value::Null);
+    if let Some(object) = value.as_object_mut() {
+        for key in ["envs", "headers"] {
+            if let Some(Value::Object(map)) = object.get_mut(key) {
+                for entry in map.values_mut() {
+                    *entry = Value::String(REDACTED.to_string());
+                }
+            }
+        }
+        if let Some(Value::String(uri)) = object.get_mut("uri") {
+            if let Some((base, _query)) = uri.split_once('?') {
+                *uri = format!("{}?{}", base, REDACTED);
+            }
+        }
+    }
+    value
+}
+
+/// How many earlier extension sets restore_extensions can step back through
+const MAX_EXTENSION_SNAPSHOTS: usize = 10;
+
 pub struct ExtensionManagerClient {
     info: InitializeResult,
     #[allow(dead_code)]
     context: PlatformExtensionContext,
+    /// The enabled extension names before each change made through this client, oldest first
+    snapshots: std::sync::Mutex<VecDeque<BTreeSet<String>>>,
+    /// Set when the context has a tool rate limit
+    rate_limiter: Option<ToolRateLimiter>,
+}
+
+/// Token buckets for the tools that aren't read-only, one per tool, so a loop repeatedly
+/// calling e.g. manage_extensions is stopped without holding up the other tools
+struct ToolRateLimiter {
+    limit: ToolRateLimit,
+    limited_tools: HashSet<String>,
+    buckets: std::sync::Mutex<HashMap<String, TokenBucket>>,
+}
+
+struct TokenBucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl ToolRateLimiter {
+    fn new(limit: ToolRateLimit) -> Self {
+        let limited_tools = tool_schemas()
+            .into_iter()
+            .filter(|tool| {
+                tool.annotations
+                    .as_ref()
+                    .and_then(|annotations| annotations.read_only_hint)
+                    != Some(true)
+            })
+            .map(|tool| tool.name.to_string())
+            .collect();
+        Self {
+            limit,
+            limited_tools,
+            buckets: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a token for a call to `tool_name`, or say how long until one is available
+    fn try_acquire(&self, tool_name: &str) -> Result<(), std::time::Duration> {
+        if !self.limited_tools.contains(tool_name) {
+            return Ok(());
+        }
+        // A zero burst or rate would lock the tool out for the rest of the session
+        let burst = f64::from(self.limit.burst.max(1));
+        let per_second = f64::from(self.limit.per_minute.max(1)) / 60.0;
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().expect("rate limiter poisoned");
+        let bucket = buckets
+            .entry(tool_name.to_string())
+            .or_insert_with(|| TokenBucket {
+                tokens: burst,
+                refilled_at: now,
+            });
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * per_second).min(burst);
+        bucket.refilled_at = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(std::time::Duration::from_secs_f64(
+            (1.0 - bucket.tokens) / per_second,
+        ))
+    }
 }
 
 impl ExtensionManagerClient {
@@ -87,11 +1020,11 @@ impl ExtensionManagerClient {
             capabilities: ServerCapabilities {
                 tasks: None,
                 tools: Some(ToolsCapability {
-                    list_changed: Some(false),
+                    list_changed: Some(true),
                 }),
                 resources: None,
                 prompts: None,
-                completions: None,
+                completions: Some(JsonObject::new()),
                 experimental: None,
                 logging: None,
             },
@@ -109,27 +1042,111 @@ impl ExtensionManagerClient {
 
                 Available tools:
                 - search_available_extensions: Find extensions available to enable/disable
+                - list_enabled_extensions: See which extensions are already enabled, with versions and tool counts
                 - manage_extensions: Enable or disable extensions
+                - reload_changed_extensions: Restart only the extensions whose stored config was edited
+                - validate_configs: Check extension configs for problems without enabling them
+                - fetch_result: Retrieve a tool result that was stored as a reference
+                - save_tool_baseline: Save the current tool set under a name
+                - diff_tool_baseline: Compare the current tool set against a saved baseline
+                - list_extension_errors: List the kinds of errors an extension has produced
+                - cancel_extension_operations: Abort all running calls to an extension
+                - set_extension_alias: Show an enabled extension, and prefix its tools, under another name
+                - get_capabilities: Show which MCP capabilities each extension and the session support
+                - preview_enable_impact: See which tools an extension would add before enabling it
+                - idle_extensions: List enabled extensions that haven't been used recently
+                - ping_extension: Check whether an extension is reachable and how fast it responds
+                - get_extension_instructions: Re-read the usage instructions an extension provided
+                - describe_tool: Get the full schema and annotations of one extension tool
+                - set_tool_budget: Cap the total number of tool calls in this session
+                - get_tool_budget: Show the session's tool call budget and how much is used
+                - generate_support_bundle: Dump extension state, with secrets redacted, for a bug report
+                - get_extension_status: Report each enabled extension's connection state, protocol version, capabilities (including resource subscriptions), and tool count as JSON
+                - disable_all_extensions: Disable every enabled extension except the built-in platform ones
+                - restore_extensions: Undo the last change to the enabled extensions
+                - get_extension_audit_log: Show which extensions were enabled or disabled in this session, and why
                 - list_resources: List resources from extensions
                 - read_resource: Read specific resources from extensions
+                - read_multiple_resources: Read several resources in one call
+                - prefetch_resources: Load resources you will read soon in the background
+                - expand_resource_template: Turn a resource template from list_resources into a URI to read
+                - watch_resource: Get notified when a resource changes
+                - write_resource: Change a resource in an extension that supports writes
+                - resource_hash: Check whether a resource changed without reading its content
+                - resource_catalog: Get a deduplicated inventory of every resource with counts and sizes
 
                 When you lack the tools needed to complete a task, use search_available_extensions first
                 to discover what extensions can help.
 
                 Use manage_extensions to enable or disable specific extensions by name.
                 Use list_resources and read_resource to work with extension data and resources.
+
+                When a tool result is large and only needs to be passed along later, add
+                "store_result_as_reference": true to that tool's arguments. The result is kept for
+                this session and a short handle is returned instead; use fetch_result to redeem it.
+
+                Calls to the same extension run one at a time. To order waiting calls, add
+                "tool_call_priority" to a tool's arguments: "interactive" (runs first), "normal"
+                (the default), or "background" (runs last). Priority only orders calls waiting
+                on the same extension; it never moves a call ahead of calls to other extensions.
             "#}.to_string()),
         };
 
-        Ok(Self { info, context })
+        let rate_limiter = context.tool_rate_limit.map(ToolRateLimiter::new);
+        Ok(Self {
+            info,
+            context,
+            snapshots: std::sync::Mutex::new(VecDeque::new()),
+            rate_limiter,
+        })
+    }
+
+    async fn enabled_extension_names(&self) -> BTreeSet<String> {
+        match self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+        {
+            Some(extension_manager) => extension_manager
+                .get_extension_configs()
+                .await
+                .into_iter()
+                .map(|config| config.name())
+                .collect(),
+            None => BTreeSet::new(),
+        }
+    }
+
+    /// Remember `before` as an undo point if the enabled extensions have changed since
+    async fn record_snapshot(&self, before: BTreeSet<String>) {
+        if self.enabled_extension_names().await == before {
+            return;
+        }
+        let mut snapshots = self.snapshots.lock().expect("snapshots poisoned");
+        if snapshots.len() == MAX_EXTENSION_SNAPSHOTS {
+            snapshots.pop_front();
+        }
+        snapshots.push_back(before);
     }
 
     async fn handle_search_available_extensions(
         &self,
+        arguments: Option<JsonObject>,
     ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let params: SearchAvailableExtensionsParams = match arguments {
+            Some(arguments) => parse_params(SEARCH_AVAILABLE_EXTENSIONS_TOOL_NAME, arguments)?,
+            None => SearchAvailableExtensionsParams::default(),
+        };
         if let Some(weak_ref) = &self.context.extension_manager {
             if let Some(extension_manager) = weak_ref.upgrade() {
-                match extension_manager.search_available_extensions().await {
+                match extension_manager
+                    .search_available_extensions(
+                        params.query.as_deref(),
+                        params.category.as_deref(),
+                    )
+                    .await
+                {
                     Ok(content) => Ok(content),
                     Err(e) => Err(ExtensionManagerToolError::OperationFailed {
                         message: format!("Failed to search available extensions: {}", e.message),
@@ -143,23 +1160,90 @@ impl ExtensionManagerClient {
         }
     }
 
-    async fn handle_manage_extensions(
+    async fn handle_list_enabled_extensions(
         &self,
-        arguments: Option<JsonObject>,
+        session_id: &str,
     ) -> Result<Vec<Content>, ExtensionManagerToolError> {
-        let arguments = arguments.ok_or(ExtensionManagerToolError::MissingParameter {
-            param_name: "arguments".to_string(),
-        })?;
-
-        let params: ManageExtensionsParams =
-            serde_json::from_value(serde_json::Value::Object(arguments))?;
-
-        match self
-            .manage_extensions_impl(params.action, params.extension_name)
+        let extension_manager = self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or(ExtensionManagerToolError::ManagerUnavailable)?;
+
+        let statuses = extension_manager.extension_statuses().await;
+        if statuses.is_empty() {
+            return Ok(vec![Content::text("No extensions are enabled")]);
+        }
+        let instructions: BTreeMap<String, String> = extension_manager
+            .get_extensions_info()
             .await
-        {
-            Ok(content) => Ok(content),
-            Err(error_data) => Err(ExtensionManagerToolError::OperationFailed {
+            .into_iter()
+            .map(|info| (info.name, info.instructions))
+            .collect();
+
+        let mut text = String::from("Enabled extensions:");
+        for status in statuses {
+            let tool_count = extension_manager
+                .get_prefixed_tools(session_id, Some(status.name.clone()))
+                .await
+                .map(|tools| tools.len())
+                .map_err(|e| ExtensionManagerToolError::OperationFailed {
+                    message: format!("Failed to list tools for {}: {}", status.name, e),
+                })?;
+            text.push_str(&format!(
+                "\n- {} (version {}, protocol {}, {} tool(s))",
+                status.name,
+                status.server_version.as_deref().unwrap_or("unknown"),
+                status.protocol_version.as_deref().unwrap_or("unknown"),
+                tool_count
+            ));
+            if status.protocol_outdated {
+                text.push_str(
+                    "\n  Warning: this extension negotiated an outdated protocol version and \
+                     may lack features such as resources",
+                );
+            }
+            if let Some(instructions) = instructions
+                .get(&status.name)
+                .filter(|instructions| !instructions.trim().is_empty())
+            {
+                text.push_str(&format!("\n  Instructions: {}", instructions.trim()));
+            }
+        }
+        Ok(vec![Content::text(text)])
+    }
+
+    fn notify_tool_list_changed(&self) {
+        // No subscribers is fine; nobody needs to refresh
+        let _ = self
+            .context
+            .notifications
+            .send(ServerNotification::ToolListChangedNotification(
+                ToolListChangedNotification::default(),
+            ));
+    }
+
+    async fn handle_manage_extensions(
+        &self,
+        session_id: &str,
+        arguments: Option<JsonObject>,
+    ) -> Result<CallToolResult, ExtensionManagerToolError> {
+        let arguments = arguments.ok_or(ExtensionManagerToolError::MissingParameter {
+            param_name: "arguments".to_string(),
+        })?;
+
+        let params: ManageExtensionsParams = parse_params(MANAGE_EXTENSIONS_TOOL_NAME, arguments)?;
+
+        let before = self.enabled_extension_names().await;
+        let result = self.manage_extensions_impl(session_id, params).await;
+        // Enabling can also turn on dependencies, so compare whole sets rather than names
+        self.record_snapshot(before).await;
+
+        match result {
+            Ok(result) => Ok(result),
+            Err(error_data) => Err(ExtensionManagerToolError::CodedFailure {
+                code: error_data.code,
                 message: error_data.message.to_string(),
             }),
         }
@@ -167,9 +1251,27 @@ impl ExtensionManagerClient {
 
     async fn manage_extensions_impl(
         &self,
-        action: ManageExtensionAction,
-        extension_name: String,
-    ) -> Result<Vec<Content>, ErrorData> {
+        session_id: &str,
+        params: ManageExtensionsParams,
+    ) -> Result<CallToolResult, ErrorData> {
+        let ManageExtensionsParams {
+            action,
+            extension_name,
+            init_timeout_ms,
+            max_tools,
+            retries,
+            allow_tools,
+            deny_tools,
+            reason,
+        } = params;
+        if max_tools == Some(0) {
+            return Err(ErrorData::new(
+                ErrorCode::INVALID_PARAMS,
+                "max_tools must be at least 1".to_string(),
+                None,
+            ));
+        }
+
         let extension_manager = self
             .context
             .extension_manager
@@ -183,314 +1285,3575 @@ impl ExtensionManagerClient {
                 )
             })?;
 
+        // Accept other casings and configured aliases, but report the configured name
+        let extension_name = get_extension_by_name(&extension_name)
+            .map(|config| config.name())
+            .unwrap_or(extension_name);
+
+        let enabled = extension_manager
+            .is_extension_enabled(&extension_name)
+            .await;
+        if action == ManageExtensionAction::Enable && enabled {
+            return Ok(manage_extensions_result(
+                &action,
+                &extension_name,
+                &[],
+                vec![Content::text(format!(
+                    "The extension '{}' is already enabled",
+                    extension_name
+                ))],
+            ));
+        }
+        if action == ManageExtensionAction::Disable && !enabled {
+            return Ok(manage_extensions_result(
+                &action,
+                &extension_name,
+                &[],
+                vec![Content::text(format!(
+                    "The extension '{}' is already disabled",
+                    extension_name
+                ))],
+            ));
+        }
+
         if action == ManageExtensionAction::Disable {
+            let key = extension_manager.resolve_extension_name(&extension_name);
+            let mut dependents = Vec::new();
+            for entry in get_all_extensions() {
+                let depends = entry
+                    .depends_on
+                    .iter()
+                    .any(|dependency| name_to_key(dependency) == key);
+                if depends
+                    && extension_manager
+                        .is_extension_enabled(&entry.config.name())
+                        .await
+                {
+                    dependents.push(entry.config.name());
+                }
+            }
+
             return extension_manager
                 .remove_extension(&extension_name)
                 .await
                 .map(|_| {
-                    vec![Content::text(format!(
+                    self.notify_tool_list_changed();
+                    self.audit(session_id, action.clone(), &extension_name, reason);
+                    let mut content = vec![Content::text(format!(
                         "The extension '{}' has been disabled successfully",
                         extension_name
-                    ))]
+                    ))];
+                    if !dependents.is_empty() {
+                        let (is, depends) = if dependents.len() == 1 {
+                            ("is", "depends")
+                        } else {
+                            ("are", "depend")
+                        };
+                        content.push(Content::text(format!(
+                            "Warning: {} {} still enabled and {} on '{}', and may stop working",
+                            dependents.join(", "),
+                            is,
+                            depends,
+                            extension_name
+                        )));
+                    }
+                    manage_extensions_result(&action, &extension_name, &[], content)
                 })
                 .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None));
         }
 
-        let config = match get_extension_by_name(&extension_name) {
+        let config = match get_extension_by_name(&extension_name).or_else(|| {
+            get_extension_by_name(&extension_manager.resolve_extension_name(&extension_name))
+        }) {
             Some(config) => config,
             None => {
+                let mut candidates: Vec<String> = get_all_extensions()
+                    .into_iter()
+                    .map(|entry| entry.config.name())
+                    .collect();
+                candidates.sort();
+                // Only suggest; enabling a guessed extension could turn on the wrong thing
+                let suggestion = match closest_extension_name(&extension_name, &candidates) {
+                    Some(closest) => format!(" Did you mean '{}'?", closest),
+                    None => String::new(),
+                };
                 return Err(ErrorData::new(
                     ErrorCode::RESOURCE_NOT_FOUND,
                     format!(
-                        "Extension '{}' not found. Please check the extension name and try again.",
-                        extension_name
+                        "Extension '{}' not found.{} Known extensions: {}",
+                        extension_name,
+                        suggestion,
+                        candidates.join(", ")
                     ),
                     None,
                 ));
             }
         };
 
-        extension_manager
-            .add_extension(config, None, None, None)
-            .await
-            .map(|_| {
-                vec![Content::text(format!(
-                    "The extension '{}' has been installed successfully",
-                    extension_name
-                ))]
-            })
-            .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))
-    }
+        let dependencies: HashMap<String, Vec<String>> = get_all_extensions()
+            .into_iter()
+            .map(|entry| (name_to_key(&entry.config.name()), entry.depends_on))
+            .collect();
+        let chain = dependency_chain(&config.name(), &|name| {
+            dependencies
+                .get(&name_to_key(name))
+                .cloned()
+                .unwrap_or_default()
+        })
+        .map_err(|cycle| {
+            ErrorData::new(
+                ErrorCode::INVALID_PARAMS,
+                format!(
+                    "The extension '{}' can't be enabled because its dependencies form a cycle: {}",
+                    extension_name, cycle
+                ),
+                None,
+            )
+        })?;
 
-    async fn handle_list_resources(
-        &self,
-        session_id: &str,
-        arguments: Option<JsonObject>,
-    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
-        if let Some(weak_ref) = &self.context.extension_manager {
-            if let Some(extension_manager) = weak_ref.upgrade() {
-                let params = arguments
-                    .map(serde_json::Value::Object)
-                    .unwrap_or(serde_json::Value::Object(serde_json::Map::new()));
+        // Dependencies come first in the chain and the requested extension last
+        let started = Instant::now();
+        let tools_before = self
+            .prefixed_tool_names(&extension_manager, session_id)
+            .await;
+        let mut activated = Vec::new();
+        let outcome = async {
+            for dependency in &chain[..chain.len() - 1] {
+                if extension_manager.is_extension_enabled(dependency).await {
+                    continue;
+                }
+                let dependency_config = get_extension_by_name(dependency).ok_or_else(|| {
+                    ErrorData::new(
+                        ErrorCode::RESOURCE_NOT_FOUND,
+                        format!(
+                            "The extension '{}' depends on '{}', which is not configured",
+                            extension_name, dependency
+                        ),
+                        None,
+                    )
+                })?;
+                self.enable_extension(
+                    &extension_manager,
+                    dependency_config,
+                    init_timeout_ms,
+                    retries,
+                )
+                .await
+                .map_err(|e| {
+                    ErrorData::new(
+                        e.code,
+                        format!(
+                            "Could not enable '{}', which '{}' depends on: {}",
+                            dependency, extension_name, e.message
+                        ),
+                        e.data,
+                    )
+                })?;
+                self.notify_tool_list_changed();
+                activated.push(dependency.clone());
+            }
 
+            self.enable_extension(&extension_manager, config, init_timeout_ms, retries)
+                .await?;
+            let hidden = if allow_tools.is_some() || !deny_tools.is_empty() {
                 match extension_manager
-                    .list_resources(
+                    .filter_extension_tools(
                         session_id,
-                        params,
-                        tokio_util::sync::CancellationToken::default(),
+                        &extension_name,
+                        allow_tools.as_deref(),
+                        &deny_tools,
                     )
                     .await
                 {
-                    Ok(content) => Ok(content),
-                    Err(e) => Err(ExtensionManagerToolError::OperationFailed {
-                        message: format!("Failed to list resources: {}", e.message),
-                    }),
+                    Ok(hidden) => hidden,
+                    Err(e) => {
+                        // Don't leave the extension running with tools the caller wanted hidden
+                        let _ = extension_manager.remove_extension(&extension_name).await;
+                        self.notify_tool_list_changed();
+                        return Err(e);
+                    }
                 }
             } else {
-                Err(ExtensionManagerToolError::ManagerUnavailable)
+                Vec::new()
+            };
+            let partial = match max_tools {
+                Some(max_tools) => match extension_manager
+                    .limit_extension_tools(session_id, &extension_name, max_tools)
+                    .await
+                {
+                    Ok(total) => total.map(|total| (max_tools, total)),
+                    Err(e) => {
+                        // Don't leave the extension running with more tools than asked for
+                        let _ = extension_manager.remove_extension(&extension_name).await;
+                        self.notify_tool_list_changed();
+                        return Err(e);
+                    }
+                },
+                None => None,
+            };
+            Ok::<_, ErrorData>((hidden, partial))
+        }
+        .await;
+        let (hidden, partial) = match outcome {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                return Err(self
+                    .roll_back_dependencies(&extension_manager, &activated, e)
+                    .await)
             }
-        } else {
-            Err(ExtensionManagerToolError::ManagerUnavailable)
+        };
+        self.notify_tool_list_changed();
+        for dependency in &activated {
+            self.audit(
+                session_id,
+                action.clone(),
+                dependency,
+                Some(format!("Dependency of '{}'", extension_name)),
+            );
         }
+        self.audit(session_id, action, &extension_name, reason);
+        let load_time = started.elapsed();
+        let added_tools: Vec<String> = self
+            .prefixed_tool_names(&extension_manager, session_id)
+            .await
+            .difference(&tools_before)
+            .cloned()
+            .collect();
+
+        let mut content = Vec::new();
+        if !hidden.is_empty() {
+            content.push(Content::text(format!(
+                "Hidden by allow_tools/deny_tools: {}",
+                hidden.join(", ")
+            )));
+        }
+        if let Some((loaded, total)) = partial {
+            content.push(Content::text(format!(
+                "Partially loaded '{}': {} of {} tools are available. \
+                 Disable and re-enable it without max_tools to load the rest.",
+                extension_name, loaded, total
+            )));
+        }
+        if !activated.is_empty() {
+            content.push(Content::text(format!(
+                "Enabled its dependencies first: {} -> {}",
+                activated.join(" -> "),
+                extension_name
+            )));
+        }
+        Ok(enabled_result(
+            &extension_name,
+            load_time,
+            added_tools,
+            content,
+        ))
     }
 
-    async fn handle_read_resource(
+    /// Disable the dependencies a failed enable turned on, newest first, and name them in
+    /// the error so the caller knows they were touched
+    async fn roll_back_dependencies(
+        &self,
+        extension_manager: &ExtensionManager,
+        activated: &[String],
+        mut error: ErrorData,
+    ) -> ErrorData {
+        if activated.is_empty() {
+            return error;
+        }
+        for dependency in activated.iter().rev() {
+            let _ = extension_manager.remove_extension(dependency).await;
+        }
+        self.notify_tool_list_changed();
+        error.message = format!(
+            "{} (disabled the dependencies it had enabled: {})",
+            error.message,
+            activated.join(", ")
+        )
+        .into();
+        error
+    }
+
+    /// Log a completed enable or disable and add it to the session's audit log
+    fn audit(
         &self,
         session_id: &str,
-        arguments: Option<JsonObject>,
-    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
-        if let Some(weak_ref) = &self.context.extension_manager {
-            if let Some(extension_manager) = weak_ref.upgrade() {
-                let params = arguments
-                    .map(serde_json::Value::Object)
-                    .unwrap_or(serde_json::Value::Object(serde_json::Map::new()));
+        action: ManageExtensionAction,
+        extension_name: &str,
+        reason: Option<String>,
+    ) {
+        info!(
+            session_id,
+            action = ?action,
+            extension = extension_name,
+            reason = reason.as_deref().unwrap_or(""),
+            "Extension reconfigured"
+        );
+        self.context
+            .extension_audit_log
+            .record(crate::agents::extension::ExtensionAuditEntry {
+                session_id: session_id.to_string(),
+                timestamp: chrono::Utc::now(),
+                action,
+                extension_name: extension_name.to_string(),
+                reason,
+            });
+    }
+
+    /// Every tool the agent can currently call, by prefixed name. A failed listing counts as
+    /// none, so the tools reported after enabling may include ones that were already there.
+    async fn prefixed_tool_names(
+        &self,
+        extension_manager: &ExtensionManager,
+        session_id: &str,
+    ) -> BTreeSet<String> {
+        extension_manager
+            .get_prefixed_tools(session_id, None)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|tool| tool.name.to_string())
+            .collect()
+    }
+
+    async fn enable_extension(
+        &self,
+        extension_manager: &Arc<ExtensionManager>,
+        config: ExtensionConfig,
+        init_timeout_ms: Option<u64>,
+        retries: Option<u32>,
+    ) -> Result<(), ErrorData> {
+        let extension_name = config.name();
+
+        // Connect with the placeholders resolved but keep them in the config that is stored.
+        // Name the missing variables but never echo resolved values, which may be secrets.
+        let config_variables = &self.context.config_variables;
+        let mut resolved = config.clone();
+        resolved
+            .interpolate_variables(|name| config_variables.resolve(name))
+            .map_err(|unresolved| {
+                ErrorData::new(
+                    ErrorCode::INVALID_PARAMS,
+                    format!(
+                        "The extension '{}' uses undefined variables: {}. Set them in the \
+                         environment or GOOSE_EXTENSION_VARIABLES before enabling it.",
+                        extension_name,
+                        unresolved.join(", ")
+                    ),
+                    None,
+                )
+            })?;
 
+        // Catch config mistakes here so the agent gets a specific message instead of a
+        // generic failure after the extension fails to start
+        validate_extension_config(&resolved).map_err(|e| {
+            ErrorData::new(
+                ErrorCode::INVALID_PARAMS,
+                format!("The extension '{}' has an {}", extension_name, e),
+                None,
+            )
+        })?;
+
+        let retries = retries
+            .unwrap_or(DEFAULT_ENABLE_RETRIES)
+            .min(MAX_ENABLE_RETRIES);
+        let enable = async {
+            let mut attempt = 0;
+            loop {
                 match extension_manager
-                    .read_resource_tool(
-                        session_id,
-                        params,
-                        tokio_util::sync::CancellationToken::default(),
+                    .add_extension_with_transport(
+                        config.clone(),
+                        resolved.clone(),
+                        None,
+                        None,
+                        None,
                     )
                     .await
                 {
-                    Ok(content) => Ok(content),
-                    Err(e) => Err(ExtensionManagerToolError::OperationFailed {
-                        message: format!("Failed to read resource: {}", e.message),
-                    }),
+                    Ok(()) => return Ok(()),
+                    Err(e) if attempt < retries && is_retryable_enable_error(&config, &e) => {
+                        let delay_ms = ENABLE_RETRY_BASE_DELAY_MS << attempt;
+                        attempt += 1;
+                        warn!(
+                            extension = %extension_name,
+                            attempt,
+                            retries,
+                            delay_ms,
+                            error = %e,
+                            "Failed to enable extension, retrying"
+                        );
+                        tokio::time::sleep(std::time::Duration::from_millis(delay_ms)).await;
+                    }
+                    Err(e) => return Err(e),
                 }
-            } else {
-                Err(ExtensionManagerToolError::ManagerUnavailable)
             }
-        } else {
-            Err(ExtensionManagerToolError::ManagerUnavailable)
-        }
+        };
+        let result = match init_timeout_ms {
+            Some(requested_ms) => {
+                let max_ms = Config::global()
+                    .get_param::<u64>("GOOSE_MAX_EXTENSION_INIT_TIMEOUT_MS")
+                    .unwrap_or(DEFAULT_MAX_INIT_TIMEOUT_MS);
+                let timeout_ms = requested_ms.min(max_ms);
+                tokio::time::timeout(std::time::Duration::from_millis(timeout_ms), enable)
+                    .await
+                    .map_err(|_| {
+                        ErrorData::new(
+                            ErrorCode::INTERNAL_ERROR,
+                            format!(
+                                "The extension '{}' did not start within {} ms and was not enabled",
+                                extension_name, timeout_ms
+                            ),
+                            None,
+                        )
+                    })?
+            }
+            None => enable.await,
+        };
+
+        result.map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))
     }
 
-    #[allow(clippy::too_many_lines)]
-    async fn get_tools(&self) -> Vec<Tool> {
-        let mut tools = vec![
-            Tool::new(
-                SEARCH_AVAILABLE_EXTENSIONS_TOOL_NAME.to_string(),
-                "Searches for additional extensions available to help complete tasks.
-        Use this tool when you're unable to find a specific feature or functionality you need to complete your task, or when standard approaches aren't working.
-        These extensions might provide the exact tools needed to solve your problem.
-        If you find a relevant one, consider using your tools to enable it.".to_string(),
-                Arc::new(
-                    serde_json::json!({
-                        "type": "object",
-                        "required": [],
-                        "properties": {}
-                    })
-                    .as_object()
-                    .expect("Schema must be an object")
-                    .clone()
-                ),
-            ).annotate(ToolAnnotations {
-                title: Some("Discover extensions".to_string()),
-                read_only_hint: Some(true),
-                destructive_hint: Some(false),
-                idempotent_hint: Some(false),
-                open_world_hint: Some(false),
-            }),
-            Tool::new(
-                MANAGE_EXTENSIONS_TOOL_NAME.to_string(),
-                "Tool to manage extensions and tools in goose context.
-            Enable or disable extensions to help complete tasks.
-            Enable or disable an extension by providing the extension name.
-            ".to_string(),
-                Arc::new(
-                    serde_json::to_value(schema_for!(ManageExtensionsParams))
-                        .expect("Failed to serialize schema")
-                        .as_object()
-                        .expect("Schema must be an object")
-                        .clone()
-                ),
-            ).annotate(ToolAnnotations {
-                title: Some("Enable or disable an extension".to_string()),
-                read_only_hint: Some(false),
-                destructive_hint: Some(false),
-                idempotent_hint: Some(false),
-                open_world_hint: Some(false),
-            }),
-        ];
+    async fn handle_reload_changed_extensions(
+        &self,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let extension_manager = self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or(ExtensionManagerToolError::ManagerUnavailable)?;
 
-        // Only add resource tools if extension manager supports resources
-        if let Some(weak_ref) = &self.context.extension_manager {
-            if let Some(extension_manager) = weak_ref.upgrade() {
-                if extension_manager.supports_resources().await {
-                    tools.extend([
-                        Tool::new(
-                            LIST_RESOURCES_TOOL_NAME.to_string(),
-                            indoc! {r#"
-            List resources from an extension(s).
+        let mut running = extension_manager.get_extension_configs().await;
+        running.sort_by_key(|config| config.key());
 
-            Resources allow extensions to share data that provide context to LLMs, such as
-            files, database schemas, or application-specific information. This tool lists resources
-            in the provided extension, and returns a list for the user to browse. If no extension
-            is provided, the tool will search all extensions for the resource.
-        "#}.to_string(),
-                            Arc::new(
-                                serde_json::to_value(schema_for!(ListResourcesParams))
-                                    .expect("Failed to serialize schema")
-                                    .as_object()
-                                    .expect("Schema must be an object")
-                                    .clone()
-                            ),
-                        ).annotate(ToolAnnotations {
-                            title: Some("List resources".to_string()),
-                            read_only_hint: Some(true),
-                            destructive_hint: Some(false),
-                            idempotent_hint: Some(false),
-                            open_world_hint: Some(false),
-                        }),
-                        Tool::new(
-                            READ_RESOURCE_TOOL_NAME.to_string(),
-                            indoc! {r#"
-            Read a resource from an extension.
+        let mut reloaded = Vec::new();
+        let mut unchanged = Vec::new();
+        let mut not_stored = Vec::new();
+        let mut failed = Vec::new();
+        for config in running {
+            let name = config.name();
+            let Some(stored) = get_extension_by_name(&name) else {
+                not_stored.push(name);
+                continue;
+            };
+            if stored == config {
+                unchanged.push(name);
+                continue;
+            }
 
-            Resources allow extensions to share data that provide context to LLMs, such as
-            files, database schemas, or application-specific information. This tool searches for the
-            resource URI in the provided extension, and reads in the resource content. If no extension
-            is provided, the tool will search all extensions for the resource.
-        "#}.to_string(),
-                            Arc::new(
-                                serde_json::to_value(schema_for!(ReadResourceParams))
-                                    .expect("Failed to serialize schema")
-                                    .as_object()
-                                    .expect("Schema must be an object")
-                                    .clone()
-                            ),
-                        ).annotate(ToolAnnotations {
-                            title: Some("Read a resource".to_string()),
-                            read_only_hint: Some(true),
-                            destructive_hint: Some(false),
-                            idempotent_hint: Some(false),
-                            open_world_hint: Some(false),
-                        }),
-                    ]);
+            // Stored configs are resolved the same way as when they are enabled
+            let config_variables = &self.context.config_variables;
+            let mut resolved = stored.clone();
+            if let Err(unresolved) =
+                resolved.interpolate_variables(|name| config_variables.resolve(name))
+            {
+                failed.push(format!(
+                    "{} (undefined variables: {})",
+                    name,
+                    unresolved.join(", ")
+                ));
+                continue;
+            }
+
+            let result = match extension_manager.remove_extension(&config.key()).await {
+                Ok(()) => {
+                    extension_manager
+                        .add_extension_with_transport(stored, resolved, None, None, None)
+                        .await
                 }
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) => reloaded.push(name),
+                Err(e) => failed.push(format!("{} ({})", name, e)),
             }
         }
 
-        tools
+        let list = |names: &[String]| {
+            if names.is_empty() {
+                "none".to_string()
+            } else {
+                names.join(", ")
+            }
+        };
+        let mut text = format!(
+            "Reloaded: {}\nUnchanged: {}",
+            list(&reloaded),
+            list(&unchanged)
+        );
+        if !not_stored.is_empty() {
+            text.push_str(&format!(
+                "\nNot in the stored config, left running: {}",
+                not_stored.join(", ")
+            ));
+        }
+        if !failed.is_empty() {
+            text.push_str(&format!(
+                "\nFailed to restart, now disabled: {}",
+                failed.join(", ")
+            ));
+        }
+        Ok(vec![Content::text(text)])
     }
-}
 
-#[async_trait]
-impl McpClientTrait for ExtensionManagerClient {
-    async fn list_resources(
+    fn handle_validate_configs(
         &self,
-        _session_id: &str,
-        _next_cursor: Option<String>,
-        _cancellation_token: CancellationToken,
-    ) -> Result<ListResourcesResult, Error> {
-        Err(Error::TransportClosed)
-    }
+        arguments: Option<JsonObject>,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let arguments = arguments.ok_or(ExtensionManagerToolError::MissingParameter {
+            param_name: "configs".to_string(),
+        })?;
 
-    async fn read_resource(
-        &self,
-        _session_id: &str,
-        _uri: &str,
-        _cancellation_token: CancellationToken,
-    ) -> Result<ReadResourceResult, Error> {
-        // Extension manager doesn't expose resources directly
-        Err(Error::TransportClosed)
-    }
+        let params: ValidateConfigsParams = parse_params(VALIDATE_CONFIGS_TOOL_NAME, arguments)?;
 
-    async fn list_tools(
-        &self,
-        _session_id: &str,
-        _next_cursor: Option<String>,
-        _cancellation_token: CancellationToken,
-    ) -> Result<ListToolsResult, Error> {
-        Ok(ListToolsResult {
-            tools: self.get_tools().await,
-            next_cursor: None,
-            meta: None,
-        })
+        let report = params
+            .configs
+            .into_iter()
+            .enumerate()
+            .map(|(index, raw)| {
+                let label = raw
+                    .get("name")
+                    .and_then(|v| v.as_str())
+                    .map(|name| format!("{} ({})", index + 1, name))
+                    .unwrap_or_else(|| (index + 1).to_string());
+                let problem = match serde_json::from_value::<ExtensionConfig>(raw) {
+                    Ok(config) => validate_extension_config(&config)
+                        .err()
+                        .map(|e| e.to_string()),
+                    Err(e) => Some(format!("could not parse config: {}", e)),
+                };
+                match problem {
+                    None => format!("- {}: valid", label),
+                    Some(problem) => format!("- {}: {}", label, problem),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if report.is_empty() {
+            return Ok(vec![Content::text("No configs provided.")]);
+        }
+
+        Ok(vec![Content::text(format!(
+            "Validation report:\n{}",
+            report.join("\n")
+        ))])
     }
 
-    async fn call_tool(
+    async fn handle_fetch_result(
         &self,
         session_id: &str,
-        name: &str,
         arguments: Option<JsonObject>,
-        _working_dir: Option<&str>,
-        _cancellation_token: CancellationToken,
-    ) -> Result<CallToolResult, Error> {
-        let result = match name {
-            SEARCH_AVAILABLE_EXTENSIONS_TOOL_NAME => {
-                self.handle_search_available_extensions().await
-            }
-            MANAGE_EXTENSIONS_TOOL_NAME => self.handle_manage_extensions(arguments).await,
-            LIST_RESOURCES_TOOL_NAME => self.handle_list_resources(session_id, arguments).await,
-            READ_RESOURCE_TOOL_NAME => self.handle_read_resource(session_id, arguments).await,
-            _ => Err(ExtensionManagerToolError::UnknownTool {
-                tool_name: name.to_string(),
-            }),
-        };
+    ) -> Result<CallToolResult, ExtensionManagerToolError> {
+        let arguments = arguments.ok_or(ExtensionManagerToolError::MissingParameter {
+            param_name: "handle".to_string(),
+        })?;
 
-        match result {
-            Ok(content) => Ok(CallToolResult::success(content)),
-            Err(error) => {
-                // Log the error for debugging
-                error!("Extension manager tool '{}' failed: {}", name, error);
+        let params: FetchResultParams = parse_params(FETCH_RESULT_TOOL_NAME, arguments)?;
 
-                // Return proper error result with is_error flag set
-                Ok(CallToolResult {
-                    content: vec![Content::text(error.to_string())],
-                    is_error: Some(true), // ✅ Properly mark as error
-                    structured_content: None,
-                    meta: None,
-                })
-            }
-        }
+        let extension_manager = self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or(ExtensionManagerToolError::ManagerUnavailable)?;
+
+        extension_manager
+            .fetch_stored_result(session_id, &params.handle)
+            .await
+            .map_err(|e| ExtensionManagerToolError::OperationFailed {
+                message: e.message.to_string(),
+            })
     }
 
-    async fn list_prompts(
+    async fn current_tool_baseline(
         &self,
-        _session_id: &str,
-        _next_cursor: Option<String>,
-        _cancellation_token: CancellationToken,
-    ) -> Result<ListPromptsResult, Error> {
-        Err(Error::TransportClosed)
+        session_id: &str,
+    ) -> Result<ToolBaseline, ExtensionManagerToolError> {
+        let extension_manager = self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or(ExtensionManagerToolError::ManagerUnavailable)?;
+
+        let tools = extension_manager
+            .get_prefixed_tools(session_id, None)
+            .await
+            .map_err(|e| ExtensionManagerToolError::OperationFailed {
+                message: format!("Failed to list tools: {}", e),
+            })?;
+
+        Ok(tools
+            .iter()
+            .map(|tool| (tool.name.to_string(), ToolSignature::from_tool(tool)))
+            .collect())
+    }
+
+    async fn handle_save_tool_baseline(
+        &self,
+        session_id: &str,
+        arguments: Option<JsonObject>,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let arguments = arguments.ok_or(ExtensionManagerToolError::MissingParameter {
+            param_name: "name".to_string(),
+        })?;
+        let params: ToolBaselineParams = parse_params(SAVE_TOOL_BASELINE_TOOL_NAME, arguments)?;
+
+        let baseline = self.current_tool_baseline(session_id).await?;
+        let tool_count = baseline.len();
+
+        let config = Config::global();
+        let mut baselines: BTreeMap<String, ToolBaseline> = config
+            .get_param(TOOL_BASELINES_CONFIG_KEY)
+            .unwrap_or_default();
+        baselines.insert(params.name.clone(), baseline);
+        config
+            .set_param(TOOL_BASELINES_CONFIG_KEY, baselines)
+            .map_err(|e| ExtensionManagerToolError::OperationFailed {
+                message: format!("Failed to save tool baseline: {}", e),
+            })?;
+
+        Ok(vec![Content::text(format!(
+            "Saved tool baseline '{}' with {} tools",
+            params.name, tool_count
+        ))])
+    }
+
+    async fn handle_diff_tool_baseline(
+        &self,
+        session_id: &str,
+        arguments: Option<JsonObject>,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let arguments = arguments.ok_or(ExtensionManagerToolError::MissingParameter {
+            param_name: "name".to_string(),
+        })?;
+        let params: ToolBaselineParams = parse_params(DIFF_TOOL_BASELINE_TOOL_NAME, arguments)?;
+
+        let mut baselines: BTreeMap<String, ToolBaseline> = Config::global()
+            .get_param(TOOL_BASELINES_CONFIG_KEY)
+            .unwrap_or_default();
+        let baseline = baselines.remove(&params.name).ok_or_else(|| {
+            ExtensionManagerToolError::OperationFailed {
+                message: format!(
+                    "No tool baseline named '{}'. Save one with save_tool_baseline first.",
+                    params.name
+                ),
+            }
+        })?;
+
+        let current = self.current_tool_baseline(session_id).await?;
+        let diff = diff_tool_baseline(&baseline, &current);
+
+        if diff == ToolBaselineDiff::default() {
+            return Ok(vec![Content::text(format!(
+                "No tool changes relative to baseline '{}'",
+                params.name
+            ))]);
+        }
+
+        let mut output = format!("Tool changes relative to baseline '{}':\n", params.name);
+        if !diff.added.is_empty() {
+            output.push_str(&format!("\nAdded:\n- {}\n", diff.added.join("\n- ")));
+        }
+        if !diff.removed.is_empty() {
+            output.push_str(&format!("\nRemoved:\n- {}\n", diff.removed.join("\n- ")));
+        }
+        if !diff.changed.is_empty() {
+            let changed = diff
+                .changed
+                .iter()
+                .map(|(name, fields)| format!("- {} ({})", name, fields.join(", ")))
+                .collect::<Vec<_>>()
+                .join("\n");
+            output.push_str(&format!("\nChanged:\n{}\n", changed));
+        }
+
+        Ok(vec![Content::text(output)])
+    }
+
+    async fn handle_list_extension_errors(
+        &self,
+        arguments: Option<JsonObject>,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let arguments = arguments.ok_or(ExtensionManagerToolError::MissingParameter {
+            param_name: "extension_name".to_string(),
+        })?;
+        let params: ListExtensionErrorsParams =
+            parse_params(LIST_EXTENSION_ERRORS_TOOL_NAME, arguments)?;
+
+        let extension_manager = self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or(ExtensionManagerToolError::ManagerUnavailable)?;
+
+        let observed = extension_manager
+            .get_observed_errors(&params.extension_name)
+            .await
+            .ok_or_else(|| ExtensionManagerToolError::OperationFailed {
+                message: format!("Extension '{}' is not enabled", params.extension_name),
+            })?;
+
+        if observed.is_empty() {
+            return Ok(vec![Content::text(format!(
+                "Error categories for '{}':\n- unknown: the extension declares no error metadata and none have been observed yet",
+                params.extension_name
+            ))]);
+        }
+
+        let categories = observed
+            .iter()
+            .map(|(category, count)| {
+                format!(
+                    "- {} ({}): {}. Seen {} time(s)",
+                    category.name(),
+                    if category.retryable() {
+                        "retryable"
+                    } else {
+                        "not retryable"
+                    },
+                    category.description(),
+                    count
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Ok(vec![Content::text(format!(
+            "Error categories observed for '{}':\n{}",
+            params.extension_name, categories
+        ))])
+    }
+
+    async fn handle_cancel_extension_operations(
+        &self,
+        arguments: Option<JsonObject>,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let arguments = arguments.ok_or(ExtensionManagerToolError::MissingParameter {
+            param_name: "extension_name".to_string(),
+        })?;
+        let params: CancelExtensionOperationsParams =
+            parse_params(CANCEL_EXTENSION_OPERATIONS_TOOL_NAME, arguments)?;
+
+        let extension_manager = self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or(ExtensionManagerToolError::ManagerUnavailable)?;
+
+        let cancelled = extension_manager
+            .cancel_extension_operations(&params.extension_name)
+            .await
+            .ok_or_else(|| ExtensionManagerToolError::OperationFailed {
+                message: format!("Extension '{}' is not enabled", params.extension_name),
+            })?;
+
+        Ok(vec![Content::text(format!(
+            "Cancelled {} in-flight operation(s) for extension '{}'",
+            cancelled, params.extension_name
+        ))])
+    }
+
+    async fn handle_set_extension_alias(
+        &self,
+        arguments: Option<JsonObject>,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let arguments = arguments.ok_or(ExtensionManagerToolError::MissingParameter {
+            param_name: "extension_name".to_string(),
+        })?;
+        let params: SetExtensionAliasParams =
+            parse_params(SET_EXTENSION_ALIAS_TOOL_NAME, arguments)?;
+
+        let extension_manager = self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or(ExtensionManagerToolError::ManagerUnavailable)?;
+
+        extension_manager
+            .set_extension_alias(&params.extension_name, params.alias.as_deref())
+            .await
+            .map_err(|e| ExtensionManagerToolError::OperationFailed {
+                message: e.to_string(),
+            })?;
+
+        let text = match params.alias {
+            Some(alias) => format!(
+                "The extension '{}' is now shown as '{}' and its tools are prefixed with '{}__'",
+                params.extension_name,
+                alias,
+                name_to_key(&alias)
+            ),
+            None => format!(
+                "The extension '{}' is shown under its own name again",
+                params.extension_name
+            ),
+        };
+        Ok(vec![Content::text(text)])
+    }
+
+    async fn handle_get_capabilities(&self) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let extension_manager = self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or(ExtensionManagerToolError::ManagerUnavailable)?;
+
+        let extensions = extension_manager.get_capabilities().await;
+        let session = extensions
+            .values()
+            .flatten()
+            .fold(CapabilityFlags::default(), |all, flags| all.union(*flags));
+
+        let content = Content::json(serde_json::json!({
+            "session": session,
+            "extensions": extensions,
+        }))
+        .map_err(|e| ExtensionManagerToolError::OperationFailed {
+            message: format!("Failed to serialize capabilities: {}", e.message),
+        })?;
+        Ok(vec![content])
+    }
+
+    async fn handle_resource_catalog(
+        &self,
+        session_id: &str,
+        cancellation_token: CancellationToken,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let extension_manager = self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or(ExtensionManagerToolError::ManagerUnavailable)?;
+
+        let catalog = extension_manager
+            .resource_catalog(session_id, cancellation_token)
+            .await;
+        let content =
+            Content::json(catalog).map_err(|e| ExtensionManagerToolError::OperationFailed {
+                message: format!("Failed to serialize resource catalog: {}", e.message),
+            })?;
+        Ok(vec![content])
+    }
+
+    async fn handle_get_extension_status(
+        &self,
+        session_id: &str,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let extension_manager = self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or(ExtensionManagerToolError::ManagerUnavailable)?;
+
+        let mut report = Vec::new();
+        for status in extension_manager.extension_statuses().await {
+            // A crashed extension can't list its tools; report what's known about it anyway
+            let tool_count = if status.crashed {
+                None
+            } else {
+                extension_manager
+                    .get_prefixed_tools(session_id, Some(status.name.clone()))
+                    .await
+                    .ok()
+                    .map(|tools| tools.len())
+            };
+            report.push(ExtensionHealth {
+                connection: if status.crashed {
+                    "crashed"
+                } else {
+                    "connected"
+                },
+                tool_count,
+                status,
+            });
+        }
+
+        let content = Content::json(serde_json::json!({ "extensions": report })).map_err(|e| {
+            ExtensionManagerToolError::OperationFailed {
+                message: format!("Failed to serialize extension status: {}", e.message),
+            }
+        })?;
+        Ok(vec![content])
+    }
+
+    async fn handle_disable_all_extensions(
+        &self,
+        session_id: &str,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let extension_manager = self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or(ExtensionManagerToolError::ManagerUnavailable)?;
+
+        let mut names: Vec<String> = extension_manager
+            .get_extension_configs()
+            .await
+            .into_iter()
+            // Platform extensions, this one included, are part of goose itself
+            .filter(|config| !matches!(config, ExtensionConfig::Platform { .. }))
+            .map(|config| config.name())
+            .collect();
+        names.sort();
+        drop(extension_manager);
+
+        let before = self.enabled_extension_names().await;
+        let mut disabled = Vec::new();
+        let mut failed = Vec::new();
+        for name in names {
+            // Best effort: keep going so one stuck extension doesn't block the reset
+            match self
+                .manage_extensions_impl(
+                    session_id,
+                    ManageExtensionsParams::new(ManageExtensionAction::Disable, name.clone()),
+                )
+                .await
+            {
+                Ok(_) => disabled.push(name),
+                Err(e) => failed.push(format!("{}: {}", name, e.message)),
+            }
+        }
+        self.record_snapshot(before).await;
+
+        let mut summary = if disabled.is_empty() {
+            "No extensions were disabled.".to_string()
+        } else {
+            format!(
+                "Disabled {} extension(s): {}",
+                disabled.len(),
+                disabled.join(", ")
+            )
+        };
+        if !failed.is_empty() {
+            summary.push_str(&format!(
+                "\nFailed to disable {} extension(s):\n{}",
+                failed.len(),
+                failed.join("\n")
+            ));
+        }
+        Ok(vec![Content::text(summary)])
+    }
+
+    async fn handle_restore_extensions(
+        &self,
+        session_id: &str,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let Some(snapshot) = self
+            .snapshots
+            .lock()
+            .expect("snapshots poisoned")
+            .pop_back()
+        else {
+            return Ok(vec![Content::text(
+                "There are no earlier extension changes to restore".to_string(),
+            )]);
+        };
+
+        let current = self.enabled_extension_names().await;
+        let keys = |names: &BTreeSet<String>| -> BTreeMap<String, String> {
+            names
+                .iter()
+                .map(|name| (name_to_key(name), name.clone()))
+                .collect()
+        };
+        let (current, snapshot) = (keys(&current), keys(&snapshot));
+
+        let mut restored = Vec::new();
+        let mut failed = Vec::new();
+        // Disable first so dependencies of re-enabled extensions aren't turned off again
+        let to_disable = current
+            .iter()
+            .filter(|(key, _)| !snapshot.contains_key(*key))
+            .map(|(_, name)| (ManageExtensionAction::Disable, name));
+        let to_enable = snapshot
+            .iter()
+            .filter(|(key, _)| !current.contains_key(*key))
+            .map(|(_, name)| (ManageExtensionAction::Enable, name));
+        for (action, name) in to_disable.chain(to_enable) {
+            let label = match action {
+                ManageExtensionAction::Enable => "enabled",
+                ManageExtensionAction::Disable => "disabled",
+            };
+            match self
+                .manage_extensions_impl(
+                    session_id,
+                    ManageExtensionsParams::new(action, name.clone()),
+                )
+                .await
+            {
+                Ok(_) => restored.push(format!("{} {}", label, name)),
+                Err(e) => failed.push(format!("{}: {}", name, e.message)),
+            }
+        }
+
+        let mut summary = if restored.is_empty() {
+            "The enabled extensions already match the previous set.".to_string()
+        } else {
+            format!(
+                "Restored the previous extension set: {}",
+                restored.join(", ")
+            )
+        };
+        if !failed.is_empty() {
+            summary.push_str(&format!(
+                "\nCould not restore {} extension(s):\n{}",
+                failed.len(),
+                failed.join("\n")
+            ));
+        }
+        Ok(vec![Content::text(summary)])
+    }
+
+    async fn handle_generate_support_bundle(
+        &self,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let extension_manager = self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or(ExtensionManagerToolError::ManagerUnavailable)?;
+
+        let active = extension_manager.extension_statuses().await;
+        let active_keys: Vec<String> = active.iter().map(|status| status.name.clone()).collect();
+        let disabled: Vec<Value> = get_all_extensions()
+            .into_iter()
+            .filter(|entry| !active_keys.contains(&entry.config.key()))
+            .map(|entry| redacted_config(&entry.config))
+            .collect();
+        let failures: Vec<Value> = active
+            .iter()
+            .filter(|status| status.crashed || status.circuit_open || !status.errors.is_empty())
+            .map(|status| {
+                serde_json::json!({
+                    "extension": status.name,
+                    "crashed": status.crashed,
+                    "circuit_open": status.circuit_open,
+                    "errors": status.errors,
+                })
+            })
+            .collect();
+        let platform: BTreeMap<&str, bool> = PLATFORM_EXTENSIONS
+            .keys()
+            .map(|key| (*key, active_keys.iter().any(|name| name == key)))
+            .collect();
+
+        let content = Content::json(serde_json::json!({
+            "goose_version": env!("CARGO_PKG_VERSION"),
+            "active_extensions": active,
+            "disabled_extensions": disabled,
+            "router_index": "not used: tools are listed directly from each extension",
+            "recent_failures": failures,
+            "platform_extensions": platform,
+        }))
+        .map_err(|e| ExtensionManagerToolError::OperationFailed {
+            message: format!("Failed to serialize support bundle: {}", e.message),
+        })?;
+        Ok(vec![content])
+    }
+
+    async fn handle_preview_enable_impact(
+        &self,
+        session_id: &str,
+        arguments: Option<JsonObject>,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let arguments = arguments.ok_or(ExtensionManagerToolError::MissingParameter {
+            param_name: "extension_name".to_string(),
+        })?;
+        let params: PreviewEnableImpactParams =
+            parse_params(PREVIEW_ENABLE_IMPACT_TOOL_NAME, arguments)?;
+
+        let extension_manager = self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or(ExtensionManagerToolError::ManagerUnavailable)?;
+
+        if extension_manager
+            .is_extension_enabled(&params.extension_name)
+            .await
+        {
+            return Ok(vec![Content::text(format!(
+                "The extension '{}' is already enabled; enabling it again adds no tools",
+                params.extension_name
+            ))]);
+        }
+
+        let config = get_extension_by_name(&params.extension_name).ok_or_else(|| {
+            ExtensionManagerToolError::OperationFailed {
+                message: format!("Extension '{}' not found", params.extension_name),
+            }
+        })?;
+        let new_tools = extension_manager
+            .preview_extension_tools(session_id, config)
+            .await
+            .map_err(|e| ExtensionManagerToolError::OperationFailed {
+                message: format!(
+                    "Failed to start '{}' for preview: {}",
+                    params.extension_name, e
+                ),
+            })?;
+        let active_tools = extension_manager
+            .get_prefixed_tools(session_id, None)
+            .await
+            .map_err(|e| ExtensionManagerToolError::OperationFailed {
+                message: format!("Failed to list active tools: {}", e),
+            })?;
+
+        // Prefixes keep full names unique, but tools with the same unprefixed name in
+        // several extensions are easy for the model to mix up
+        let active_by_name: BTreeMap<&str, &str> = active_tools
+            .iter()
+            .filter_map(|tool| tool.name.split_once("__"))
+            .map(|(extension, name)| (name, extension))
+            .collect();
+        let collisions: Vec<String> = new_tools
+            .iter()
+            .filter_map(|tool| tool.name.split_once("__"))
+            .filter_map(|(_, name)| {
+                active_by_name
+                    .get(name)
+                    .map(|extension| format!("- {} (also in {})", name, extension))
+            })
+            .collect();
+
+        let mut text = format!(
+            "Enabling '{}' would add {} tool(s):\n{}",
+            params.extension_name,
+            new_tools.len(),
+            new_tools
+                .iter()
+                .map(|tool| format!("- {}", tool.name))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+        if collisions.is_empty() {
+            text.push_str("\n\nNo name collisions with active tools.");
+        } else {
+            text.push_str(&format!(
+                "\n\nSame names as active tools:\n{}",
+                collisions.join("\n")
+            ));
+        }
+        if let Ok(counter) = create_token_counter().await {
+            text.push_str(&format!(
+                "\n\nThe tool definitions add about {} tokens to every request.",
+                counter.count_tokens_for_tools(&new_tools)
+            ));
+        }
+        Ok(vec![Content::text(text)])
+    }
+
+    async fn handle_idle_extensions(
+        &self,
+        session_id: &str,
+        arguments: Option<JsonObject>,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let params: IdleExtensionsParams =
+            parse_params(IDLE_EXTENSIONS_TOOL_NAME, arguments.unwrap_or_default())?;
+        let idle_minutes = params.idle_minutes.unwrap_or(DEFAULT_IDLE_MINUTES);
+
+        let extension_manager = self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or(ExtensionManagerToolError::ManagerUnavailable)?;
+
+        let idle = extension_manager
+            .idle_extensions(
+                session_id,
+                std::time::Duration::from_secs(idle_minutes.saturating_mul(60)),
+            )
+            .await
+            .map_err(|e| ExtensionManagerToolError::OperationFailed {
+                message: format!("Failed to list idle extensions: {}", e),
+            })?;
+
+        if idle.is_empty() {
+            return Ok(vec![Content::text(format!(
+                "Every enabled extension was used in the last {} minute(s)",
+                idle_minutes
+            ))]);
+        }
+
+        let lines = idle
+            .iter()
+            .map(|extension| {
+                let last_used = extension
+                    .last_used
+                    .map(|time| format!("last used {}", time.to_rfc3339()))
+                    .unwrap_or_else(|| "never used".to_string());
+                format!(
+                    "- {}: {}, {} tool(s)",
+                    extension.name, last_used, extension.tool_count
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        Ok(vec![Content::text(format!(
+            "Extensions not used in the last {} minute(s):\n{}\n\nConsider disabling them with manage_extensions to keep the tool list small.",
+            idle_minutes, lines
+        ))])
+    }
+
+    async fn handle_ping_extension(
+        &self,
+        session_id: &str,
+        arguments: Option<JsonObject>,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let arguments = arguments.ok_or(ExtensionManagerToolError::MissingParameter {
+            param_name: "extension_name".to_string(),
+        })?;
+        let params: PingExtensionParams = parse_params(PING_EXTENSION_TOOL_NAME, arguments)?;
+
+        let extension_manager = self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or(ExtensionManagerToolError::ManagerUnavailable)?;
+
+        let ping = extension_manager
+            .ping_extension(session_id, &params.extension_name)
+            .await
+            .ok_or_else(|| ExtensionManagerToolError::OperationFailed {
+                message: format!("Extension '{}' is not enabled", params.extension_name),
+            })?;
+
+        Ok(vec![Content::text(format_ping(
+            &params.extension_name,
+            &ping,
+        ))])
+    }
+
+    async fn handle_get_extension_instructions(
+        &self,
+        arguments: Option<JsonObject>,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let arguments = arguments.ok_or(ExtensionManagerToolError::MissingParameter {
+            param_name: "extension_name".to_string(),
+        })?;
+        let params: GetExtensionInstructionsParams =
+            parse_params(GET_EXTENSION_INSTRUCTIONS_TOOL_NAME, arguments)?;
+
+        let extension_manager = self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or(ExtensionManagerToolError::ManagerUnavailable)?;
+
+        let instructions = extension_manager
+            .get_extension_instructions(&params.extension_name)
+            .await
+            .ok_or_else(|| ExtensionManagerToolError::OperationFailed {
+                message: format!("Extension '{}' is not enabled", params.extension_name),
+            })?;
+
+        Ok(vec![Content::text(instructions.unwrap_or_else(|| {
+            format!(
+                "The extension '{}' did not provide any instructions",
+                params.extension_name
+            )
+        }))])
+    }
+
+    async fn handle_describe_tool(
+        &self,
+        session_id: &str,
+        arguments: Option<JsonObject>,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let arguments = arguments.ok_or(ExtensionManagerToolError::MissingParameter {
+            param_name: "extension_name".to_string(),
+        })?;
+        let params: DescribeToolParams = parse_params(DESCRIBE_TOOL_TOOL_NAME, arguments)?;
+
+        let extension_manager = self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or(ExtensionManagerToolError::ManagerUnavailable)?;
+
+        let tool = extension_manager
+            .describe_tool(session_id, &params.extension_name, &params.tool_name)
+            .await
+            .map_err(|e| ExtensionManagerToolError::CodedFailure {
+                code: e.code,
+                message: e.message.to_string(),
+            })?;
+        let content =
+            Content::json(tool).map_err(|e| ExtensionManagerToolError::OperationFailed {
+                message: format!("Failed to serialize tool definition: {}", e.message),
+            })?;
+        Ok(vec![content])
+    }
+
+    fn handle_set_tool_budget(
+        &self,
+        session_id: &str,
+        arguments: Option<JsonObject>,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let params: SetToolBudgetParams =
+            parse_params(SET_TOOL_BUDGET_TOOL_NAME, arguments.unwrap_or_default())?;
+
+        let budget = self
+            .context
+            .tool_budgets
+            .set_limit(session_id, params.limit);
+        Ok(vec![Content::text(format_tool_budget(&budget))])
+    }
+
+    fn handle_get_extension_audit_log(
+        &self,
+        session_id: &str,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let entries = self.context.extension_audit_log.entries(session_id);
+        if entries.is_empty() {
+            return Ok(vec![Content::text(
+                "No extensions have been enabled or disabled in this session".to_string(),
+            )]);
+        }
+
+        let lines: Vec<String> = entries
+            .iter()
+            .map(|entry| {
+                let action = match entry.action {
+                    ManageExtensionAction::Enable => "enabled",
+                    ManageExtensionAction::Disable => "disabled",
+                };
+                let mut line = format!(
+                    "{} {} {}",
+                    entry
+                        .timestamp
+                        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                    action,
+                    entry.extension_name
+                );
+                if let Some(reason) = &entry.reason {
+                    line.push_str(&format!(": {}", reason));
+                }
+                line
+            })
+            .collect();
+        Ok(vec![Content::text(lines.join("\n"))])
+    }
+
+    fn handle_get_tool_budget(
+        &self,
+        session_id: &str,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let budget = self.context.tool_budgets.get(session_id);
+        Ok(vec![Content::text(format_tool_budget(&budget))])
+    }
+
+    async fn handle_list_resources(
+        &self,
+        session_id: &str,
+        arguments: Option<JsonObject>,
+        cancellation_token: CancellationToken,
+    ) -> Result<CallToolResult, ExtensionManagerToolError> {
+        if let Some(weak_ref) = &self.context.extension_manager {
+            if let Some(extension_manager) = weak_ref.upgrade() {
+                let params: ListResourcesParams =
+                    parse_params(LIST_RESOURCES_TOOL_NAME, arguments.unwrap_or_default())?;
+
+                if params.count_only {
+                    let (counts, notes) = with_resource_timeout(
+                        "count resources",
+                        resource_tool_timeout_ms(),
+                        extension_manager.count_resources(session_id, params, cancellation_token),
+                    )
+                    .await?;
+                    return Ok(resource_count_result(counts, notes));
+                }
+
+                with_resource_timeout(
+                    "list resources",
+                    resource_tool_timeout_ms(),
+                    extension_manager.list_resources(session_id, params, cancellation_token),
+                )
+                .await
+                .map(CallToolResult::success)
+            } else {
+                Err(ExtensionManagerToolError::ManagerUnavailable)
+            }
+        } else {
+            Err(ExtensionManagerToolError::ManagerUnavailable)
+        }
+    }
+
+    async fn handle_read_resource(
+        &self,
+        session_id: &str,
+        arguments: Option<JsonObject>,
+        cancellation_token: CancellationToken,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        if let Some(weak_ref) = &self.context.extension_manager {
+            if let Some(extension_manager) = weak_ref.upgrade() {
+                let params: ReadResourceParams =
+                    parse_params(READ_RESOURCE_TOOL_NAME, arguments.unwrap_or_default())?;
+
+                if params.stream {
+                    return stream_resource(&extension_manager, session_id, &params);
+                }
+
+                with_resource_timeout(
+                    "read resource",
+                    resource_tool_timeout_ms(),
+                    extension_manager.read_resource_tool(session_id, params, cancellation_token),
+                )
+                .await
+            } else {
+                Err(ExtensionManagerToolError::ManagerUnavailable)
+            }
+        } else {
+            Err(ExtensionManagerToolError::ManagerUnavailable)
+        }
+    }
+
+    async fn handle_read_multiple_resources(
+        &self,
+        session_id: &str,
+        arguments: Option<JsonObject>,
+        cancellation_token: CancellationToken,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let extension_manager = self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or(ExtensionManagerToolError::ManagerUnavailable)?;
+        let arguments = arguments.ok_or(ExtensionManagerToolError::MissingParameter {
+            param_name: "uris".to_string(),
+        })?;
+        let params: ReadMultipleResourcesParams =
+            parse_params(READ_MULTIPLE_RESOURCES_TOOL_NAME, arguments)?;
+
+        with_resource_timeout(
+            "read resources",
+            resource_tool_timeout_ms(),
+            extension_manager.read_multiple_resources_tool(session_id, params, cancellation_token),
+        )
+        .await
+    }
+
+    fn handle_expand_resource_template(
+        &self,
+        arguments: Option<JsonObject>,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let arguments = arguments.ok_or(ExtensionManagerToolError::MissingParameter {
+            param_name: "uri_template".to_string(),
+        })?;
+        let params: ExpandResourceTemplateParams =
+            parse_params(EXPAND_RESOURCE_TEMPLATE_TOOL_NAME, arguments)?;
+
+        expand_template(&params.uri_template, &params.variables)
+            .map(|uri| vec![Content::text(uri)])
+            .map_err(|e| ExtensionManagerToolError::CodedFailure {
+                code: ErrorCode::INVALID_PARAMS,
+                message: e.to_string(),
+            })
+    }
+
+    fn handle_prefetch_resources(
+        &self,
+        session_id: &str,
+        arguments: Option<JsonObject>,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let arguments = arguments.ok_or(ExtensionManagerToolError::MissingParameter {
+            param_name: "uris".to_string(),
+        })?;
+        let params: PrefetchResourcesParams =
+            parse_params(PREFETCH_RESOURCES_TOOL_NAME, arguments)?;
+
+        let extension_manager = self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or(ExtensionManagerToolError::ManagerUnavailable)?;
+
+        let count = params.uris.len();
+        let ttl_secs = params.ttl_secs.unwrap_or(DEFAULT_PREFETCH_TTL_SECS);
+        // The reads finish on their own; read_resource uses whichever are done by then
+        drop(extension_manager.prefetch_resources(
+            session_id,
+            params.uris,
+            params.extension_name.as_deref(),
+            std::time::Duration::from_secs(ttl_secs),
+        ));
+        Ok(vec![Content::text(format!(
+            "Prefetching {} resource(s) in the background. Once loaded, read_resource serves \
+             them from cache for {} seconds.",
+            count, ttl_secs
+        ))])
+    }
+
+    async fn handle_watch_resource(
+        &self,
+        session_id: &str,
+        arguments: Option<JsonObject>,
+        cancellation_token: CancellationToken,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let arguments = arguments.ok_or(ExtensionManagerToolError::MissingParameter {
+            param_name: "uri".to_string(),
+        })?;
+        let params: WatchResourceParams = parse_params(WATCH_RESOURCE_TOOL_NAME, arguments)?;
+
+        let extension_manager = self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or(ExtensionManagerToolError::ManagerUnavailable)?;
+
+        extension_manager
+            .watch_resource(
+                session_id,
+                &params.uri,
+                params.extension_name.as_deref(),
+                cancellation_token,
+            )
+            .await
+            .map_err(|e| ExtensionManagerToolError::OperationFailed {
+                message: format!("Failed to watch resource: {}", e.message),
+            })
+    }
+
+    async fn handle_write_resource(
+        &self,
+        session_id: &str,
+        arguments: Option<JsonObject>,
+        cancellation_token: CancellationToken,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let arguments = arguments.ok_or(ExtensionManagerToolError::MissingParameter {
+            param_name: "uri".to_string(),
+        })?;
+        let params: WriteResourceParams = parse_params(WRITE_RESOURCE_TOOL_NAME, arguments)?;
+
+        let extension_manager = self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or(ExtensionManagerToolError::ManagerUnavailable)?;
+
+        extension_manager
+            .write_resource(
+                session_id,
+                &params.uri,
+                &params.content,
+                params.extension_name.as_deref(),
+                cancellation_token,
+            )
+            .await
+            .map_err(|e| ExtensionManagerToolError::OperationFailed {
+                message: format!("Failed to write resource: {}", e.message),
+            })
+    }
+
+    async fn handle_resource_hash(
+        &self,
+        session_id: &str,
+        arguments: Option<JsonObject>,
+        cancellation_token: CancellationToken,
+    ) -> Result<Vec<Content>, ExtensionManagerToolError> {
+        let extension_manager = self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or(ExtensionManagerToolError::ManagerUnavailable)?;
+
+        let arguments = arguments.ok_or(ExtensionManagerToolError::MissingParameter {
+            param_name: "uri".to_string(),
+        })?;
+        let params: ResourceHashParams = parse_params(RESOURCE_HASH_TOOL_NAME, arguments)?;
+
+        extension_manager
+            .resource_hash_tool(session_id, params, cancellation_token)
+            .await
+            .map_err(|e| ExtensionManagerToolError::OperationFailed {
+                message: format!("Failed to hash resource: {}", e.message),
+            })
+    }
+
+    /// The tools to list for this session, sorted by name so the listing is stable whichever
+    /// optional tools are offered
+    async fn get_tools(&self) -> Vec<Tool> {
+        let mut tools = base_tools();
+
+        // Only add resource tools if extension manager supports resources
+        if let Some(weak_ref) = &self.context.extension_manager {
+            if let Some(extension_manager) = weak_ref.upgrade() {
+                if extension_manager.supports_resources().await {
+                    tools.extend(resource_tools());
+                }
+                if extension_manager.supports_resource_writes().await {
+                    tools.push(write_resource_tool());
+                }
+            }
+        }
+
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+        tools
+    }
+}
+
+/// Every tool the extension manager can offer, including the resource tools that a live
+/// session only lists when an enabled extension supports resources. Lets integrators
+/// generate client bindings without starting an agent. Sorted by name.
+pub fn tool_schemas() -> Vec<Tool> {
+    let mut tools = base_tools();
+    tools.extend(resource_tools());
+    tools.push(write_resource_tool());
+    tools.sort_by(|a, b| a.name.cmp(&b.name));
+    tools
+}
+
+/// Offered when some enabled extension accepts resource writes
+fn write_resource_tool() -> Tool {
+    Tool::new(
+        WRITE_RESOURCE_TOOL_NAME.to_string(),
+        indoc! {r#"
+            Write new text content to a resource.
+
+            Only extensions that support resource writes accept this; the write replaces the
+            resource's content and the extension's confirmation is returned. Pass
+            extension_name when more than one extension supports writes.
+        "#}
+        .to_string(),
+        Arc::new(
+            serde_json::to_value(schema_for!(WriteResourceParams))
+                .expect("Failed to serialize schema")
+                .as_object()
+                .expect("Schema must be an object")
+                .clone(),
+        ),
+    )
+    .annotate(ToolAnnotations {
+        title: Some("Write a resource".to_string()),
+        read_only_hint: Some(false),
+        destructive_hint: Some(true),
+        idempotent_hint: Some(true),
+        open_world_hint: Some(false),
+    })
+}
+
+#[allow(clippy::too_many_lines)]
+fn base_tools() -> Vec<Tool> {
+    vec![
+        Tool::new(
+            SEARCH_AVAILABLE_EXTENSIONS_TOOL_NAME.to_string(),
+            "Searches for additional extensions available to help complete tasks.
+        Use this tool when you're unable to find a specific feature or functionality you need to complete your task, or when standard approaches aren't working.
+        These extensions might provide the exact tools needed to solve your problem.
+        Pass a query to rank extensions by keyword match on their names and descriptions, or a category such as \"stdio\" or \"builtin\" to list only that type.
+        If you find a relevant one, consider using your tools to enable it.".to_string(),
+            Arc::new(
+                serde_json::to_value(schema_for!(SearchAvailableExtensionsParams))
+                    .expect("Failed to serialize schema")
+                    .as_object()
+                    .expect("Schema must be an object")
+                    .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Discover extensions".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(false),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            LIST_ENABLED_EXTENSIONS_TOOL_NAME.to_string(),
+            indoc! {r#"
+        List the extensions that are currently enabled, with their versions, how many tools
+        each provides, and their instructions.
+
+        Check this before enabling an extension with manage_extensions to avoid enabling one
+        that is already active.
+    "#}.to_string(),
+            Arc::new(
+                serde_json::json!({
+                    "type": "object",
+                    "required": [],
+                    "properties": {}
+                })
+                .as_object()
+                .expect("Schema must be an object")
+                .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("List enabled extensions".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(true),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            MANAGE_EXTENSIONS_TOOL_NAME.to_string(),
+            "Tool to manage extensions and tools in goose context.
+            Enable or disable extensions to help complete tasks.
+            Enable or disable an extension by providing the extension name.
+            For an extension that is known to start slowly, set init_timeout_ms to allow it
+            more time.
+            For an extension with many tools, set max_tools to load only the first ones.
+            To forbid specific tools, list them in deny_tools, or list the only tools to permit
+            in allow_tools; a tool in both is denied. Hidden tools can't be called.
+            ".to_string(),
+            Arc::new(
+                serde_json::to_value(schema_for!(ManageExtensionsParams))
+                    .expect("Failed to serialize schema")
+                    .as_object()
+                    .expect("Schema must be an object")
+                    .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Enable or disable an extension".to_string()),
+            read_only_hint: Some(false),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(false),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            VALIDATE_CONFIGS_TOOL_NAME.to_string(),
+            indoc! {r#"
+        Validate one or more extension configs without starting any extension.
+
+        Each config is checked the same way it would be when enabling it, and a
+        per-config report of problems is returned.
+    "#}.to_string(),
+            Arc::new(
+                serde_json::to_value(schema_for!(ValidateConfigsParams))
+                    .expect("Failed to serialize schema")
+                    .as_object()
+                    .expect("Schema must be an object")
+                    .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Validate extension configs".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(true),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            RELOAD_CHANGED_EXTENSIONS_TOOL_NAME.to_string(),
+            indoc! {r#"
+        Restart only the enabled extensions whose stored config no longer matches the config
+        they are running with, and leave the rest untouched.
+
+        Use this after editing an extension's config instead of disabling and re-enabling
+        everything. Reports which extensions were reloaded and which were unchanged.
+    "#}.to_string(),
+            Arc::new(
+                serde_json::json!({
+                    "type": "object",
+                    "required": [],
+                    "properties": {}
+                })
+                .as_object()
+                .expect("Schema must be an object")
+                .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Reload changed extensions".to_string()),
+            read_only_hint: Some(false),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(true),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            FETCH_RESULT_TOOL_NAME.to_string(),
+            indoc! {r#"
+        Retrieve the content of a tool result that was stored as a reference.
+
+        Pass the handle returned when a tool was called with store_result_as_reference.
+        Handles are only valid within the session that created them.
+    "#}.to_string(),
+            Arc::new(
+                serde_json::to_value(schema_for!(FetchResultParams))
+                    .expect("Failed to serialize schema")
+                    .as_object()
+                    .expect("Schema must be an object")
+                    .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Fetch a stored result".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(true),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            SAVE_TOOL_BASELINE_TOOL_NAME.to_string(),
+            indoc! {r#"
+        Save the current full tool set as a named baseline.
+
+        Use diff_tool_baseline later to detect tools that were added, removed, or changed
+        since the baseline was saved, for example after an extension update.
+    "#}.to_string(),
+            Arc::new(
+                serde_json::to_value(schema_for!(ToolBaselineParams))
+                    .expect("Failed to serialize schema")
+                    .as_object()
+                    .expect("Schema must be an object")
+                    .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Save tool baseline".to_string()),
+            read_only_hint: Some(false),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(false),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            DIFF_TOOL_BASELINE_TOOL_NAME.to_string(),
+            indoc! {r#"
+        Compare the current tool set against a baseline saved with save_tool_baseline.
+
+        Reports tools that were added, removed, or whose description or input schema changed.
+    "#}.to_string(),
+            Arc::new(
+                serde_json::to_value(schema_for!(ToolBaselineParams))
+                    .expect("Failed to serialize schema")
+                    .as_object()
+                    .expect("Schema must be an object")
+                    .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Diff against tool baseline".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(true),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            LIST_EXTENSION_ERRORS_TOOL_NAME.to_string(),
+            indoc! {r#"
+        List the error categories an enabled extension can produce.
+
+        Extensions don't declare their errors, so categories are inferred from failures seen in
+        this session. Each entry says whether retrying is likely to help, which is useful when
+        planning recovery from failed tool calls.
+    "#}.to_string(),
+            Arc::new(
+                serde_json::to_value(schema_for!(ListExtensionErrorsParams))
+                    .expect("Failed to serialize schema")
+                    .as_object()
+                    .expect("Schema must be an object")
+                    .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("List extension error categories".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(true),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            CANCEL_EXTENSION_OPERATIONS_TOOL_NAME.to_string(),
+            indoc! {r#"
+        Cancel all in-flight tool calls to an extension without disabling it.
+
+        Use this when an extension is stuck or misbehaving. The extension stays enabled and
+        can be called again afterwards.
+    "#}.to_string(),
+            Arc::new(
+                serde_json::to_value(schema_for!(CancelExtensionOperationsParams))
+                    .expect("Failed to serialize schema")
+                    .as_object()
+                    .expect("Schema must be an object")
+                    .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Cancel extension operations".to_string()),
+            read_only_hint: Some(false),
+            destructive_hint: Some(true),
+            idempotent_hint: Some(true),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            SET_EXTENSION_ALIAS_TOOL_NAME.to_string(),
+            indoc! {r#"
+        Show an enabled extension under a clearer name.
+
+        The extension's tools are prefixed with the alias and manage_extensions accepts
+        either name. Its configuration is unchanged. Omit the alias to restore the
+        original name.
+    "#}.to_string(),
+            Arc::new(
+                serde_json::to_value(schema_for!(SetExtensionAliasParams))
+                    .expect("Failed to serialize schema")
+                    .as_object()
+                    .expect("Schema must be an object")
+                    .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Alias an extension".to_string()),
+            read_only_hint: Some(false),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(true),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            GET_CAPABILITIES_TOOL_NAME.to_string(),
+            indoc! {r#"
+        Get the MCP capabilities (tools, resources, prompts, completions, logging) each enabled
+        extension advertised, plus what the session supports as a whole.
+
+        Extensions that reported no server information are listed as null.
+    "#}.to_string(),
+            Arc::new(
+                serde_json::json!({
+                    "type": "object",
+                    "required": [],
+                    "properties": {}
+                })
+                .as_object()
+                .expect("Schema must be an object")
+                .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Get extension capabilities".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(true),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            PREVIEW_ENABLE_IMPACT_TOOL_NAME.to_string(),
+            indoc! {r#"
+        Preview what enabling an extension would add, without enabling it.
+
+        Briefly starts the extension on its own to list the tools it would add, flags tools
+        whose names match tools already active from other extensions, and estimates how
+        many tokens the new tool definitions would cost per request.
+
+        Starting the extension runs its command or connects to its server, so whatever the
+        extension does on startup happens during the preview. It is shut down again before
+        the preview returns and is never added to the active set.
+    "#}.to_string(),
+            Arc::new(
+                serde_json::to_value(schema_for!(PreviewEnableImpactParams))
+                    .expect("Failed to serialize schema")
+                    .as_object()
+                    .expect("Schema must be an object")
+                    .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Preview enabling an extension".to_string()),
+            read_only_hint: Some(false),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(true),
+            open_world_hint: Some(true),
+        }),
+        Tool::new(
+            IDLE_EXTENSIONS_TOOL_NAME.to_string(),
+            indoc! {r#"
+        List enabled extensions that have not handled a successful tool call recently.
+
+        Shows when each was last used and how many tools it adds. Disabling idle extensions
+        keeps the tool list small in long sessions.
+    "#}.to_string(),
+            Arc::new(
+                serde_json::to_value(schema_for!(IdleExtensionsParams))
+                    .expect("Failed to serialize schema")
+                    .as_object()
+                    .expect("Schema must be an object")
+                    .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("List idle extensions".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(false),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            PING_EXTENSION_TOOL_NAME.to_string(),
+            indoc! {r#"
+        Check whether an extension is reachable and how quickly it responds.
+
+        For remote extensions this times a lightweight round-trip and tells network failures
+        apart from protocol errors. For local extensions it reports whether the process is
+        still alive. Use it to diagnose a failing extension before retrying its tools.
+    "#}.to_string(),
+            Arc::new(
+                serde_json::to_value(schema_for!(PingExtensionParams))
+                    .expect("Failed to serialize schema")
+                    .as_object()
+                    .expect("Schema must be an object")
+                    .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Ping an extension".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(false),
+            open_world_hint: Some(true),
+        }),
+        Tool::new(
+            GET_EXTENSION_INSTRUCTIONS_TOOL_NAME.to_string(),
+            indoc! {r#"
+        Get the usage instructions an enabled extension provided when it connected.
+
+        Use it to re-read an extension's guidance mid-conversation without re-enabling it.
+    "#}.to_string(),
+            Arc::new(
+                serde_json::to_value(schema_for!(GetExtensionInstructionsParams))
+                    .expect("Failed to serialize schema")
+                    .as_object()
+                    .expect("Schema must be an object")
+                    .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Get extension instructions".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(true),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            DESCRIBE_TOOL_TOOL_NAME.to_string(),
+            indoc! {r#"
+        Get the full definition of one tool of an enabled extension: its description, input
+        schema and annotations, as the extension lists it.
+
+        Use it to check the exact argument shape before calling a tool. Pass the tool name
+        without the extension prefix.
+    "#}.to_string(),
+            Arc::new(
+                serde_json::to_value(schema_for!(DescribeToolParams))
+                    .expect("Failed to serialize schema")
+                    .as_object()
+                    .expect("Schema must be an object")
+                    .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Describe a tool".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(true),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            SET_TOOL_BUDGET_TOOL_NAME.to_string(),
+            indoc! {r#"
+        Cap the total number of tool calls allowed in this session.
+
+        Calls already made count against the new limit. Once the budget is exhausted, other
+        tool calls return a "budget exhausted" result instead of running. The budget tools
+        themselves are never blocked. Omit the limit to remove the cap.
+    "#}.to_string(),
+            Arc::new(
+                serde_json::to_value(schema_for!(SetToolBudgetParams))
+                    .expect("Failed to serialize schema")
+                    .as_object()
+                    .expect("Schema must be an object")
+                    .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Set tool call budget".to_string()),
+            read_only_hint: Some(false),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(true),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            GET_TOOL_BUDGET_TOOL_NAME.to_string(),
+            "Show this session's tool call budget, how many calls have been made, and how many remain.".to_string(),
+            Arc::new(
+                serde_json::json!({
+                    "type": "object",
+                    "required": [],
+                    "properties": {}
+                })
+                .as_object()
+                .expect("Schema must be an object")
+                .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Get tool call budget".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(true),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            GENERATE_SUPPORT_BUNDLE_TOOL_NAME.to_string(),
+            indoc! {r#"
+        Generate a JSON support bundle for a bug report: enabled extensions with their server
+        versions, capabilities, and observed errors, configured extensions that are not enabled,
+        and which platform extensions are active.
+
+        Environment variable values, header values, and URI query strings are redacted.
+    "#}.to_string(),
+            Arc::new(
+                serde_json::json!({
+                    "type": "object",
+                    "required": [],
+                    "properties": {}
+                })
+                .as_object()
+                .expect("Schema must be an object")
+                .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Generate support bundle".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(true),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            GET_EXTENSION_STATUS_TOOL_NAME.to_string(),
+            indoc! {r#"
+        Report the health of each enabled extension as JSON: whether its connection is alive
+        or has crashed, the server name, version, and MCP protocol version it reported, its
+        tool count, when it last handled a call successfully, and whether its most recent call
+        failed.
+
+        Its capabilities include `resource_subscriptions`, which says whether the extension can
+        push resource updates; check it before calling watch_resource.
+    "#}.to_string(),
+            Arc::new(
+                serde_json::json!({
+                    "type": "object",
+                    "required": [],
+                    "properties": {}
+                })
+                .as_object()
+                .expect("Schema must be an object")
+                .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Get extension status".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(false),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            RESTORE_EXTENSIONS_TOOL_NAME.to_string(),
+            indoc! {r#"
+            Undo the most recent change to the enabled extensions made with manage_extensions or
+            disable_all_extensions, enabling and disabling extensions to get back the set that was
+            enabled before it. Call it again to step further back; the last 10 changes are kept.
+        "#}.to_string(),
+            Arc::new(
+                serde_json::json!({
+                    "type": "object",
+                    "required": [],
+                    "properties": {}
+                })
+                .as_object()
+                .expect("Schema must be an object")
+                .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Restore previous extensions".to_string()),
+            read_only_hint: Some(false),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(false),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            GET_EXTENSION_AUDIT_LOG_TOOL_NAME.to_string(),
+            indoc! {r#"
+            List the extensions enabled or disabled with manage_extensions in this session,
+            oldest first, with the time and the reason given for each change.
+        "#}.to_string(),
+            Arc::new(
+                serde_json::json!({
+                    "type": "object",
+                    "required": [],
+                    "properties": {}
+                })
+                .as_object()
+                .expect("Schema must be an object")
+                .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Get extension audit log".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(true),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            DISABLE_ALL_EXTENSIONS_TOOL_NAME.to_string(),
+            indoc! {r#"
+        Disable every enabled extension to get back to a clean slate, for example when
+        recovering from errors. The built-in platform extensions, including this one, stay
+        enabled. Extensions that fail to disable are listed and don't stop the rest.
+    "#}.to_string(),
+            Arc::new(
+                serde_json::json!({
+                    "type": "object",
+                    "required": [],
+                    "properties": {}
+                })
+                .as_object()
+                .expect("Schema must be an object")
+                .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Disable all extensions".to_string()),
+            read_only_hint: Some(false),
+            destructive_hint: Some(true),
+            idempotent_hint: Some(true),
+            open_world_hint: Some(false),
+        }),
+    ]
+}
+
+/// Tools for reading extension resources, offered when some enabled extension has them
+#[allow(clippy::too_many_lines)]
+fn resource_tools() -> Vec<Tool> {
+    vec![
+        Tool::new(
+            LIST_RESOURCES_TOOL_NAME.to_string(),
+            indoc! {r#"
+            List resources from an extension(s).
+
+            Resources allow extensions to share data that provide context to LLMs, such as
+            files, database schemas, or application-specific information. This tool lists resources
+            in the provided extension, and returns a list for the user to browse. If no extension
+            is provided, the tool will search all extensions for the resource.
+
+            Use uri_prefix to only list resources under a path, such as a directory. The result
+            then also says how many resources matched. Use name_pattern to only query the
+            extensions whose names match a glob or /regex/.
+
+            For extensions with many resources, pass a limit to get one page at a time. The last
+            item of a page holds a next_cursor; pass it as cursor to get the next page, until it
+            is null.
+
+            Each resource has an index within its extension's listing, which read_resource
+            accepts in place of the URI until the extension is disabled.
+
+            Set count_only to get just the number of resources per extension, for example to
+            decide whether to paginate.
+        "#}.to_string(),
+            Arc::new(
+                serde_json::to_value(schema_for!(ListResourcesParams))
+                    .expect("Failed to serialize schema")
+                    .as_object()
+                    .expect("Schema must be an object")
+                    .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("List resources".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(false),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            READ_RESOURCE_TOOL_NAME.to_string(),
+            indoc! {r#"
+            Read a resource from an extension.
+
+            Resources allow extensions to share data that provide context to LLMs, such as
+            files, database schemas, or application-specific information. This tool searches for the
+            resource URI in the provided extension, and reads in the resource content. If no extension
+            is provided, the tool will search all extensions for the resource.
+
+            Set all_representations to get every form the extension offers for the resource, such
+            as a table as both CSV and JSON, each labeled with its MIME type.
+
+            Binary resources such as images and PDFs are returned with their MIME type: images as
+            image content, anything else as an embedded base64 resource. Set max_bytes to refuse
+            resources larger than that.
+
+            If several extensions serve the URI, the read fails and lists them; pass
+            extension_name or mime_type to choose.
+
+            Pass index and extension_name instead of uri to read a resource by its index in the
+            latest list_resources result for that extension.
+
+            For large text resources, set truncate_at to get only the first bytes; the content is
+            then marked truncated in its _meta and says which offset to continue from. Use offset
+            and length to read a specific byte range.
+
+            The content's _meta carries an etag, and a lastModified time when the extension
+            provides one. When polling, pass the etag back as if_none_match to get a short
+            "unchanged" note instead of the full content if the resource hasn't changed.
+
+            For very large resources, set stream to get a stream id at once and receive the
+            content as numbered notifications/resource_chunk notifications to reassemble. A
+            stream whose chunks go undelivered for a minute is abandoned without a last chunk.
+        "#}.to_string(),
+            Arc::new(
+                serde_json::to_value(schema_for!(ReadResourceParams))
+                    .expect("Failed to serialize schema")
+                    .as_object()
+                    .expect("Schema must be an object")
+                    .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Read a resource".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(false),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            READ_MULTIPLE_RESOURCES_TOOL_NAME.to_string(),
+            indoc! {r#"
+            Read several resources in one call.
+
+            The resources are read concurrently and returned in the order of uris, each labeled
+            with its URI like read_resource. A URI that can't be read gets an error note instead
+            of failing the whole call. Set max_total_bytes to cap the combined size; resources
+            that would exceed it are listed as skipped.
+        "#}.to_string(),
+            Arc::new(
+                serde_json::to_value(schema_for!(ReadMultipleResourcesParams))
+                    .expect("Failed to serialize schema")
+                    .as_object()
+                    .expect("Schema must be an object")
+                    .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Read several resources".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(false),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            EXPAND_RESOURCE_TEMPLATE_TOOL_NAME.to_string(),
+            indoc! {r#"
+            Fill in a resource template with values for its variables, returning the concrete
+            URI to pass to read_resource.
+
+            list_resources shows each extension's templates and their variables. Every variable
+            not marked optional needs a value, and values for variables the template doesn't
+            use are rejected. Values are percent-encoded as RFC 6570 requires.
+        "#}.to_string(),
+            Arc::new(
+                serde_json::to_value(schema_for!(ExpandResourceTemplateParams))
+                    .expect("Failed to serialize schema")
+                    .as_object()
+                    .expect("Schema must be an object")
+                    .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Expand resource template".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(true),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            PREFETCH_RESOURCES_TOOL_NAME.to_string(),
+            indoc! {r#"
+            Start loading several resources in the background so that later read_resource
+            calls for them return at once.
+
+            Returns immediately. The resources are read concurrently and kept for ttl_secs; a
+            URI that fails to load isn't cached, and read_resource then reads it as usual.
+            Writing a resource drops its cached copy.
+        "#}.to_string(),
+            Arc::new(
+                serde_json::to_value(schema_for!(PrefetchResourcesParams))
+                    .expect("Failed to serialize schema")
+                    .as_object()
+                    .expect("Schema must be an object")
+                    .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Prefetch resources".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(true),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            WATCH_RESOURCE_TOOL_NAME.to_string(),
+            indoc! {r#"
+            Watch a resource for changes.
+
+            Subscribes to the resource with the extension that serves it, which must support
+            resource subscriptions (see `resource_subscriptions` in get_extension_status). Each
+            change is then sent to the client as a resource updated notification with the
+            resource's URI; changes made while the agent is idle are sent when it next replies.
+            Read the resource again to see what changed. The watch lasts for the rest of the
+            session, or until the extension is disabled.
+        "#}.to_string(),
+            Arc::new(
+                serde_json::to_value(schema_for!(WatchResourceParams))
+                    .expect("Failed to serialize schema")
+                    .as_object()
+                    .expect("Schema must be an object")
+                    .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Watch a resource".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(true),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            RESOURCE_HASH_TOOL_NAME.to_string(),
+            indoc! {r#"
+            Get a stable hash of a resource's current content without returning the content.
+
+            Also reports whether the content changed since the resource was last read or hashed
+            in this session, so you can cheaply check for changes before reading it again.
+        "#}.to_string(),
+            Arc::new(
+                serde_json::to_value(schema_for!(ResourceHashParams))
+                    .expect("Failed to serialize schema")
+                    .as_object()
+                    .expect("Schema must be an object")
+                    .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Hash a resource".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(false),
+            open_world_hint: Some(false),
+        }),
+        Tool::new(
+            RESOURCE_CATALOG_TOOL_NAME.to_string(),
+            indoc! {r#"
+            Get one inventory of the resources across all enabled extensions.
+
+            Each resource is listed once per URI with its providing extension, MIME type, and
+            size, along with the total count, the count per extension, and the total bytes.
+            Use list_resources to browse a single extension or filter by URI prefix.
+        "#}.to_string(),
+            Arc::new(
+                serde_json::json!({
+                    "type": "object",
+                    "required": [],
+                    "properties": {}
+                })
+                .as_object()
+                .expect("Schema must be an object")
+                .clone()
+            ),
+        ).annotate(ToolAnnotations {
+            title: Some("Catalog resources".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(true),
+            open_world_hint: Some(false),
+        }),
+    ]
+}
+
+#[async_trait]
+impl McpClientTrait for ExtensionManagerClient {
+    async fn list_resources(
+        &self,
+        _session_id: &str,
+        _next_cursor: Option<String>,
+        _cancellation_token: CancellationToken,
+    ) -> Result<ListResourcesResult, Error> {
+        Err(Error::TransportClosed)
+    }
+
+    async fn read_resource(
+        &self,
+        _session_id: &str,
+        _uri: &str,
+        _cancellation_token: CancellationToken,
+    ) -> Result<ReadResourceResult, Error> {
+        // Extension manager doesn't expose resources directly
+        Err(Error::TransportClosed)
+    }
+
+    async fn list_tools(
+        &self,
+        _session_id: &str,
+        _next_cursor: Option<String>,
+        _cancellation_token: CancellationToken,
+    ) -> Result<ListToolsResult, Error> {
+        Ok(ListToolsResult {
+            tools: self.get_tools().await,
+            next_cursor: None,
+            meta: None,
+        })
     }
 
-    async fn get_prompt(
+    async fn call_tool(
+        &self,
+        session_id: &str,
+        name: &str,
+        arguments: Option<JsonObject>,
+        _working_dir: Option<&str>,
+        cancellation_token: CancellationToken,
+    ) -> Result<CallToolResult, Error> {
+        // Time the whole handler, including the calls it makes into other extensions
+        let span = tracing::info_span!(
+            "extension_manager_tool",
+            tool_name = name,
+            duration_ms = tracing::field::Empty
+        );
+        let started = Instant::now();
+        // Set by handlers that forward another tool's result, so its machine-readable output
+        // reaches the caller along with the content
+        let mut structured_content = None;
+        let result = async {
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.try_acquire(name).map_err(|retry_after| {
+                    ExtensionManagerToolError::RateLimited {
+                        tool_name: name.to_string(),
+                        retry_after_secs: retry_after.as_secs().saturating_add(1),
+                    }
+                })?;
+            }
+            match name.parse::<ExtensionManagerTool>() {
+                Ok(ExtensionManagerTool::SearchAvailableExtensions) => {
+                    self.handle_search_available_extensions(arguments).await
+                }
+                Ok(ExtensionManagerTool::ListEnabledExtensions) => {
+                    self.handle_list_enabled_extensions(session_id).await
+                }
+                Ok(ExtensionManagerTool::ManageExtensions) => self
+                    .handle_manage_extensions(session_id, arguments)
+                    .await
+                    .map(|result| {
+                        structured_content = result.structured_content;
+                        result.content
+                    }),
+                Ok(ExtensionManagerTool::ValidateConfigs) => {
+                    self.handle_validate_configs(arguments)
+                }
+                Ok(ExtensionManagerTool::ReloadChangedExtensions) => {
+                    self.handle_reload_changed_extensions().await
+                }
+                Ok(ExtensionManagerTool::FetchResult) => self
+                    .handle_fetch_result(session_id, arguments)
+                    .await
+                    .map(|result| {
+                        structured_content = result.structured_content;
+                        result.content
+                    }),
+                Ok(ExtensionManagerTool::ListExtensionErrors) => {
+                    self.handle_list_extension_errors(arguments).await
+                }
+                Ok(ExtensionManagerTool::CancelExtensionOperations) => {
+                    self.handle_cancel_extension_operations(arguments).await
+                }
+                Ok(ExtensionManagerTool::SetExtensionAlias) => {
+                    self.handle_set_extension_alias(arguments).await
+                }
+                Ok(ExtensionManagerTool::GetCapabilities) => self.handle_get_capabilities().await,
+                Ok(ExtensionManagerTool::PreviewEnableImpact) => {
+                    self.handle_preview_enable_impact(session_id, arguments)
+                        .await
+                }
+                Ok(ExtensionManagerTool::IdleExtensions) => {
+                    self.handle_idle_extensions(session_id, arguments).await
+                }
+                Ok(ExtensionManagerTool::PingExtension) => {
+                    self.handle_ping_extension(session_id, arguments).await
+                }
+                Ok(ExtensionManagerTool::SetToolBudget) => {
+                    self.handle_set_tool_budget(session_id, arguments)
+                }
+                Ok(ExtensionManagerTool::GetToolBudget) => self.handle_get_tool_budget(session_id),
+                Ok(ExtensionManagerTool::GetExtensionStatus) => {
+                    self.handle_get_extension_status(session_id).await
+                }
+                Ok(ExtensionManagerTool::RestoreExtensions) => {
+                    self.handle_restore_extensions(session_id).await
+                }
+                Ok(ExtensionManagerTool::GetExtensionAuditLog) => {
+                    self.handle_get_extension_audit_log(session_id)
+                }
+                Ok(ExtensionManagerTool::DisableAllExtensions) => {
+                    self.handle_disable_all_extensions(session_id).await
+                }
+                Ok(ExtensionManagerTool::GenerateSupportBundle) => {
+                    self.handle_generate_support_bundle().await
+                }
+                Ok(ExtensionManagerTool::SaveToolBaseline) => {
+                    self.handle_save_tool_baseline(session_id, arguments).await
+                }
+                Ok(ExtensionManagerTool::DiffToolBaseline) => {
+                    self.handle_diff_tool_baseline(session_id, arguments).await
+                }
+                Ok(ExtensionManagerTool::ListResources) => self
+                    .handle_list_resources(session_id, arguments, cancellation_token)
+                    .await
+                    .map(|result| {
+                        structured_content = result.structured_content;
+                        result.content
+                    }),
+                Ok(ExtensionManagerTool::ReadResource) => {
+                    self.handle_read_resource(session_id, arguments, cancellation_token)
+                        .await
+                }
+                Ok(ExtensionManagerTool::ReadMultipleResources) => {
+                    self.handle_read_multiple_resources(session_id, arguments, cancellation_token)
+                        .await
+                }
+                Ok(ExtensionManagerTool::PrefetchResources) => {
+                    self.handle_prefetch_resources(session_id, arguments)
+                }
+                Ok(ExtensionManagerTool::ExpandResourceTemplate) => {
+                    self.handle_expand_resource_template(arguments)
+                }
+                Ok(ExtensionManagerTool::WatchResource) => {
+                    self.handle_watch_resource(session_id, arguments, cancellation_token)
+                        .await
+                }
+                Ok(ExtensionManagerTool::GetExtensionInstructions) => {
+                    self.handle_get_extension_instructions(arguments).await
+                }
+                Ok(ExtensionManagerTool::DescribeTool) => {
+                    self.handle_describe_tool(session_id, arguments).await
+                }
+                Ok(ExtensionManagerTool::WriteResource) => {
+                    self.handle_write_resource(session_id, arguments, cancellation_token)
+                        .await
+                }
+                Ok(ExtensionManagerTool::ResourceHash) => {
+                    self.handle_resource_hash(session_id, arguments, cancellation_token)
+                        .await
+                }
+                Ok(ExtensionManagerTool::ResourceCatalog) => {
+                    self.handle_resource_catalog(session_id, cancellation_token)
+                        .await
+                }
+                Err(error) => Err(error),
+            }
+        }
+        .instrument(span.clone())
+        .await;
+        let duration_ms = started.elapsed().as_millis() as u64;
+        span.record("duration_ms", duration_ms);
+        info!(
+            parent: &span,
+            tool_name = name,
+            duration_ms,
+            "extension manager tool call finished"
+        );
+
+        match result {
+            Ok(content) => Ok(CallToolResult {
+                structured_content,
+                ..CallToolResult::success(content)
+            }),
+            Err(error) => {
+                // Log the error for debugging
+                error!("Extension manager tool '{}' failed: {}", name, error);
+
+                let structured_content = match &error {
+                    ExtensionManagerToolError::CodedFailure { code, message } => {
+                        Some(serde_json::json!({ "error_code": code.0, "message": message }))
+                    }
+                    _ => None,
+                };
+
+                // Return proper error result with is_error flag set
+                Ok(CallToolResult {
+                    content: vec![Content::text(error.to_string())],
+                    is_error: Some(true), // ✅ Properly mark as error
+                    structured_content,
+                    meta: None,
+                })
+            }
+        }
+    }
+
+    async fn list_prompts(
         &self,
         _session_id: &str,
-        _name: &str,
-        _arguments: Value,
+        _next_cursor: Option<String>,
         _cancellation_token: CancellationToken,
+    ) -> Result<ListPromptsResult, Error> {
+        // The extension manager aggregates prompts by asking each extension, this one
+        // included, so listing the others' prompts here would duplicate them and re-enter it
+        Ok(ListPromptsResult::default())
+    }
+
+    /// Complete the uri argument of read_resource and similar tools from the resources
+    /// extensions list, scoped to the extension_name in the completion context if there is one
+    async fn complete(
+        &self,
+        session_id: &str,
+        request: CompleteRequestParams,
+        cancellation_token: CancellationToken,
+    ) -> Result<CompleteResult, Error> {
+        if request.argument.name != "uri" {
+            return Ok(CompleteResult::default());
+        }
+        let extension_manager = self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or(Error::TransportClosed)?;
+        let extension_name = request
+            .context
+            .as_ref()
+            .and_then(|context| context.arguments.as_ref())
+            .and_then(|arguments| arguments.get("extension_name"));
+
+        let mut values = extension_manager
+            .complete_resource_uri(
+                session_id,
+                &request.argument.value,
+                extension_name.map(String::as_str),
+                cancellation_token,
+            )
+            .await;
+        let total = values.len();
+        values.truncate(CompletionInfo::MAX_VALUES);
+        Ok(CompleteResult {
+            completion: CompletionInfo {
+                has_more: Some(total > values.len()),
+                total: Some(total as u32),
+                values,
+            },
+        })
+    }
+
+    /// Pass the request on to the enabled extension that defines the prompt
+    async fn get_prompt(
+        &self,
+        session_id: &str,
+        name: &str,
+        arguments: Value,
+        cancellation_token: CancellationToken,
     ) -> Result<GetPromptResult, Error> {
-        Err(Error::TransportClosed)
+        let extension_manager = self
+            .context
+            .extension_manager
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or_else(|| {
+                ServiceError::McpError(ErrorData::new(
+                    ErrorCode::INTERNAL_ERROR,
+                    "Extension manager is no longer available".to_string(),
+                    None,
+                ))
+            })?;
+        extension_manager
+            .route_prompt(session_id, name, arguments, cancellation_token)
+            .await
+            .map_err(ServiceError::McpError)
     }
 
     async fn subscribe(&self) -> mpsc::Receiver<ServerNotification> {
-        mpsc::channel(1).1
+        let mut notifications = self.context.notifications.subscribe();
+        let (tx, rx) = mpsc::channel(16);
+        tokio::spawn(async move {
+            loop {
+                let notification = tokio::select! {
+                    _ = tx.closed() => break,
+                    notification = notifications.recv() => notification,
+                };
+                match notification {
+                    Ok(notification) => {
+                        if tx.send(notification).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        rx
     }
 
     fn get_info(&self) -> Option<&InitializeResult> {
         Some(&self.info)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_client() -> (
+        tempfile::TempDir,
+        Arc<crate::agents::extension_manager::ExtensionManager>,
+        ExtensionManagerClient,
+    ) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extension_manager = Arc::new(
+            crate::agents::extension_manager::ExtensionManager::new_without_provider(
+                temp_dir.path().to_path_buf(),
+            ),
+        );
+        let mut context = extension_manager.get_context().clone();
+        context.extension_manager = Some(Arc::downgrade(&extension_manager));
+        let client = ExtensionManagerClient::new(context).unwrap();
+        (temp_dir, extension_manager, client)
+    }
+
+    fn signature(description: &str, schema_hash: &str) -> ToolSignature {
+        ToolSignature {
+            description: description.to_string(),
+            schema_hash: schema_hash.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_params_names_tool_and_fields() {
+        fn error<T: DeserializeOwned + JsonSchema + std::fmt::Debug>(arguments: Value) -> String {
+            let Value::Object(arguments) = arguments else {
+                panic!("arguments must be an object");
+            };
+            parse_params::<T>("some_tool", arguments)
+                .unwrap_err()
+                .to_string()
+        }
+
+        let message = error::<ManageExtensionsParams>(serde_json::json!({ "action": "enable" }));
+        assert!(message.starts_with(
+            "Invalid arguments for some_tool: missing field `extension_name`. \
+             Required fields: action, extension_name. Optional fields: "
+        ));
+        assert!(message.contains("max_tools"));
+
+        let required_name = [
+            error::<ListExtensionErrorsParams>(serde_json::json!({})),
+            error::<CancelExtensionOperationsParams>(serde_json::json!({})),
+            error::<SetExtensionAliasParams>(serde_json::json!({})),
+            error::<PreviewEnableImpactParams>(serde_json::json!({})),
+            error::<PingExtensionParams>(serde_json::json!({})),
+            error::<GetExtensionInstructionsParams>(serde_json::json!({})),
+        ];
+        for message in required_name {
+            assert!(
+                message
+                    .contains("missing field `extension_name`. Required fields: extension_name."),
+                "{}",
+                message
+            );
+        }
+
+        for (message, required) in [
+            (
+                error::<ValidateConfigsParams>(serde_json::json!({})),
+                "configs",
+            ),
+            (error::<FetchResultParams>(serde_json::json!({})), "handle"),
+            (error::<ToolBaselineParams>(serde_json::json!({})), "name"),
+            (error::<WatchResourceParams>(serde_json::json!({})), "uri"),
+            (
+                error::<WriteResourceParams>(serde_json::json!({ "uri": "file:///a" })),
+                "uri, content",
+            ),
+            (error::<ResourceHashParams>(serde_json::json!({})), "uri"),
+            (
+                error::<ReadMultipleResourcesParams>(serde_json::json!({ "uris": "file:///a" })),
+                "uris",
+            ),
+        ] {
+            assert!(
+                message.contains(&format!("Required fields: {}.", required)),
+                "{}",
+                message
+            );
+        }
+
+        for (message, optional) in [
+            (
+                error::<SearchAvailableExtensionsParams>(serde_json::json!({ "query": 1 })),
+                "query",
+            ),
+            (
+                error::<IdleExtensionsParams>(serde_json::json!({ "idle_minutes": "ten" })),
+                "idle_minutes",
+            ),
+            (
+                error::<SetToolBudgetParams>(serde_json::json!({ "limit": -1 })),
+                "limit",
+            ),
+            (
+                error::<ReadResourceParams>(serde_json::json!({ "index": "first" })),
+                "index",
+            ),
+            (
+                error::<ReadResourceParams>(serde_json::json!({ "uri": "a", "chunk_bytes": -1 })),
+                "chunk_bytes",
+            ),
+            (
+                error::<ListResourcesParams>(serde_json::json!({ "limit": "ten" })),
+                "limit",
+            ),
+        ] {
+            assert!(message.contains("Required fields: none."), "{}", message);
+            assert!(message.contains(optional), "{}", message);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_with_resource_timeout() {
+        let error = with_resource_timeout(
+            "read resource",
+            10,
+            std::future::pending::<Result<Vec<Content>, ErrorData>>(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Extension operation failed: Failed to read resource: timed out after 10 ms"
+        );
+
+        let content = with_resource_timeout("read resource", 10, async {
+            Ok(vec![Content::text("done")])
+        })
+        .await
+        .unwrap();
+        assert_eq!(content.len(), 1);
+    }
+
+    #[test]
+    fn test_closest_extension_name() {
+        let candidates = vec![
+            "computercontroller".to_string(),
+            "developer".to_string(),
+            "memory".to_string(),
+        ];
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(
+            closest_extension_name("developers", &candidates),
+            Some("developer")
+        );
+        assert_eq!(
+            closest_extension_name("Memroy", &candidates),
+            Some("memory")
+        );
+        assert_eq!(closest_extension_name("slack", &candidates), None);
+    }
+
+    #[test]
+    fn test_is_retryable_enable_error() {
+        let stdio = ExtensionConfig::Stdio {
+            name: "local".to_string(),
+            description: String::new(),
+            cmd: "missing-binary".to_string(),
+            args: vec![],
+            envs: Default::default(),
+            env_keys: vec![],
+            timeout: None,
+            bundled: None,
+            available_tools: vec![],
+        };
+        let remote = ExtensionConfig::StreamableHttp {
+            name: "remote".to_string(),
+            description: String::new(),
+            uri: "https://example.com/mcp".to_string(),
+            envs: Default::default(),
+            env_keys: vec![],
+            headers: HashMap::new(),
+            timeout: None,
+            bundled: None,
+            available_tools: vec![],
+        };
+        let missing_binary = || {
+            ExtensionError::IoError(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "no such file",
+            ))
+        };
+        let connection_failed = || ExtensionError::SetupError("connection reset".to_string());
+
+        assert!(!is_retryable_enable_error(&stdio, &missing_binary()));
+        assert!(is_retryable_enable_error(&stdio, &connection_failed()));
+        assert!(is_retryable_enable_error(&remote, &connection_failed()));
+        assert!(!is_retryable_enable_error(
+            &remote,
+            &ExtensionError::ConfigError("invalid uri".to_string())
+        ));
+    }
+
+    #[test]
+    fn test_dependency_chain() {
+        let graph = |name: &str| -> Vec<String> {
+            match name {
+                "git_review" => vec!["developer".to_string(), "github".to_string()],
+                "github" => vec!["developer".to_string()],
+                "a" => vec!["b".to_string()],
+                "b" => vec!["a".to_string()],
+                _ => vec![],
+            }
+        };
+
+        assert_eq!(
+            dependency_chain("git_review", &graph).unwrap(),
+            vec!["developer", "github", "git_review"]
+        );
+        assert_eq!(dependency_chain("memory", &graph).unwrap(), vec!["memory"]);
+        assert_eq!(dependency_chain("a", &graph).unwrap_err(), "a -> b -> a");
+    }
+
+    #[tokio::test]
+    async fn test_failed_enable_rolls_back_its_dependencies() {
+        let (_temp_dir, extension_manager, client) = test_client();
+        extension_manager
+            .add_extension(
+                ExtensionConfig::Platform {
+                    name: "todo".to_string(),
+                    description: "todo".to_string(),
+                    display_name: None,
+                    bundled: None,
+                    available_tools: vec![],
+                },
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let error = client
+            .roll_back_dependencies(
+                &extension_manager,
+                &["todo".to_string()],
+                ErrorData::new(ErrorCode::INTERNAL_ERROR, "startup failed", None),
+            )
+            .await;
+
+        assert_eq!(
+            error.message,
+            "startup failed (disabled the dependencies it had enabled: todo)"
+        );
+        assert!(!extension_manager.is_extension_enabled("todo").await);
+    }
+
+    #[test]
+    fn test_tool_names_round_trip() {
+        for tool in ExtensionManagerTool::ALL {
+            assert_eq!(tool.as_str().parse::<ExtensionManagerTool>().unwrap(), tool);
+        }
+        assert!(matches!(
+            "read_resources".parse::<ExtensionManagerTool>(),
+            Err(ExtensionManagerToolError::UnknownTool { .. })
+        ));
+    }
+
+    #[test]
+    fn test_tool_schemas_cover_every_tool() {
+        let names: Vec<String> = tool_schemas()
+            .into_iter()
+            .map(|tool| tool.name.to_string())
+            .collect();
+        assert!(names.is_sorted());
+        let mut expected: Vec<String> = ExtensionManagerTool::ALL
+            .iter()
+            .map(|tool| tool.as_str().to_string())
+            .collect();
+        expected.sort();
+        assert_eq!(names, expected);
+    }
+
+    #[test]
+    fn test_preview_enable_impact_is_not_read_only() {
+        // The preview starts the extension, so it must not be treated as a pure read
+        let preview = tool_schemas()
+            .into_iter()
+            .find(|tool| tool.name == PREVIEW_ENABLE_IMPACT_TOOL_NAME)
+            .unwrap();
+        let annotations = preview.annotations.unwrap();
+        assert_eq!(annotations.read_only_hint, Some(false));
+        assert_eq!(annotations.open_world_hint, Some(true));
+        assert!(ToolRateLimiter::new(ToolRateLimit {
+            burst: 1,
+            per_minute: 1,
+        })
+        .limited_tools
+        .contains(PREVIEW_ENABLE_IMPACT_TOOL_NAME));
+    }
+
+    #[test]
+    fn test_diff_tool_baseline() {
+        let baseline: ToolBaseline = [
+            ("ext__kept".to_string(), signature("same", "a")),
+            ("ext__removed".to_string(), signature("gone", "b")),
+            ("ext__changed".to_string(), signature("old", "c")),
+        ]
+        .into_iter()
+        .collect();
+        let current: ToolBaseline = [
+            ("ext__kept".to_string(), signature("same", "a")),
+            ("ext__changed".to_string(), signature("new", "d")),
+            ("ext__added".to_string(), signature("fresh", "e")),
+        ]
+        .into_iter()
+        .collect();
+
+        let diff = diff_tool_baseline(&baseline, &current);
+        assert_eq!(diff.added, vec!["ext__added".to_string()]);
+        assert_eq!(diff.removed, vec!["ext__removed".to_string()]);
+        assert_eq!(
+            diff.changed,
+            vec![("ext__changed".to_string(), vec!["description", "schema"])]
+        );
+
+        assert_eq!(
+            diff_tool_baseline(&baseline, &baseline),
+            ToolBaselineDiff::default()
+        );
+    }
+
+    #[test]
+    fn test_enabled_result_reports_load_time_and_added_tools() {
+        let result = enabled_result(
+            "developer",
+            std::time::Duration::from_millis(1300),
+            vec![
+                "developer__shell".to_string(),
+                "developer__edit".to_string(),
+            ],
+            vec![Content::text("Hidden by allow_tools/deny_tools: x")],
+        );
+
+        assert_eq!(result.content.len(), 2);
+        assert_eq!(
+            result.content[0].as_text().unwrap().text,
+            "The extension 'developer' has been installed successfully in 1.3s, adding 2 \
+             tool(s): developer__shell, developer__edit"
+        );
+        assert_eq!(
+            result.structured_content,
+            Some(serde_json::json!({
+                "action": "enable",
+                "extension_name": "developer",
+                "success": true,
+                "tools_added": ["developer__shell", "developer__edit"],
+                "load_time_ms": 1300,
+            }))
+        );
+
+        let result = enabled_result("empty", std::time::Duration::ZERO, vec![], vec![]);
+        assert_eq!(
+            result.content[0].as_text().unwrap().text,
+            "The extension 'empty' has been installed successfully in 0.0s, adding 0 tool(s)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_manage_extensions_reports_error_code() {
+        let (_temp_dir, _extension_manager, client) = test_client();
+
+        let result = client
+            .call_tool(
+                "test-session-id",
+                MANAGE_EXTENSIONS_TOOL_NAME,
+                serde_json::json!({
+                    "action": "enable",
+                    "extension_name": "no-such-extension-configured",
+                })
+                .as_object()
+                .cloned(),
+                None,
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(result.is_error, Some(true));
+        let structured = result.structured_content.unwrap();
+        assert_eq!(structured["error_code"], ErrorCode::RESOURCE_NOT_FOUND.0);
+        assert!(structured["message"]
+            .as_str()
+            .unwrap()
+            .contains("no-such-extension-configured"));
+    }
+
+    #[tokio::test]
+    async fn test_idle_extensions_accepts_huge_windows() {
+        let (_temp_dir, _extension_manager, client) = test_client();
+
+        let result = client
+            .call_tool(
+                "test-session-id",
+                IDLE_EXTENSIONS_TOOL_NAME,
+                serde_json::json!({ "idle_minutes": u64::MAX })
+                    .as_object()
+                    .cloned(),
+                None,
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        assert_ne!(result.is_error, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_disable_all_extensions_keeps_platform_extensions() {
+        let (_temp_dir, extension_manager, client) = test_client();
+        extension_manager
+            .add_extension(
+                ExtensionConfig::Platform {
+                    name: "todo".to_string(),
+                    description: "todo".to_string(),
+                    display_name: None,
+                    bundled: None,
+                    available_tools: vec![],
+                },
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let result = client
+            .call_tool(
+                "test-session-id",
+                DISABLE_ALL_EXTENSIONS_TOOL_NAME,
+                None,
+                None,
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+
+        assert_ne!(result.is_error, Some(true));
+        assert_eq!(
+            result.content[0].as_text().unwrap().text,
+            "No extensions were disabled."
+        );
+        assert!(extension_manager.is_extension_enabled("todo").await);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_stops_repeated_state_changes() {
+        let (_temp_dir, _extension_manager, client) = test_client();
+        let mut context = client.context.clone();
+        context.tool_rate_limit = Some(ToolRateLimit {
+            burst: 2,
+            per_minute: 1,
+        });
+        let client = ExtensionManagerClient::new(context).unwrap();
+
+        let call = |name: &'static str| {
+            let client = &client;
+            async move {
+                let arguments = serde_json::json!({
+                    "action": "enable",
+                    "extension_name": "no-such-extension-configured",
+                });
+                let result = client
+                    .call_tool(
+                        "test-session-id",
+                        name,
+                        arguments.as_object().cloned(),
+                        None,
+                        CancellationToken::new(),
+                    )
+                    .await
+                    .unwrap();
+                result.content[0].as_text().unwrap().text.clone()
+            }
+        };
+
+        for _ in 0..2 {
+            assert!(!call(MANAGE_EXTENSIONS_TOOL_NAME)
+                .await
+                .contains("called too often"));
+        }
+        let limited = call(MANAGE_EXTENSIONS_TOOL_NAME).await;
+        assert!(
+            limited.starts_with("manage_extensions was called too often"),
+            "{}",
+            limited
+        );
+
+        // Other tools have their own buckets, and read-only ones aren't limited
+        assert!(!call(DISABLE_ALL_EXTENSIONS_TOOL_NAME)
+            .await
+            .contains("called too often"));
+        for _ in 0..3 {
+            assert!(!call(LIST_ENABLED_EXTENSIONS_TOOL_NAME)
+                .await
+                .contains("called too often"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_disable_reason_is_audited() {
+        let (_temp_dir, extension_manager, client) = test_client();
+        extension_manager
+            .add_extension(
+                ExtensionConfig::Platform {
+                    name: "todo".to_string(),
+                    description: "todo".to_string(),
+                    display_name: None,
+                    bundled: None,
+                    available_tools: vec![],
+                },
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let audit_log = |session_id: &'static str| {
+            let client = &client;
+            async move {
+                let result = client
+                    .call_tool(
+                        session_id,
+                        GET_EXTENSION_AUDIT_LOG_TOOL_NAME,
+                        None,
+                        None,
+                        CancellationToken::new(),
+                    )
+                    .await
+                    .unwrap();
+                result.content[0].as_text().unwrap().text.clone()
+            }
+        };
+        assert_eq!(
+            audit_log("test-session-id").await,
+            "No extensions have been enabled or disabled in this session"
+        );
+
+        let result = client
+            .call_tool(
+                "test-session-id",
+                MANAGE_EXTENSIONS_TOOL_NAME,
+                serde_json::json!({
+                    "action": "disable",
+                    "extension_name": "todo",
+                    "reason": "not needed for this task",
+                })
+                .as_object()
+                .cloned(),
+                None,
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        assert_ne!(result.is_error, Some(true));
+
+        let log = audit_log("test-session-id").await;
+        assert!(
+            log.ends_with(" disabled todo: not needed for this task"),
+            "{}",
+            log
+        );
+        assert_eq!(log.lines().count(), 1);
+        assert_eq!(
+            audit_log("other-session").await,
+            "No extensions have been enabled or disabled in this session"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_restore_extensions_reverts_to_snapshot() {
+        let (_temp_dir, extension_manager, client) = test_client();
+
+        let before = client.enabled_extension_names().await;
+        extension_manager
+            .add_extension(
+                ExtensionConfig::Platform {
+                    name: "todo".to_string(),
+                    description: "todo".to_string(),
+                    display_name: None,
+                    bundled: None,
+                    available_tools: vec![],
+                },
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        client.record_snapshot(before).await;
+
+        let restore = || {
+            client.call_tool(
+                "test-session-id",
+                RESTORE_EXTENSIONS_TOOL_NAME,
+                None,
+                None,
+                CancellationToken::new(),
+            )
+        };
+        let result = restore().await.unwrap();
+        assert_eq!(
+            result.content[0].as_text().unwrap().text,
+            "Restored the previous extension set: disabled todo"
+        );
+        assert!(!extension_manager.is_extension_enabled("todo").await);
+
+        let result = restore().await.unwrap();
+        assert_eq!(
+            result.content[0].as_text().unwrap().text,
+            "There are no earlier extension changes to restore"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_disabling_a_disabled_extension_is_a_no_op() {
+        let (_temp_dir, _extension_manager, client) = test_client();
+
+        let result = client
+            .call_tool(
+                "test-session-id",
+                MANAGE_EXTENSIONS_TOOL_NAME,
+                serde_json::json!({ "action": "disable", "extension_name": "developer" })
+                    .as_object()
+                    .cloned(),
+                None,
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+
+        assert_ne!(result.is_error, Some(true));
+        assert_eq!(
+            result.content[0].as_text().unwrap().text,
+            "The extension 'developer' is already disabled"
+        );
+        assert_eq!(
+            result.structured_content,
+            Some(serde_json::json!({
+                "action": "disable",
+                "extension_name": "developer",
+                "success": true,
+                "tools_added": [],
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_has_no_prompts_of_its_own() {
+        let (_temp_dir, _extension_manager, client) = test_client();
+
+        let prompts = client
+            .list_prompts("test-session-id", None, CancellationToken::new())
+            .await
+            .unwrap();
+        assert!(prompts.prompts.is_empty());
+
+        let error = client
+            .get_prompt(
+                "test-session-id",
+                "review",
+                Value::Null,
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(error, ServiceError::McpError(ref data) if data.code == ErrorCode::INVALID_PARAMS)
+        );
+    }
+
+    #[test]
+    fn test_redacted_config_hides_secrets() {
+        let config = ExtensionConfig::StreamableHttp {
+            name: "remote".to_string(),
+            description: String::new(),
+            uri: "https://example.com/mcp?token=secret".to_string(),
+            envs: crate::agents::extension::Envs::new(
+                [("API_KEY".to_string(), "secret".to_string())].into(),
+            ),
+            env_keys: vec!["OTHER_KEY".to_string()],
+            headers: [("Authorization".to_string(), "Bearer secret".to_string())].into(),
+            timeout: None,
+            bundled: None,
+            available_tools: vec![],
+        };
+
+        let value = redacted_config(&config);
+        assert!(!value.to_string().contains("secret"));
+        assert_eq!(value["envs"]["API_KEY"], REDACTED);
+        assert_eq!(value["headers"]["Authorization"], REDACTED);
+        assert_eq!(
+            value["uri"],
+            format!("https://example.com/mcp?{}", REDACTED)
+        );
+        assert_eq!(value["env_keys"][0], "OTHER_KEY");
+    }
+
+    #[tokio::test]
+    async fn test_enabled_config_keeps_variable_placeholders() {
+        let fixture = goose_test_support::McpFixture::new(None).await;
+        let variables = serde_json::json!({ "FIXTURE_MCP_URL": fixture.url }).to_string();
+        let (_temp_dir, extension_manager, client) = {
+            let _guard =
+                env_lock::lock_env([("GOOSE_EXTENSION_VARIABLES", Some(variables.as_str()))]);
+            test_client()
+        };
+
+        let config = ExtensionConfig::StreamableHttp {
+            name: "fixture".to_string(),
+            description: String::new(),
+            uri: "${FIXTURE_MCP_URL}".to_string(),
+            envs: Default::default(),
+            env_keys: vec![],
+            headers: HashMap::new(),
+            timeout: Some(30),
+            bundled: None,
+            available_tools: vec![],
+        };
+        client
+            .enable_extension(&extension_manager, config.clone(), None, Some(0))
+            .await
+            .unwrap();
+
+        // The extension connected through the resolved URI...
+        let tools = extension_manager
+            .get_prefixed_tools("test-session-id", Some("fixture".to_string()))
+            .await
+            .unwrap();
+        assert!(tools.iter().any(|tool| tool.name == "fixture__get_code"));
+        // ...but what gets persisted with the session still has the placeholder
+        assert_eq!(
+            extension_manager.get_extension_configs().await,
+            vec![config]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_extension_leaves_variable_placeholders_alone() {
+        let fixture = goose_test_support::McpFixture::new(None).await;
+        let variables = serde_json::json!({ "FIXTURE_MCP_URL": fixture.url }).to_string();
+        let (_temp_dir, extension_manager, _client) = {
+            let _guard =
+                env_lock::lock_env([("GOOSE_EXTENSION_VARIABLES", Some(variables.as_str()))]);
+            test_client()
+        };
+
+        // Configs from recipes, deeplinks or the server connect as given, so a placeholder
+        // can't pull a local variable out to the extension
+        let config = ExtensionConfig::StreamableHttp {
+            name: "fixture".to_string(),
+            description: String::new(),
+            uri: "${FIXTURE_MCP_URL}".to_string(),
+            envs: Default::default(),
+            env_keys: vec![],
+            headers: HashMap::new(),
+            timeout: Some(30),
+            bundled: None,
+            available_tools: vec![],
+        };
+        assert!(extension_manager
+            .add_extension(config, None, None, None)
+            .await
+            .is_err());
+        assert!(extension_manager.get_extension_configs().await.is_empty());
+    }
+}
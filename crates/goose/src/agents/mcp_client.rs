@@ -10,14 +10,16 @@ use rmcp::{
     model::{
         CallToolRequest, CallToolRequestParams, CallToolResult, CancelledNotification,
         CancelledNotificationMethod, CancelledNotificationParam, ClientCapabilities, ClientInfo,
-        ClientRequest, CreateMessageRequestParams, CreateMessageResult, GetPromptRequest,
-        GetPromptRequestParams, GetPromptResult, Implementation, InitializeResult,
-        ListPromptsRequest, ListPromptsResult, ListResourcesRequest, ListResourcesResult,
-        ListToolsRequest, ListToolsResult, LoggingMessageNotification,
+        ClientRequest, CompleteRequestParams, CompleteResult, CreateMessageRequestParams,
+        CreateMessageResult, CustomRequest, GetPromptRequest, GetPromptRequestParams,
+        GetPromptResult, Implementation, InitializeResult, ListPromptsRequest, ListPromptsResult,
+        ListResourceTemplatesRequest, ListResourceTemplatesResult, ListResourcesRequest,
+        ListResourcesResult, ListToolsRequest, ListToolsResult, LoggingMessageNotification,
         LoggingMessageNotificationMethod, PaginatedRequestParams, ProgressNotification,
         ProgressNotificationMethod, ProtocolVersion, ReadResourceRequest,
-        ReadResourceRequestParams, ReadResourceResult, RequestId, Role, SamplingMessage,
-        ServerNotification, ServerResult,
+        ReadResourceRequestParams, ReadResourceResult, RequestId, ResourceUpdatedNotification,
+        ResourceUpdatedNotificationMethod, Role, SamplingMessage, ServerNotification, ServerResult,
+        SubscribeRequest, SubscribeRequestParams, UnsubscribeRequest, UnsubscribeRequestParams,
     },
     service::{
         ClientInitializeError, PeerRequestOptions, RequestContext, RequestHandle, RunningService,
@@ -37,6 +39,11 @@ pub type BoxError = Box<dyn std::error::Error + Sync + Send>;
 
 pub type Error = rmcp::ServiceError;
 
+/// MCP has no standard way to write a resource. Servers that accept writes advertise this
+/// method as an experimental capability and handle it as a custom request with `uri` and
+/// `text` params.
+pub const WRITE_RESOURCE_METHOD: &str = "resources/write";
+
 #[async_trait::async_trait]
 pub trait McpClientTrait: Send + Sync {
     async fn list_tools(
@@ -66,6 +73,16 @@ pub trait McpClientTrait: Send + Sync {
         Err(Error::TransportClosed)
     }
 
+    /// The server's parameterized resources, whose URIs are RFC 6570 templates
+    async fn list_resource_templates(
+        &self,
+        _session_id: &str,
+        _next_cursor: Option<String>,
+        _cancel_token: CancellationToken,
+    ) -> Result<ListResourceTemplatesResult, Error> {
+        Err(Error::TransportClosed)
+    }
+
     async fn read_resource(
         &self,
         _session_id: &str,
@@ -75,6 +92,37 @@ pub trait McpClientTrait: Send + Sync {
         Err(Error::TransportClosed)
     }
 
+    /// Ask the server to send a resource updated notification, through `subscribe`, whenever
+    /// the resource changes
+    async fn subscribe_resource(
+        &self,
+        _session_id: &str,
+        _uri: &str,
+        _cancel_token: CancellationToken,
+    ) -> Result<(), Error> {
+        Err(Error::TransportClosed)
+    }
+
+    async fn unsubscribe_resource(
+        &self,
+        _session_id: &str,
+        _uri: &str,
+        _cancel_token: CancellationToken,
+    ) -> Result<(), Error> {
+        Err(Error::TransportClosed)
+    }
+
+    /// Write `text` to a resource, returning the server's response as is
+    async fn write_resource(
+        &self,
+        _session_id: &str,
+        _uri: &str,
+        _text: &str,
+        _cancel_token: CancellationToken,
+    ) -> Result<Value, Error> {
+        Err(Error::TransportClosed)
+    }
+
     async fn list_prompts(
         &self,
         _session_id: &str,
@@ -94,6 +142,15 @@ pub trait McpClientTrait: Send + Sync {
         Err(Error::TransportClosed)
     }
 
+    async fn complete(
+        &self,
+        _session_id: &str,
+        _request: CompleteRequestParams,
+        _cancel_token: CancellationToken,
+    ) -> Result<CompleteResult, Error> {
+        Err(Error::TransportClosed)
+    }
+
     async fn subscribe(&self) -> mpsc::Receiver<ServerNotification> {
         mpsc::channel(1).1
     }
@@ -194,6 +251,26 @@ impl ClientHandler for GooseClient {
             });
     }
 
+    async fn on_resource_updated(
+        &self,
+        params: rmcp::model::ResourceUpdatedNotificationParam,
+        context: rmcp::service::NotificationContext<rmcp::RoleClient>,
+    ) {
+        self.notification_handlers
+            .lock()
+            .await
+            .iter()
+            .for_each(|handler| {
+                let _ = handler.try_send(ServerNotification::ResourceUpdatedNotification(
+                    ResourceUpdatedNotification {
+                        params: params.clone(),
+                        method: ResourceUpdatedNotificationMethod,
+                        extensions: context.extensions.clone(),
+                    },
+                ));
+            });
+    }
+
     async fn create_message(
         &self,
         params: CreateMessageRequestParams,
@@ -493,6 +570,31 @@ impl McpClientTrait for McpClient {
         }
     }
 
+    async fn list_resource_templates(
+        &self,
+        session_id: &str,
+        cursor: Option<String>,
+        cancel_token: CancellationToken,
+    ) -> Result<ListResourceTemplatesResult, Error> {
+        let res = self
+            .send_request_with_context(
+                session_id,
+                None,
+                ClientRequest::ListResourceTemplatesRequest(ListResourceTemplatesRequest {
+                    params: Some(PaginatedRequestParams { meta: None, cursor }),
+                    method: Default::default(),
+                    extensions: Default::default(),
+                }),
+                cancel_token,
+            )
+            .await?;
+
+        match res {
+            ServerResult::ListResourceTemplatesResult(result) => Ok(result),
+            _ => Err(ServiceError::UnexpectedResponse),
+        }
+    }
+
     async fn read_resource(
         &self,
         session_id: &str,
@@ -521,6 +623,85 @@ impl McpClientTrait for McpClient {
         }
     }
 
+    async fn subscribe_resource(
+        &self,
+        session_id: &str,
+        uri: &str,
+        cancel_token: CancellationToken,
+    ) -> Result<(), Error> {
+        let res = self
+            .send_request_with_context(
+                session_id,
+                None,
+                ClientRequest::SubscribeRequest(SubscribeRequest {
+                    params: SubscribeRequestParams {
+                        meta: None,
+                        uri: uri.to_string(),
+                    },
+                    method: Default::default(),
+                    extensions: Default::default(),
+                }),
+                cancel_token,
+            )
+            .await?;
+
+        match res {
+            ServerResult::EmptyResult(_) => Ok(()),
+            _ => Err(ServiceError::UnexpectedResponse),
+        }
+    }
+
+    async fn unsubscribe_resource(
+        &self,
+        session_id: &str,
+        uri: &str,
+        cancel_token: CancellationToken,
+    ) -> Result<(), Error> {
+        let res = self
+            .send_request_with_context(
+                session_id,
+                None,
+                ClientRequest::UnsubscribeRequest(UnsubscribeRequest {
+                    params: UnsubscribeRequestParams {
+                        meta: None,
+                        uri: uri.to_string(),
+                    },
+                    method: Default::default(),
+                    extensions: Default::default(),
+                }),
+                cancel_token,
+            )
+            .await?;
+
+        match res {
+            ServerResult::EmptyResult(_) => Ok(()),
+            _ => Err(ServiceError::UnexpectedResponse),
+        }
+    }
+
+    async fn write_resource(
+        &self,
+        session_id: &str,
+        uri: &str,
+        text: &str,
+        cancel_token: CancellationToken,
+    ) -> Result<Value, Error> {
+        let res = self
+            .send_request_with_context(
+                session_id,
+                None,
+                ClientRequest::CustomRequest(CustomRequest::new(
+                    WRITE_RESOURCE_METHOD,
+                    Some(serde_json::json!({ "uri": uri, "text": text })),
+                )),
+                cancel_token,
+            )
+            .await?;
+
+        // The result has no fixed shape, so it may parse as any result type; pass it on as JSON
+        serde_json::to_value(res).map_err(|_| ServiceError::UnexpectedResponse)
+    }
+
     async fn list_tools(
         &self,
         session_id: &str,
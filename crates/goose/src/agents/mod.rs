@@ -2,10 +2,12 @@ mod agent;
 pub(crate) mod apps_extension;
 mod builtin_skills;
 pub(crate) mod chatrecall_extension;
+pub mod circuit_breaker;
 pub(crate) mod code_execution_extension;
 pub mod container;
 pub mod execute_commands;
 pub mod extension;
+pub mod extension_errors;
 pub mod extension_malware_check;
 pub mod extension_manager;
 pub mod extension_manager_extension;
@@ -14,8 +16,10 @@ mod large_response_handler;
 pub mod mcp_client;
 pub mod moim;
 pub mod platform_tools;
+pub mod priority_semaphore;
 pub mod prompt_manager;
 mod reply_parts;
+pub mod resource_template;
 pub mod retry;
 mod schedule_tool;
 pub(crate) mod skills_extension;
@@ -0,0 +1,185 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+/// Priority of a tool call when waiting for a permit on a busy extension.
+///
+/// Calls to a single extension are serialized, so when several are waiting the highest
+/// priority goes first, and calls with the same priority keep their arrival order.
+/// Priority only orders calls waiting on the same extension; calls to different extensions
+/// never wait on each other. The default is `Normal`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolCallPriority {
+    /// Batch or background work that can wait behind everything else
+    Background,
+    #[default]
+    Normal,
+    /// Calls the user is actively waiting on
+    Interactive,
+}
+
+struct Waiter {
+    priority: ToolCallPriority,
+    seq: u64,
+    tx: oneshot::Sender<PriorityPermit>,
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Waiter {}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Waiter {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Max-heap: higher priority first, then lower sequence number (earlier arrival)
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+struct State {
+    available: usize,
+    next_seq: u64,
+    waiters: BinaryHeap<Waiter>,
+}
+
+/// A counting semaphore that hands out permits to waiters in priority order
+pub struct PrioritySemaphore {
+    state: Mutex<State>,
+}
+
+/// Held while a call runs; releasing it passes the permit to the next waiter
+pub struct PriorityPermit {
+    semaphore: Option<Arc<PrioritySemaphore>>,
+}
+
+impl Drop for PriorityPermit {
+    fn drop(&mut self) {
+        if let Some(semaphore) = self.semaphore.take() {
+            semaphore.release();
+        }
+    }
+}
+
+impl PrioritySemaphore {
+    pub fn new(permits: usize) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(State {
+                available: permits,
+                next_seq: 0,
+                waiters: BinaryHeap::new(),
+            }),
+        })
+    }
+
+    pub async fn acquire(self: &Arc<Self>, priority: ToolCallPriority) -> PriorityPermit {
+        let rx = {
+            let mut state = self.state.lock().expect("semaphore state poisoned");
+            if state.available > 0 && state.waiters.is_empty() {
+                state.available -= 1;
+                return PriorityPermit {
+                    semaphore: Some(Arc::clone(self)),
+                };
+            }
+
+            let (tx, rx) = oneshot::channel();
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.waiters.push(Waiter { priority, seq, tx });
+            rx
+        };
+
+        // The sender is only dropped after handing over a permit or when the semaphore
+        // itself goes away, which cannot happen while we hold an Arc to it.
+        rx.await.expect("priority semaphore dropped while waiting")
+    }
+
+    fn release(self: &Arc<Self>) {
+        let mut state = self.state.lock().expect("semaphore state poisoned");
+        while let Some(waiter) = state.waiters.pop() {
+            let permit = PriorityPermit {
+                semaphore: Some(Arc::clone(self)),
+            };
+            match waiter.tx.send(permit) {
+                Ok(()) => return,
+                // The waiter gave up; disarm the permit so it doesn't release again
+                Err(mut permit) => permit.semaphore = None,
+            }
+        }
+        state.available += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_higher_priority_waiter_goes_first() {
+        let semaphore = PrioritySemaphore::new(1);
+        let held = semaphore.acquire(ToolCallPriority::Normal).await;
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut handles = Vec::new();
+        for (label, priority) in [
+            ("background", ToolCallPriority::Background),
+            ("normal", ToolCallPriority::Normal),
+            ("interactive", ToolCallPriority::Interactive),
+        ] {
+            let semaphore = Arc::clone(&semaphore);
+            let order = Arc::clone(&order);
+            handles.push(tokio::spawn(async move {
+                let _permit = semaphore.acquire(priority).await;
+                order.lock().unwrap().push(label);
+            }));
+            // Make sure each waiter is queued before the next one
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        drop(held);
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        assert_eq!(
+            *order.lock().unwrap(),
+            vec!["interactive", "normal", "background"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_waiter_does_not_leak_permit() {
+        let semaphore = PrioritySemaphore::new(1);
+        let held = semaphore.acquire(ToolCallPriority::Normal).await;
+
+        let waiting = tokio::time::timeout(
+            Duration::from_millis(10),
+            semaphore.acquire(ToolCallPriority::Interactive),
+        )
+        .await;
+        assert!(waiting.is_err());
+
+        drop(held);
+        let reacquired = tokio::time::timeout(
+            Duration::from_millis(100),
+            semaphore.acquire(ToolCallPriority::Normal),
+        )
+        .await;
+        assert!(reacquired.is_ok());
+    }
+}
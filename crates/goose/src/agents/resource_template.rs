@@ -0,0 +1,365 @@
+use std::collections::{BTreeSet, HashMap};
+
+use thiserror::Error;
+
+/// Problems expanding an RFC 6570 URI template
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TemplateError {
+    #[error("Invalid URI template '{template}': {reason}")]
+    Malformed { template: String, reason: String },
+    #[error("Missing values for required template variables: {}", .0.join(", "))]
+    MissingVariables(Vec<String>),
+    #[error("Unknown template variables: {}", .0.join(", "))]
+    UnknownVariables(Vec<String>),
+}
+
+/// A variable named in a template expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateVariable {
+    pub name: String,
+    /// Path-style expressions (`{x}`, `{+x}`, `{#x}`, `{.x}`, `{/x}`) can't be left out without
+    /// changing what the URI points at, so their variables are required. Query-style ones
+    /// (`{?x}`, `{&x}`, `{;x}`) are dropped from the URI when unset.
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Simple,
+    Reserved,
+    Fragment,
+    Label,
+    Path,
+    PathParam,
+    Query,
+    QueryContinuation,
+}
+
+impl Operator {
+    fn parse(c: char) -> Option<Self> {
+        match c {
+            '+' => Some(Self::Reserved),
+            '#' => Some(Self::Fragment),
+            '.' => Some(Self::Label),
+            '/' => Some(Self::Path),
+            ';' => Some(Self::PathParam),
+            '?' => Some(Self::Query),
+            '&' => Some(Self::QueryContinuation),
+            _ => None,
+        }
+    }
+
+    /// What goes before the first value, between values, whether values are `name=value`
+    /// pairs, and what follows the name for an empty value
+    fn style(self) -> (&'static str, &'static str, bool, &'static str) {
+        match self {
+            Self::Simple | Self::Reserved => ("", ",", false, ""),
+            Self::Fragment => ("#", ",", false, ""),
+            Self::Label => (".", ".", false, ""),
+            Self::Path => ("/", "/", false, ""),
+            Self::PathParam => (";", ";", true, ""),
+            Self::Query => ("?", "&", true, "="),
+            Self::QueryContinuation => ("&", "&", true, "="),
+        }
+    }
+
+    fn allows_reserved(self) -> bool {
+        matches!(self, Self::Reserved | Self::Fragment)
+    }
+
+    fn required(self) -> bool {
+        !matches!(
+            self,
+            Self::PathParam | Self::Query | Self::QueryContinuation
+        )
+    }
+}
+
+#[derive(Debug)]
+struct VarSpec {
+    name: String,
+    /// Keep only this many characters of the value, from `{var:3}`
+    prefix: Option<usize>,
+}
+
+#[derive(Debug)]
+enum Part {
+    Literal(String),
+    Expression {
+        operator: Operator,
+        variables: Vec<VarSpec>,
+    },
+}
+
+fn parse(template: &str) -> Result<Vec<Part>, TemplateError> {
+    let malformed = |reason: String| TemplateError::Malformed {
+        template: template.to_string(),
+        reason,
+    };
+
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while let Some((literal, after)) = rest.split_once('{') {
+        if literal.contains('}') {
+            return Err(malformed("'}' without a matching '{'".to_string()));
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal.to_string()));
+        }
+        let (mut expression, remaining) = after
+            .split_once('}')
+            .ok_or_else(|| malformed("unclosed '{'".to_string()))?;
+        let mut chars = expression.chars();
+        let operator = match chars.next().and_then(Operator::parse) {
+            Some(operator) => {
+                expression = chars.as_str();
+                operator
+            }
+            None => Operator::Simple,
+        };
+
+        let mut variables = Vec::new();
+        for spec in expression.split(',') {
+            // Values are plain strings, so exploding a list or map changes nothing
+            let spec = spec.strip_suffix('*').unwrap_or(spec);
+            let (name, prefix) = match spec.split_once(':') {
+                Some((name, length)) => {
+                    let length = length
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|length| (1..10000).contains(length))
+                        .ok_or_else(|| {
+                            malformed(format!("invalid prefix length in '{{{}}}'", spec))
+                        })?;
+                    (name, Some(length))
+                }
+                None => (spec, None),
+            };
+            let valid = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '%');
+            if !valid {
+                return Err(malformed(format!("invalid variable name '{}'", name)));
+            }
+            variables.push(VarSpec {
+                name: name.to_string(),
+                prefix,
+            });
+        }
+        parts.push(Part::Expression {
+            operator,
+            variables,
+        });
+        rest = remaining;
+    }
+    if rest.contains('}') {
+        return Err(malformed("'}' without a matching '{'".to_string()));
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Literal(rest.to_string()));
+    }
+    Ok(parts)
+}
+
+/// The variables a template uses, in order of first appearance
+pub fn template_variables(template: &str) -> Result<Vec<TemplateVariable>, TemplateError> {
+    let mut variables: Vec<TemplateVariable> = Vec::new();
+    for part in parse(template)? {
+        let Part::Expression {
+            operator,
+            variables: specs,
+        } = part
+        else {
+            continue;
+        };
+        for spec in specs {
+            match variables.iter_mut().find(|v| v.name == spec.name) {
+                Some(variable) => variable.required |= operator.required(),
+                None => variables.push(TemplateVariable {
+                    name: spec.name,
+                    required: operator.required(),
+                }),
+            }
+        }
+    }
+    Ok(variables)
+}
+
+fn encode(value: &str, allow_reserved: bool) -> String {
+    const RESERVED: &str = ":/?#[]@!$&'()*+,;=";
+    let mut encoded = String::with_capacity(value.len());
+    let bytes = value.as_bytes();
+    for (i, &byte) in bytes.iter().enumerate() {
+        let c = byte as char;
+        let is_triplet = allow_reserved
+            && byte == b'%'
+            && bytes.get(i + 1).is_some_and(u8::is_ascii_hexdigit)
+            && bytes.get(i + 2).is_some_and(u8::is_ascii_hexdigit);
+        let unreserved = byte.is_ascii_alphanumeric() || (byte.is_ascii() && "-._~".contains(c));
+        let reserved = allow_reserved && byte.is_ascii() && RESERVED.contains(c);
+        if unreserved || reserved || is_triplet {
+            encoded.push(c);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Expand an RFC 6570 URI template with string values. Every required variable must have a
+/// value, and every value must name a variable the template uses.
+pub fn expand_template(
+    template: &str,
+    values: &HashMap<String, String>,
+) -> Result<String, TemplateError> {
+    let parts = parse(template)?;
+
+    let variables = template_variables(template)?;
+    let missing: Vec<String> = variables
+        .iter()
+        .filter(|variable| variable.required && !values.contains_key(&variable.name))
+        .map(|variable| variable.name.clone())
+        .collect();
+    if !missing.is_empty() {
+        return Err(TemplateError::MissingVariables(missing));
+    }
+    let known: BTreeSet<&str> = variables.iter().map(|v| v.name.as_str()).collect();
+    let unknown: BTreeSet<String> = values
+        .keys()
+        .filter(|name| !known.contains(name.as_str()))
+        .cloned()
+        .collect();
+    if !unknown.is_empty() {
+        return Err(TemplateError::UnknownVariables(
+            unknown.into_iter().collect(),
+        ));
+    }
+
+    let mut uri = String::new();
+    for part in parts {
+        match part {
+            Part::Literal(literal) => uri.push_str(&encode(&literal, true)),
+            Part::Expression {
+                operator,
+                variables,
+            } => {
+                let (first, separator, named, if_empty) = operator.style();
+                let mut expanded = Vec::new();
+                for spec in variables {
+                    let Some(value) = values.get(&spec.name) else {
+                        continue;
+                    };
+                    let value: String = match spec.prefix {
+                        Some(length) => value.chars().take(length).collect(),
+                        None => value.clone(),
+                    };
+                    let value = encode(&value, operator.allows_reserved());
+                    expanded.push(match (named, value.is_empty()) {
+                        (false, _) => value,
+                        (true, true) => format!("{}{}", spec.name, if_empty),
+                        (true, false) => format!("{}={}", spec.name, value),
+                    });
+                }
+                if !expanded.is_empty() {
+                    uri.push_str(first);
+                    uri.push_str(&expanded.join(separator));
+                }
+            }
+        }
+    }
+    Ok(uri)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_expand_template_operators() {
+        let vars = values(&[
+            ("var", "value"),
+            ("hello", "Hello World!"),
+            ("path", "/foo/bar"),
+            ("x", "1024"),
+            ("y", "768"),
+            ("empty", ""),
+        ]);
+        let cases = [
+            ("{var}", "value"),
+            ("{hello}", "Hello%20World%21"),
+            ("{+hello}", "Hello%20World!"),
+            ("{+path}/here", "/foo/bar/here"),
+            ("{#path}", "#/foo/bar"),
+            ("X{.var}", "X.value"),
+            ("{/var,x}/here", "/value/1024/here"),
+            ("{;x,y,empty}", ";x=1024;y=768;empty"),
+            ("{?x,y,empty}", "?x=1024&y=768&empty="),
+            ("?fixed=yes{&x}", "?fixed=yes&x=1024"),
+            ("{var:3}", "val"),
+        ];
+        for (template, expected) in cases {
+            let used: HashMap<String, String> = template_variables(template)
+                .unwrap()
+                .into_iter()
+                .map(|v| (v.name.clone(), vars[&v.name].clone()))
+                .collect();
+            assert_eq!(
+                expand_template(template, &used).unwrap(),
+                expected,
+                "{}",
+                template
+            );
+        }
+    }
+
+    #[test]
+    fn test_expand_template_validates_variables() {
+        let template = "file:///{+path}{?rev,lines}";
+        assert_eq!(
+            template_variables(template).unwrap(),
+            vec![
+                TemplateVariable {
+                    name: "path".to_string(),
+                    required: true
+                },
+                TemplateVariable {
+                    name: "rev".to_string(),
+                    required: false
+                },
+                TemplateVariable {
+                    name: "lines".to_string(),
+                    required: false
+                },
+            ]
+        );
+
+        assert_eq!(
+            expand_template(template, &values(&[("path", "src/main.rs")])).unwrap(),
+            "file:///src/main.rs"
+        );
+        assert_eq!(
+            expand_template(template, &values(&[("rev", "abc")])),
+            Err(TemplateError::MissingVariables(vec!["path".to_string()]))
+        );
+        assert_eq!(
+            expand_template(template, &values(&[("path", "a"), ("paht", "b")])),
+            Err(TemplateError::UnknownVariables(vec!["paht".to_string()]))
+        );
+        assert!(matches!(
+            expand_template("file:///{path", &HashMap::new()),
+            Err(TemplateError::Malformed { .. })
+        ));
+        assert!(matches!(
+            template_variables("file:///{pa th}"),
+            Err(TemplateError::Malformed { .. })
+        ));
+    }
+}
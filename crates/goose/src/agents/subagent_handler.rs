@@ -271,6 +271,7 @@ async fn run_subagent_stream(
                 conversation.push(msg);
             }
             Ok(AgentEvent::McpNotification(_)) => {}
+            Ok(AgentEvent::ExtensionNotification(_)) => {}
             Ok(AgentEvent::ModelChange { .. }) => {}
             Ok(AgentEvent::HistoryReplaced(updated_conversation)) => {
                 conversation = updated_conversation;
@@ -3,6 +3,7 @@ use crate::agents::ExtensionConfig;
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use serde_yaml::Mapping;
+use std::collections::HashMap;
 use tracing::warn;
 use utoipa::ToSchema;
 
@@ -11,10 +12,16 @@ pub const DEFAULT_EXTENSION_TIMEOUT: u64 = 300;
 pub const DEFAULT_EXTENSION_DESCRIPTION: &str = "";
 pub const DEFAULT_DISPLAY_NAME: &str = "Developer";
 const EXTENSIONS_CONFIG_KEY: &str = "extensions";
+/// Other names for configured extensions, e.g. `dev: developer`
+const EXTENSION_ALIASES_CONFIG_KEY: &str = "extension_aliases";
 
 #[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
 pub struct ExtensionEntry {
     pub enabled: bool,
+    /// Names of extensions that have to be enabled for this one to work; enabling this
+    /// extension through the extension manager enables them first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
     #[serde(flatten)]
     pub config: ExtensionConfig,
 }
@@ -73,12 +80,43 @@ fn save_extensions_map(extensions: IndexMap<String, ExtensionEntry>) {
     }
 }
 
+/// Look up a configured extension by name, preferring an exact match, then one that differs
+/// only in casing or spacing (so "Developer" finds "developer"), then an alias from the
+/// `extension_aliases` config. Use the returned config's `name()` as the canonical name.
 pub fn get_extension_by_name(name: &str) -> Option<ExtensionConfig> {
-    let extensions = get_extensions_map();
+    let aliases: HashMap<String, String> = Config::global()
+        .get_param(EXTENSION_ALIASES_CONFIG_KEY)
+        .unwrap_or_default();
+    find_extension(&get_extensions_map(), &aliases, name)
+}
+
+fn find_extension(
+    extensions: &IndexMap<String, ExtensionEntry>,
+    aliases: &HashMap<String, String>,
+    name: &str,
+) -> Option<ExtensionConfig> {
+    let normalized = |name: &str| {
+        let key = name_to_key(name);
+        extensions
+            .iter()
+            .find(|(key_in_config, entry)| {
+                name_to_key(&entry.config.name()) == key || name_to_key(key_in_config) == key
+            })
+            .map(|(_key, entry)| entry.config.clone())
+    };
+
     extensions
         .values()
         .find(|entry| entry.config.name() == name)
         .map(|entry| entry.config.clone())
+        .or_else(|| normalized(name))
+        .or_else(|| {
+            let key = name_to_key(name);
+            aliases
+                .iter()
+                .find(|(alias, _target)| name_to_key(alias) == key)
+                .and_then(|(_alias, target)| normalized(target))
+        })
 }
 
 pub fn set_extension(entry: ExtensionEntry) {
@@ -168,3 +206,53 @@ pub fn resolve_extensions_for_new_session(
 
     get_enabled_extensions()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(key: &str, name: &str) -> (String, ExtensionEntry) {
+        (
+            key.to_string(),
+            ExtensionEntry {
+                enabled: true,
+                depends_on: vec![],
+                config: ExtensionConfig::Builtin {
+                    name: name.to_string(),
+                    display_name: None,
+                    description: String::new(),
+                    timeout: None,
+                    bundled: None,
+                    available_tools: vec![],
+                },
+            },
+        )
+    }
+
+    #[test]
+    fn test_find_extension_by_casing_and_alias() {
+        let extensions: IndexMap<String, ExtensionEntry> = [
+            entry("developer", "developer"),
+            entry("developer_legacy", "Developer"),
+            entry("computercontroller", "Computer Controller"),
+        ]
+        .into_iter()
+        .collect();
+        let aliases = HashMap::from([
+            ("Dev".to_string(), "DEVELOPER".to_string()),
+            ("ghost".to_string(), "not-configured".to_string()),
+        ]);
+        let find = |name: &str| find_extension(&extensions, &aliases, name).map(|c| c.name());
+
+        // An exact match wins over one that only differs in casing
+        assert_eq!(find("Developer").as_deref(), Some("Developer"));
+        assert_eq!(find("DEVELOPER").as_deref(), Some("developer"));
+        assert_eq!(
+            find("computer controller").as_deref(),
+            Some("Computer Controller")
+        );
+        assert_eq!(find("dev").as_deref(), Some("developer"));
+        assert_eq!(find("ghost"), None);
+        assert_eq!(find("unknown"), None);
+    }
+}
@@ -65,6 +65,7 @@ fn migrate_platform_extensions(config: &mut Mapping) -> bool {
                     available_tools: Vec::new(),
                 },
                 enabled,
+                depends_on: Vec::new(),
             };
 
             if let Ok(value) = serde_yaml::to_value(&new_entry) {
@@ -108,6 +109,7 @@ mod tests {
                 available_tools: Vec::new(),
             },
             enabled: false,
+            depends_on: Vec::new(),
         };
         extensions.insert(
             serde_yaml::Value::String("todo".to_string()),
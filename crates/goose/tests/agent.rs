@@ -475,6 +475,7 @@ mod tests {
                         responses.push(response);
                     }
                     Ok(AgentEvent::McpNotification(_)) => {}
+                    Ok(AgentEvent::ExtensionNotification(_)) => {}
                     Ok(AgentEvent::ModelChange { .. }) => {}
                     Ok(AgentEvent::HistoryReplaced(_updated_conversation)) => {
                         // We should update the conversation here, but we're not reading it
@@ -524,6 +525,7 @@ mod tests {
             // Set it as disabled initially so tests can enable it
             let todo_extension_entry = ExtensionEntry {
                 enabled: false,
+                depends_on: Vec::new(),
                 config: ExtensionConfig::Platform {
                     name: "todo".to_string(),
                     description: